@@ -0,0 +1,137 @@
+use crate::database::asset::AssetId;
+use crate::error::ServerError;
+use rusqlite::Transaction as SqlTransaction;
+use sea_query::{enum_def, Expr, OnConflict, Order, Query, SqliteQueryBuilder};
+use sea_query_rusqlite::RusqliteBinder;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// an asset of which dividends are reinvested in `account`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[enum_def]
+pub struct AccountDrip {
+    pub account: Uuid,
+    pub asset: AssetId,
+}
+
+impl AccountDrip {
+    pub fn new(account: Uuid, asset: AssetId) -> Self {
+        Self { account, asset }
+    }
+
+    pub fn by_account(
+        account: Uuid,
+        transaction: &SqlTransaction,
+    ) -> Result<Vec<AssetId>, ServerError> {
+        let (query, values) = Query::select()
+            .column(AccountDripIden::Asset)
+            .from(AccountDripIden::Table)
+            .and_where(Expr::col(AccountDripIden::Account).eq(account))
+            .order_by(AccountDripIden::Asset, Order::Asc)
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let record: Result<Vec<_>, rusqlite::Error> = statement
+            .query_and_then(&*values.as_params(), |row| row.get(0))?
+            .collect();
+
+        Ok(record?)
+    }
+
+    // does nothing if the asset is flagged already
+    pub fn insert(
+        &self,
+        transaction: &SqlTransaction,
+    ) -> Result<(), ServerError> {
+        let (query, values) = Query::insert()
+            .into_table(AccountDripIden::Table)
+            .columns([AccountDripIden::Account, AccountDripIden::Asset])
+            .values([self.account.into(), self.asset.clone().into()])?
+            .on_conflict(
+                OnConflict::columns([
+                    AccountDripIden::Account,
+                    AccountDripIden::Asset,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .build_rusqlite(SqliteQueryBuilder);
+
+        transaction.execute(&query, &*values.as_params())?;
+        Ok(())
+    }
+
+    pub fn delete(
+        &self,
+        transaction: &SqlTransaction,
+    ) -> Result<(), ServerError> {
+        let (query, values) = Query::delete()
+            .from_table(AccountDripIden::Table)
+            .and_where(Expr::col(AccountDripIden::Account).eq(self.account))
+            .and_where(Expr::col(AccountDripIden::Asset).eq(self.asset.clone()))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        transaction.execute(&query, &*values.as_params())?;
+        Ok(())
+    }
+
+    // unflags every asset of `account`
+    pub fn clear(
+        account: Uuid,
+        transaction: &SqlTransaction,
+    ) -> Result<(), ServerError> {
+        let (query, values) = Query::delete()
+            .from_table(AccountDripIden::Table)
+            .and_where(Expr::col(AccountDripIden::Account).eq(account))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        transaction.execute(&query, &*values.as_params())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::account::{Account, AccountKind};
+    use crate::database::{self, User};
+    use rusqlite::Connection;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_drip() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let tran = conn.transaction()?;
+        database::migration::run_migration(&tran)?;
+        let mut u0 = User::new(
+            String::from("test_user"),
+            Sha256::digest("password").to_vec(),
+        );
+        u0.id = u0.insert(&tran)?;
+        let mut a0 =
+            Account::new("test_account", "alias", u0.id, AccountKind::NRA);
+        a0.id = a0.insert(&tran)?;
+
+        let xeqt = AccountDrip::new(a0.id, AssetId::stock("TSE", "XEQT"));
+        let vfv = AccountDrip::new(a0.id, AssetId::stock("TSE", "VFV"));
+        xeqt.insert(&tran)?;
+        xeqt.insert(&tran)?;
+        vfv.insert(&tran)?;
+        assert_eq!(
+            vec![vfv.asset.clone(), xeqt.asset.clone()],
+            AccountDrip::by_account(a0.id, &tran)?
+        );
+
+        vfv.delete(&tran)?;
+        assert_eq!(
+            vec![xeqt.asset.clone()],
+            AccountDrip::by_account(a0.id, &tran)?
+        );
+
+        // flags go along with the account
+        Account::delete(a0.id, &tran)?;
+        assert!(AccountDrip::by_account(a0.id, &tran)?.is_empty());
+
+        Ok(())
+    }
+}
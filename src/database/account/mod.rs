@@ -1,7 +1,9 @@
+mod drip;
 mod kind;
 
 use crate::error::ServerError;
 use core::str;
+pub use drip::AccountDrip;
 pub use kind::AccountKind;
 use rusqlite::{Row, Transaction as SqlTransaction};
 use sea_query::{enum_def, Expr, IdenStatic, Query, SqliteQueryBuilder};
@@ -120,7 +122,7 @@ impl Account {
         transaction: &SqlTransaction,
     ) -> Result<(), ServerError> {
         {
-            use super::transaction::{Transaction, TransactionIden};
+            use super::transaction::{TransactionIden, Transfer};
             let (query, values) = Query::select()
                 .columns([TransactionIden::Id])
                 .from(TransactionIden::Table)
@@ -129,10 +131,12 @@ impl Account {
             let mut statement = transaction.prepare(&query)?;
             statement
                 .query_and_then(&*values.as_params(), |row| row.get(0))?
+                // the other leg of a transfer goes along
                 .try_for_each(|x: Result<Uuid, _>| {
-                    Transaction::delete(x?, &transaction)
+                    Transfer::delete(x?, &transaction)
                 })?;
         }
+        AccountDrip::clear(id, transaction)?;
 
         let (query, values) = Query::delete()
             .from_table(AccountIden::Table)
@@ -1,12 +1,15 @@
-use super::AssetId;
+use super::{AssetId, AssetIden};
+use crate::error::ServerError;
 use chrono::NaiveDate;
-use rusqlite::types::FromSqlError;
-use rusqlite::{Connection, Row, RowIndex};
-use rust_decimal::prelude::FromPrimitive;
+use rusqlite::{Row, Transaction as SqlTransaction};
 use rust_decimal::Decimal;
-use sea_query::{enum_def, IdenStatic};
+use sea_query::{
+    enum_def, Alias, Asterisk, Cond, Expr, IdenStatic, Order, Query,
+    SelectStatement, SqliteQueryBuilder,
+};
+use sea_query_rusqlite::RusqliteBinder;
 use serde::{Deserialize, Serialize};
-use std::usize;
+use std::collections::HashMap;
 use uuid::Uuid;
 
 pub enum AssetUpdateKind {
@@ -41,12 +44,210 @@ impl TryFrom<&Row<'_>> for AssetPrice {
 }
 
 impl AssetPrice {
-    pub fn asset(&self, connection: &mut Connection) -> Option<super::Asset> {
-        match super::Asset::by_id(self.asset, connection) {
+    pub fn asset(&self, transaction: &SqlTransaction) -> Option<super::Asset> {
+        match super::Asset::by_id(self.asset, transaction) {
             Ok(Some(asset)) => Some(asset),
             _ => None,
         }
     }
+
+    // prices of `assets`, searching global assets and the ones owned by
+    // `owner`.
+    fn select(assets: &[AssetId], owner: Option<Uuid>) -> SelectStatement {
+        Query::select()
+            .columns([
+                (AssetPriceIden::Table, AssetPriceIden::Asset),
+                (AssetPriceIden::Table, AssetPriceIden::Date),
+                (AssetPriceIden::Table, AssetPriceIden::Price),
+                (AssetPriceIden::Table, AssetPriceIden::Currency),
+            ])
+            .from(AssetPriceIden::Table)
+            .inner_join(
+                AssetIden::Table,
+                Expr::col((AssetIden::Table, AssetIden::Id))
+                    .equals((AssetPriceIden::Table, AssetPriceIden::Asset)),
+            )
+            .and_where(
+                Expr::col((AssetIden::Table, AssetIden::AssetId))
+                    .is_in(assets.iter().cloned()),
+            )
+            .cond_where(
+                Cond::any()
+                    .add(
+                        Expr::col((AssetIden::Table, AssetIden::Owner))
+                            .is_null(),
+                    )
+                    .add_option(owner.map(|x| {
+                        Expr::col((AssetIden::Table, AssetIden::Owner)).eq(x)
+                    })),
+            )
+            .to_owned()
+    }
+
+    // order among the prices of the same date, the one of a private asset
+    // of the owner first then by currency.
+    fn tie_break(select: &mut SelectStatement) -> &mut SelectStatement {
+        select
+            .order_by_expr(
+                Expr::col((AssetIden::Table, AssetIden::Owner)).is_null(),
+                Order::Asc,
+            )
+            .order_by(
+                (AssetPriceIden::Table, AssetPriceIden::Currency),
+                Order::Asc,
+            )
+    }
+
+    // latest price of `asset` on or before `date`, quoted in `currency` if
+    // given.
+    pub fn latest(
+        asset: &AssetId,
+        owner: Option<Uuid>,
+        currency: Option<&AssetId>,
+        date: NaiveDate,
+        transaction: &SqlTransaction,
+    ) -> Result<Option<AssetPrice>, ServerError> {
+        let mut select = Self::select(std::slice::from_ref(asset), owner)
+            .and_where_option(currency.map(|x| {
+                Expr::col((AssetPriceIden::Table, AssetPriceIden::Currency))
+                    .eq(x.clone())
+            }))
+            .and_where(
+                Expr::col((AssetPriceIden::Table, AssetPriceIden::Date))
+                    .lte(date),
+            )
+            .order_by(
+                (AssetPriceIden::Table, AssetPriceIden::Date),
+                Order::Desc,
+            )
+            .to_owned();
+        let (query, values) = Self::tie_break(&mut select)
+            .limit(1)
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let record: Option<Result<_, rusqlite::Error>> = statement
+            .query_and_then(&*values.as_params(), |row| {
+                AssetPrice::try_from(row)
+            })?
+            .next();
+
+        Ok(record.transpose()?)
+    }
+
+    // stores prices of the asset with row id `asset`, replacing the ones on
+    // the same date and currency.
+    pub fn insert(
+        asset: Uuid,
+        data: &[(NaiveDate, Decimal, AssetId)],
+        transaction: &SqlTransaction,
+    ) -> Result<(), ServerError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder = Query::insert()
+            .replace()
+            .into_table(AssetPriceIden::Table)
+            .columns([
+                AssetPriceIden::Asset,
+                AssetPriceIden::Date,
+                AssetPriceIden::Price,
+                AssetPriceIden::Currency,
+            ])
+            .to_owned();
+        for (date, price, currency) in data {
+            builder.values([
+                asset.into(),
+                (*date).into(),
+                price.serialize()[..].into(),
+                currency.clone().into(),
+            ])?;
+        }
+        let (query, values) = builder.build_rusqlite(SqliteQueryBuilder);
+
+        transaction.execute(&query, &*values.as_params())?;
+        Ok(())
+    }
+
+    // latest price of each of `assets` on or before `date` in one query,
+    // assets without any price are left out.
+    pub fn latest_batch(
+        assets: &[AssetId],
+        owner: Option<Uuid>,
+        currency: Option<&AssetId>,
+        date: NaiveDate,
+        transaction: &SqlTransaction,
+    ) -> Result<HashMap<AssetId, AssetPrice>, ServerError> {
+        // prices ranked per asset in the order of `latest`, only the first
+        // one of each asset is read
+        let rank = Alias::new("rank");
+        let prices = Self::select(assets, owner)
+            .column((AssetIden::Table, AssetIden::AssetId))
+            .expr_as(
+                Expr::cust(
+                    "ROW_NUMBER() OVER (PARTITION BY asset.asset_id \
+                     ORDER BY asset_price.date DESC, asset.owner IS NULL, \
+                     asset_price.currency)",
+                ),
+                rank.clone(),
+            )
+            .and_where_option(currency.map(|x| {
+                Expr::col((AssetPriceIden::Table, AssetPriceIden::Currency))
+                    .eq(x.clone())
+            }))
+            .and_where(
+                Expr::col((AssetPriceIden::Table, AssetPriceIden::Date))
+                    .lte(date),
+            )
+            .to_owned();
+        let (query, values) = Query::select()
+            .column(Asterisk)
+            .from_subquery(prices, Alias::new("prices"))
+            .and_where(Expr::col(rank).eq(1))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let record: Result<HashMap<_, _>, rusqlite::Error> = statement
+            .query_and_then(&*values.as_params(), |row| {
+                Ok((
+                    row.get::<_, AssetId>(AssetIden::AssetId.as_str())?,
+                    AssetPrice::try_from(row)?,
+                ))
+            })?
+            .collect();
+
+        Ok(record?)
+    }
+
+    // every stored price of `asset` in date order, the prices of a date
+    // are in the reverse order of `latest` so that the last one wins
+    pub fn history(
+        asset: &AssetId,
+        owner: Option<Uuid>,
+        transaction: &SqlTransaction,
+    ) -> Result<Vec<AssetPrice>, ServerError> {
+        let (query, values) = Self::select(std::slice::from_ref(asset), owner)
+            .order_by((AssetPriceIden::Table, AssetPriceIden::Date), Order::Asc)
+            .order_by_expr(
+                Expr::col((AssetIden::Table, AssetIden::Owner)).is_null(),
+                Order::Desc,
+            )
+            .order_by(
+                (AssetPriceIden::Table, AssetPriceIden::Currency),
+                Order::Desc,
+            )
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let record: Result<Vec<_>, rusqlite::Error> = statement
+            .query_and_then(&*values.as_params(), |row| {
+                AssetPrice::try_from(row)
+            })?
+            .collect();
+
+        Ok(record?)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -64,21 +265,282 @@ impl TryFrom<&Row<'_>> for AssetDividend {
 
     fn try_from(value: &Row<'_>) -> Result<Self, Self::Error> {
         Ok(Self {
-            asset: value.get(AssetPriceIden::Asset.as_str())?,
-            date: value.get(AssetPriceIden::Date.as_str())?,
+            asset: value.get(AssetDividendIden::Asset.as_str())?,
+            date: value.get(AssetDividendIden::Date.as_str())?,
             dividend: Decimal::deserialize(
-                value.get(AssetPriceIden::Price.as_str())?,
+                value.get(AssetDividendIden::Dividend.as_str())?,
             ),
-            currency: value.get(AssetPriceIden::Currency.as_str())?,
+            currency: value.get(AssetDividendIden::Currency.as_str())?,
         })
     }
 }
 
 impl AssetDividend {
-    pub fn asset(&self, connection: &mut Connection) -> Option<super::Asset> {
-        match super::Asset::by_id(self.asset, connection) {
+    pub fn asset(&self, transaction: &SqlTransaction) -> Option<super::Asset> {
+        match super::Asset::by_id(self.asset, transaction) {
+            Ok(Some(asset)) => Some(asset),
+            _ => None,
+        }
+    }
+
+    // every dividend per share of `asset` in date order, searching global
+    // assets and the ones owned by `owner`.
+    pub fn history(
+        asset: &AssetId,
+        owner: Option<Uuid>,
+        transaction: &SqlTransaction,
+    ) -> Result<Vec<AssetDividend>, ServerError> {
+        let (query, values) = Query::select()
+            .columns([
+                (AssetDividendIden::Table, AssetDividendIden::Asset),
+                (AssetDividendIden::Table, AssetDividendIden::Date),
+                (AssetDividendIden::Table, AssetDividendIden::Dividend),
+                (AssetDividendIden::Table, AssetDividendIden::Currency),
+            ])
+            .from(AssetDividendIden::Table)
+            .inner_join(
+                AssetIden::Table,
+                Expr::col((AssetIden::Table, AssetIden::Id)).equals((
+                    AssetDividendIden::Table,
+                    AssetDividendIden::Asset,
+                )),
+            )
+            .and_where(
+                Expr::col((AssetIden::Table, AssetIden::AssetId))
+                    .eq(asset.clone()),
+            )
+            .cond_where(
+                Cond::any()
+                    .add(
+                        Expr::col((AssetIden::Table, AssetIden::Owner))
+                            .is_null(),
+                    )
+                    .add_option(owner.map(|x| {
+                        Expr::col((AssetIden::Table, AssetIden::Owner)).eq(x)
+                    })),
+            )
+            .order_by(
+                (AssetDividendIden::Table, AssetDividendIden::Date),
+                Order::Asc,
+            )
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let record: Result<Vec<_>, rusqlite::Error> = statement
+            .query_and_then(&*values.as_params(), |row| {
+                AssetDividend::try_from(row)
+            })?
+            .collect();
+
+        Ok(record?)
+    }
+}
+
+// `ratio` new shares for every old share, e.g. 2 for a 2-for-1 split and
+// 0.1 for a 1-for-10 reverse split.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[enum_def]
+pub struct AssetSplit {
+    #[serde(default)]
+    pub asset: Uuid,
+    pub date: NaiveDate,
+    pub ratio: Decimal,
+}
+
+impl TryFrom<&Row<'_>> for AssetSplit {
+    type Error = rusqlite::Error;
+
+    fn try_from(value: &Row<'_>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            asset: value.get(AssetSplitIden::Asset.as_str())?,
+            date: value.get(AssetSplitIden::Date.as_str())?,
+            ratio: Decimal::deserialize(
+                value.get(AssetSplitIden::Ratio.as_str())?,
+            ),
+        })
+    }
+}
+
+impl AssetSplit {
+    pub fn asset(&self, transaction: &SqlTransaction) -> Option<super::Asset> {
+        match super::Asset::by_id(self.asset, transaction) {
             Ok(Some(asset)) => Some(asset),
             _ => None,
         }
     }
+
+    // every split of `asset` in date order, searching global assets and the
+    // ones owned by `owner`.
+    pub fn history(
+        asset: &AssetId,
+        owner: Option<Uuid>,
+        transaction: &SqlTransaction,
+    ) -> Result<Vec<AssetSplit>, ServerError> {
+        let (query, values) = Query::select()
+            .columns([
+                (AssetSplitIden::Table, AssetSplitIden::Asset),
+                (AssetSplitIden::Table, AssetSplitIden::Date),
+                (AssetSplitIden::Table, AssetSplitIden::Ratio),
+            ])
+            .from(AssetSplitIden::Table)
+            .inner_join(
+                AssetIden::Table,
+                Expr::col((AssetIden::Table, AssetIden::Id))
+                    .equals((AssetSplitIden::Table, AssetSplitIden::Asset)),
+            )
+            .and_where(
+                Expr::col((AssetIden::Table, AssetIden::AssetId))
+                    .eq(asset.clone()),
+            )
+            .cond_where(
+                Cond::any()
+                    .add(
+                        Expr::col((AssetIden::Table, AssetIden::Owner))
+                            .is_null(),
+                    )
+                    .add_option(owner.map(|x| {
+                        Expr::col((AssetIden::Table, AssetIden::Owner)).eq(x)
+                    })),
+            )
+            .order_by((AssetSplitIden::Table, AssetSplitIden::Date), Order::Asc)
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let record: Result<Vec<_>, rusqlite::Error> = statement
+            .query_and_then(&*values.as_params(), |row| {
+                AssetSplit::try_from(row)
+            })?
+            .collect();
+
+        Ok(record?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Asset;
+    use super::*;
+    use crate::database::{self, User};
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    #[test]
+    fn test_latest() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let (xyz, abc) =
+            (AssetId::stock("TSE", "XYZ"), AssetId::stock("TSE", "ABC"));
+        let (cad, usd) = (AssetId::currency("CAD"), AssetId::currency("USD"));
+
+        let u0 = {
+            let tran = conn.transaction()?;
+            database::migration::run_migration(&tran)?;
+            let mut u0 =
+                User::new("test_user", Sha256::digest("password").to_vec());
+            u0.id = u0.insert(&tran)?;
+
+            let mut a0 = Asset::new(xyz.clone(), "XYZ", None);
+            a0.id = a0.insert(&tran)?;
+            a0.insert_price(
+                &[
+                    (date!(2020, 1, 1), dec!(10), cad.clone()),
+                    (date!(2020, 1, 3), dec!(11), cad.clone()),
+                    (date!(2020, 1, 2), dec!(8), usd.clone()),
+                    (date!(2020, 1, 3), dec!(9), usd.clone()),
+                ],
+                &tran,
+            )?;
+            // private assets of the user
+            let mut a1 = Asset::new(abc.clone(), "ABC", Some(u0.id));
+            a1.id = a1.insert(&tran)?;
+            a1.insert_price(
+                &[(date!(2020, 1, 2), dec!(5), cad.clone())],
+                &tran,
+            )?;
+            let mut a2 = Asset::new(xyz.clone(), "XYZ", Some(u0.id));
+            a2.id = a2.insert(&tran)?;
+            a2.insert_price(
+                &[(date!(2020, 1, 3), dec!(12), cad.clone())],
+                &tran,
+            )?;
+            tran.commit()?;
+            u0
+        };
+        {
+            let tran = conn.transaction()?;
+            let res =
+                AssetPrice::latest(&xyz, None, None, date!(2020, 1, 2), &tran)?
+                    .expect("no price");
+            assert_eq!((dec!(8), usd.clone()), (res.price, res.currency));
+            let res = AssetPrice::latest(
+                &xyz,
+                None,
+                Some(&cad),
+                date!(2020, 1, 2),
+                &tran,
+            )?
+            .expect("no price");
+            assert_eq!(dec!(10), res.price);
+            assert!(AssetPrice::latest(
+                &abc,
+                None,
+                None,
+                date!(2020, 1, 2),
+                &tran
+            )?
+            .is_none());
+
+            // same date, ordered by currency then the owner's price first
+            let res =
+                AssetPrice::latest(&xyz, None, None, date!(2020, 1, 3), &tran)?
+                    .expect("no price");
+            assert_eq!((dec!(11), cad.clone()), (res.price, res.currency));
+            let res = AssetPrice::latest(
+                &xyz,
+                Some(u0.id),
+                None,
+                date!(2020, 1, 3),
+                &tran,
+            )?
+            .expect("no price");
+            assert_eq!(dec!(12), res.price);
+
+            let res = AssetPrice::history(&xyz, None, &tran)?;
+            let prices: Vec<_> = res.iter().map(|x| x.price).collect();
+            assert_eq!(vec![dec!(10), dec!(8), dec!(9), dec!(11)], prices);
+            let res = AssetPrice::history(&xyz, Some(u0.id), &tran)?;
+            assert_eq!(Some(dec!(12)), res.last().map(|x| x.price));
+
+            let res = AssetPrice::latest_batch(
+                &[xyz.clone(), abc.clone(), AssetId::stock("TSE", "NONE")],
+                Some(u0.id),
+                Some(&cad),
+                date!(2020, 1, 5),
+                &tran,
+            )?;
+            assert_eq!(2, res.len());
+            assert_eq!(dec!(12), res[&xyz].price);
+            assert_eq!(dec!(5), res[&abc].price);
+
+            let res = AssetPrice::latest_batch(
+                std::slice::from_ref(&xyz),
+                None,
+                None,
+                date!(2020, 1, 5),
+                &tran,
+            )?;
+            assert_eq!(
+                (dec!(11), cad.clone()),
+                (res[&xyz].price, res[&xyz].currency.clone())
+            );
+        }
+
+        Ok(())
+    }
 }
@@ -2,7 +2,7 @@ use rusqlite::types::{FromSql, FromSqlError, ValueRef};
 use serde::de::Error;
 use serde::{Deserialize, Serialize, Serializer};
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub enum AssetId {
     // stock or ETF, anything tradable through stock exchanges
     STOCK { exchange: String, ticker: String },
@@ -1,17 +1,23 @@
 mod history;
 mod id;
-mod price;
 mod update;
 
+use super::transaction::TransactionIden;
 use crate::error::ServerError;
-use chrono::NaiveDate;
-use history::{AssetDividendIden, AssetPriceIden};
+use chrono::{DateTime, NaiveDate, Utc};
+pub use history::{AssetDividend, AssetPrice, AssetSplit};
+use history::{AssetDividendIden, AssetPriceIden, AssetSplitIden};
 pub use id::AssetId;
-use rusqlite::{Connection, Row};
+use rusqlite::{Row, Transaction as SqlTransaction};
 use rust_decimal::Decimal;
-use sea_query::{enum_def, Cond, Expr, IdenStatic, Query, SqliteQueryBuilder};
+use sea_query::{
+    enum_def, Alias, Cond, Expr, Func, IdenStatic, JoinType, Order, Query,
+    SqliteQueryBuilder,
+};
 use sea_query_rusqlite::RusqliteBinder;
 use serde::{Deserialize, Serialize};
+use update::AssetUpdate;
+pub use update::AssetUpdateKind;
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -60,17 +66,13 @@ impl Asset {
         }
     }
 
-    pub fn owner(&self, connection: &mut Connection) -> Option<super::User> {
-        if let Some(tran) = connection.transaction().ok() {
-            match self.owner {
-                Some(owner) => match super::User::by_id(owner, &tran) {
-                    Ok(Some(user)) => Some(user),
-                    _ => None,
-                },
+    pub fn owner(&self, transaction: &SqlTransaction) -> Option<super::User> {
+        match self.owner {
+            Some(owner) => match super::User::by_id(owner, transaction) {
+                Ok(Some(user)) => Some(user),
                 _ => None,
-            }
-        } else {
-            None
+            },
+            _ => None,
         }
     }
 }
@@ -78,7 +80,7 @@ impl Asset {
 impl Asset {
     pub fn by_id(
         id: Uuid,
-        connection: &mut Connection,
+        transaction: &SqlTransaction,
     ) -> Result<Option<Self>, ServerError> {
         let (query, values) = Query::select()
             .columns([
@@ -91,7 +93,7 @@ impl Asset {
             .and_where(Expr::col(AssetIden::Id).eq(id))
             .build_rusqlite(SqliteQueryBuilder);
 
-        let mut statement = connection.prepare(&query)?;
+        let mut statement = transaction.prepare(&query)?;
         let record: Option<Result<_, rusqlite::Error>> = statement
             .query_and_then(&*values.as_params(), |row| Asset::try_from(row))?
             .next();
@@ -102,7 +104,7 @@ impl Asset {
     pub fn by_asset(
         asset: AssetId,
         owner: Option<Uuid>,
-        connection: &mut Connection,
+        transaction: &SqlTransaction,
     ) -> Result<Option<Self>, ServerError> {
         let (query, values) = Query::select()
             .columns([
@@ -120,7 +122,7 @@ impl Asset {
             )
             .build_rusqlite(SqliteQueryBuilder);
 
-        let mut statement = connection.prepare(&query)?;
+        let mut statement = transaction.prepare(&query)?;
         let record: Option<Result<_, rusqlite::Error>> = statement
             .query_and_then(&*values.as_params(), |row| Asset::try_from(row))?
             .next();
@@ -130,7 +132,7 @@ impl Asset {
 
     pub fn by_owner(
         owner: Uuid,
-        connection: &mut Connection,
+        transaction: &SqlTransaction,
     ) -> Result<Vec<Self>, ServerError> {
         let (query, values) = Query::select()
             .columns([
@@ -143,7 +145,7 @@ impl Asset {
             .and_where(Expr::col(AssetIden::Owner).eq(owner))
             .build_rusqlite(SqliteQueryBuilder);
 
-        let mut statement = connection.prepare(&query)?;
+        let mut statement = transaction.prepare(&query)?;
         let record: Result<Vec<_>, rusqlite::Error> = statement
             .query_and_then(&*values.as_params(), |row| Asset::try_from(row))?
             .collect();
@@ -154,7 +156,7 @@ impl Asset {
     pub fn search(
         query: impl Into<String>,
         owner: Uuid,
-        connection: &mut Connection,
+        transaction: &SqlTransaction,
     ) -> Result<Vec<Self>, ServerError> {
         let (query, values) = Query::select()
             .columns([
@@ -176,7 +178,7 @@ impl Asset {
             .limit(10)
             .build_rusqlite(SqliteQueryBuilder);
 
-        let mut statement = connection.prepare(&query)?;
+        let mut statement = transaction.prepare(&query)?;
         let record: Result<Vec<_>, rusqlite::Error> = statement
             .query_and_then(&*values.as_params(), |row| Asset::try_from(row))?
             .collect();
@@ -184,9 +186,75 @@ impl Asset {
         Ok(record?)
     }
 
+    // catalog assets referenced by any transaction along with the date they
+    // were first traded
+    pub fn traded(
+        transaction: &SqlTransaction,
+    ) -> Result<Vec<(Self, NaiveDate)>, ServerError> {
+        let (leaf, traded, first) = (
+            Alias::new("leaf"),
+            Alias::new("traded"),
+            Alias::new("first"),
+        );
+        // assets are the string leaves of the action but its reason
+        let leaves = Query::select()
+            .expr_as(
+                Expr::col((leaf.clone(), Alias::new("atom"))),
+                AssetIden::AssetId,
+            )
+            .expr_as(
+                Expr::col((TransactionIden::Table, TransactionIden::Date))
+                    .min(),
+                first.clone(),
+            )
+            .from(TransactionIden::Table)
+            .from_function(
+                Func::cust(Alias::new("json_tree")).arg(Expr::col((
+                    TransactionIden::Table,
+                    TransactionIden::Action,
+                ))),
+                leaf.clone(),
+            )
+            .and_where(
+                Expr::col((leaf.clone(), Alias::new("key"))).is_not("reason"),
+            )
+            .and_where(Expr::col((leaf.clone(), Alias::new("type"))).eq("text"))
+            .group_by_col((leaf, Alias::new("atom")))
+            .to_owned();
+        let (query, values) = Query::select()
+            .columns([
+                (AssetIden::Table, AssetIden::Id),
+                (AssetIden::Table, AssetIden::AssetId),
+                (AssetIden::Table, AssetIden::Name),
+                (AssetIden::Table, AssetIden::Owner),
+            ])
+            .column((traded.clone(), first))
+            .from(AssetIden::Table)
+            .join_subquery(
+                JoinType::InnerJoin,
+                leaves,
+                traded.clone(),
+                Expr::col((AssetIden::Table, AssetIden::AssetId))
+                    .equals((traded, AssetIden::AssetId)),
+            )
+            .and_where(
+                Expr::col((AssetIden::Table, AssetIden::Owner)).is_null(),
+            )
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let record: Result<Vec<_>, rusqlite::Error> = statement
+            .query_and_then(&*values.as_params(), |row| {
+                Ok((Asset::try_from(row)?, row.get("first")?))
+            })?
+            .collect();
+
+        Ok(record?)
+    }
+
     pub fn insert(
         &self,
-        connection: &mut Connection,
+        transaction: &SqlTransaction,
     ) -> Result<Uuid, ServerError> {
         assert!(self.id.is_nil());
 
@@ -207,71 +275,75 @@ impl Asset {
             ])?
             .build_rusqlite(SqliteQueryBuilder);
 
-        connection.execute(&query, &*values.as_params())?;
+        transaction.execute(&query, &*values.as_params())?;
         Ok(id)
     }
 
     pub fn delete(
         id: Uuid,
-        connection: &mut Connection,
+        transaction: &SqlTransaction,
     ) -> Result<(), ServerError> {
-        // TODO: delete related tables
-        let (query1, values1) = Query::delete()
+        // delete related tables
+        let (query, values) = Query::delete()
+            .from_table(AssetPriceIden::Table)
+            .and_where(Expr::col(AssetPriceIden::Asset).eq(id))
+            .build_rusqlite(SqliteQueryBuilder);
+        transaction.execute(&query, &*values.as_params())?;
+
+        let (query, values) = Query::delete()
+            .from_table(AssetDividendIden::Table)
+            .and_where(Expr::col(AssetDividendIden::Asset).eq(id))
+            .build_rusqlite(SqliteQueryBuilder);
+        transaction.execute(&query, &*values.as_params())?;
+
+        let (query, values) = Query::delete()
+            .from_table(AssetSplitIden::Table)
+            .and_where(Expr::col(AssetSplitIden::Asset).eq(id))
+            .build_rusqlite(SqliteQueryBuilder);
+        transaction.execute(&query, &*values.as_params())?;
+
+        AssetUpdate::delete(id, transaction)?;
+
+        // delete asset
+        let (query, values) = Query::delete()
             .from_table(AssetIden::Table)
             .and_where(Expr::col(AssetIden::Id).eq(id))
             .build_rusqlite(SqliteQueryBuilder);
 
-        let transaction = connection.transaction()?;
-        transaction.execute(&query1, &*values1.as_params())?;
-        transaction.commit()?;
+        transaction.execute(&query, &*values.as_params())?;
         Ok(())
     }
 
     pub fn insert_price(
         &self,
-        data: &Vec<(NaiveDate, Decimal, AssetId)>,
-        connection: &mut Connection,
+        data: &[(NaiveDate, Decimal, AssetId)],
+        transaction: &SqlTransaction,
     ) -> Result<(), ServerError> {
         // TODO: update AssetUpdate datetime
-        let mut builder = Query::insert()
-            .replace()
-            .into_table(AssetPriceIden::Table)
-            .columns([
-                AssetPriceIden::Asset,
-                AssetPriceIden::Date,
-                AssetPriceIden::Price,
-                AssetPriceIden::Currency,
-            ])
-            .to_owned();
-        data.iter().for_each(|(date, price, currency)| {
-            _ = builder.values([
-                self.id.into(),
-                date.clone().into(),
-                price.serialize()[..].into(),
-                currency.clone().into(),
-            ]);
-        });
-        let (query, values) = builder.build_rusqlite(SqliteQueryBuilder);
-
-        connection.execute(&query, &*values.as_params())?;
-        Ok(())
+        AssetPrice::insert(self.id, data, transaction)
     }
 
+    // latest price on or before `date`, in `currency` if given
     pub fn price(
         &self,
         date: NaiveDate,
-        connection: &mut Connection,
+        currency: Option<&AssetId>,
+        transaction: &SqlTransaction,
     ) -> Result<Option<(Decimal, AssetId)>, ServerError> {
-        // TODO: issue, cannot get multi-currency price
         let (query, values) = Query::select()
             .columns([AssetPriceIden::Price, AssetPriceIden::Currency])
             .from(AssetPriceIden::Table)
             .and_where(Expr::col(AssetPriceIden::Asset).eq(self.id))
             .and_where(Expr::col(AssetPriceIden::Date).lte(date))
+            .and_where_option(
+                currency
+                    .map(|x| Expr::col(AssetPriceIden::Currency).eq(x.clone())),
+            )
+            .order_by(AssetPriceIden::Date, Order::Desc)
             .limit(1)
             .build_rusqlite(SqliteQueryBuilder);
 
-        let mut statement = connection.prepare(&query)?;
+        let mut statement = transaction.prepare(&query)?;
         let record: Option<Result<_, rusqlite::Error>> = statement
             .query_and_then(&*values.as_params(), |row| {
                 Ok((
@@ -286,11 +358,52 @@ impl Asset {
         Ok(record.transpose()?)
     }
 
+    // prices between `start` and `end` inclusively in date order, in
+    // `currency` if given
+    pub fn prices(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        currency: Option<&AssetId>,
+        transaction: &SqlTransaction,
+    ) -> Result<Vec<AssetPrice>, ServerError> {
+        let (query, values) = Query::select()
+            .columns([
+                AssetPriceIden::Asset,
+                AssetPriceIden::Date,
+                AssetPriceIden::Price,
+                AssetPriceIden::Currency,
+            ])
+            .from(AssetPriceIden::Table)
+            .and_where(Expr::col(AssetPriceIden::Asset).eq(self.id))
+            .and_where(Expr::col(AssetPriceIden::Date).gte(start))
+            .and_where(Expr::col(AssetPriceIden::Date).lte(end))
+            .and_where_option(
+                currency
+                    .map(|x| Expr::col(AssetPriceIden::Currency).eq(x.clone())),
+            )
+            .order_by(AssetPriceIden::Date, Order::Asc)
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let record: Result<Vec<_>, rusqlite::Error> = statement
+            .query_and_then(&*values.as_params(), |row| {
+                AssetPrice::try_from(row)
+            })?
+            .collect();
+
+        Ok(record?)
+    }
+
     pub fn insert_dividend(
         &self,
-        data: &Vec<(NaiveDate, Decimal, AssetId)>,
-        connection: &mut Connection,
+        data: &[(NaiveDate, Decimal, AssetId)],
+        transaction: &SqlTransaction,
     ) -> Result<(), ServerError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
         let mut builder = Query::insert()
             .replace()
             .into_table(AssetDividendIden::Table)
@@ -301,155 +414,326 @@ impl Asset {
                 AssetDividendIden::Currency,
             ])
             .to_owned();
-        data.iter().for_each(|(date, dividend, currency)| {
-            _ = builder.values([
+        for (date, dividend, currency) in data {
+            builder.values([
                 self.id.into(),
-                date.clone().into(),
+                (*date).into(),
                 dividend.serialize()[..].into(),
                 currency.clone().into(),
-            ]);
-        });
+            ])?;
+        }
         let (query, values) = builder.build_rusqlite(SqliteQueryBuilder);
 
-        connection.execute(&query, &*values.as_params())?;
+        transaction.execute(&query, &*values.as_params())?;
         Ok(())
     }
 
     pub fn insert_split(
         &self,
-        data: &Vec<(NaiveDate, Decimal)>,
+        data: &[(NaiveDate, Decimal)],
+        transaction: &SqlTransaction,
     ) -> Result<(), ServerError> {
-        todo!()
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder = Query::insert()
+            .replace()
+            .into_table(AssetSplitIden::Table)
+            .columns([
+                AssetSplitIden::Asset,
+                AssetSplitIden::Date,
+                AssetSplitIden::Ratio,
+            ])
+            .to_owned();
+        for (date, ratio) in data {
+            builder.values([
+                self.id.into(),
+                (*date).into(),
+                ratio.serialize()[..].into(),
+            ])?;
+        }
+        let (query, values) = builder.build_rusqlite(SqliteQueryBuilder);
+
+        transaction.execute(&query, &*values.as_params())?;
+        Ok(())
+    }
+
+    // every split in date order
+    pub fn splits(
+        &self,
+        transaction: &SqlTransaction,
+    ) -> Result<Vec<AssetSplit>, ServerError> {
+        let (query, values) = Query::select()
+            .columns([
+                AssetSplitIden::Asset,
+                AssetSplitIden::Date,
+                AssetSplitIden::Ratio,
+            ])
+            .from(AssetSplitIden::Table)
+            .and_where(Expr::col(AssetSplitIden::Asset).eq(self.id))
+            .order_by(AssetSplitIden::Date, Order::Asc)
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let record: Result<Vec<_>, rusqlite::Error> = statement
+            .query_and_then(&*values.as_params(), |row| {
+                AssetSplit::try_from(row)
+            })?
+            .collect();
+
+        Ok(record?)
+    }
+
+    // when `kind` data of the asset was last fetched, the minimum datetime if
+    // it never was.
+    pub fn updated_at(
+        &self,
+        kind: AssetUpdateKind,
+        transaction: &SqlTransaction,
+    ) -> Result<DateTime<Utc>, ServerError> {
+        AssetUpdate::new(self.id, kind).get_update(transaction)
+    }
+
+    pub fn set_updated_at(
+        &self,
+        kind: AssetUpdateKind,
+        updated_at: DateTime<Utc>,
+        transaction: &SqlTransaction,
+    ) -> Result<(), ServerError> {
+        AssetUpdate::new(self.id, kind).set_update(updated_at, transaction)
+    }
+
+    // the earliest date `kind` data of the asset was fetched from, none if
+    // unknown.
+    pub fn fetched_from(
+        &self,
+        kind: AssetUpdateKind,
+        transaction: &SqlTransaction,
+    ) -> Result<Option<NaiveDate>, ServerError> {
+        AssetUpdate::new(self.id, kind).get_start(transaction)
+    }
+
+    pub fn set_fetched_from(
+        &self,
+        kind: AssetUpdateKind,
+        start: NaiveDate,
+        transaction: &SqlTransaction,
+    ) -> Result<(), ServerError> {
+        AssetUpdate::new(self.id, kind).set_start(start, transaction)
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use crate::database::{self, User};
-//     use rust_decimal_macros::dec;
-//     use sha2::{Digest, Sha256};
-
-//     macro_rules! date {
-//         ($y:expr, $m:expr, $d:expr) => {
-//             NaiveDate::from_ymd_opt($y, $m, $d).expect("panic")
-//         };
-//     }
-
-//     #[test]
-//     fn test_insert_and_select() {
-//         let mut connection =
-//             Connection::open_in_memory().expect("fail to create database");
-//         database::migration::run_migration(&mut connection)
-//             .expect("database initialization fail");
-
-//         let mut u0 = User::new(
-//             String::from("test_user"),
-//             Sha256::digest("password").to_vec(),
-//         );
-//         u0.id = u0.insert(&mut connection).expect("panic");
-
-//         let mut a0 =
-//             Asset::new(AssetId::currency("CAD"), "Canadian Dollar", None);
-//         a0.id = a0.insert(&mut connection).expect("panic");
-
-//         let res = Asset::by_id(a0.id, &mut connection)
-//             .expect("panic")
-//             .expect("panic");
-//         assert_eq!(a0.id, res.id);
-//         assert_eq!(a0.asset_id, res.asset_id);
-//         assert_eq!(a0.name, res.name);
-//         assert_eq!(a0.name, res.name);
-
-//         let res = Asset::by_asset(a0.asset_id.clone(), None, &mut connection)
-//             .expect("panic")
-//             .expect("panic");
-//         assert_eq!(a0.id, res.id);
-//         assert_eq!(a0.asset_id, res.asset_id);
-//         assert_eq!(a0.name, res.name);
-//         assert_eq!(a0.name, res.name);
-
-//         let mut a1 = Asset::new(
-//             AssetId::unknown("TDB2606"),
-//             "TD Global Tactical Monthly Income Fund - H8",
-//             Some(u0.id),
-//         );
-//         a1.id = a1.insert(&mut connection).expect("panic");
-
-//         let res = Asset::by_id(a1.id, &mut connection)
-//             .expect("panic")
-//             .expect("panic");
-//         assert_eq!(a1.id, res.id);
-//         assert_eq!(a1.asset_id, res.asset_id);
-//         assert_eq!(a1.name, res.name);
-//         assert_eq!(a1.name, res.name);
-
-//         let res =
-//             Asset::by_asset(a1.asset_id.clone(), a1.owner, &mut connection)
-//                 .expect("panic")
-//                 .expect("panic");
-//         assert_eq!(a1.id, res.id);
-//         assert_eq!(a1.asset_id, res.asset_id);
-//         assert_eq!(a1.name, res.name);
-//         assert_eq!(a1.name, res.name);
-
-//         let mut a2 = Asset::new(
-//             AssetId::unknown("TDB627"),
-//             "TD Dividend Income Fund - I",
-//             Some(u0.id),
-//         );
-//         a2.id = a2.insert(&mut connection).expect("panic");
-
-//         let res = Asset::by_owner(u0.id, &mut connection).expect("panic");
-//         assert!(!res.contains(&a0));
-//         assert!(res.contains(&a1));
-//         assert!(res.contains(&a2));
-
-//         let res = Asset::search("", u0.id, &mut connection).expect("panic");
-//         assert!(res.contains(&a0));
-//         assert!(res.contains(&a1));
-//         assert!(res.contains(&a2));
-
-//         let res =
-//             Asset::search("", Uuid::nil(), &mut connection).expect("panic");
-//         assert!(res.contains(&a0));
-//         assert!(!res.contains(&a1));
-//         assert!(!res.contains(&a2));
-
-//         let res = Asset::search("C", u0.id, &mut connection).expect("panic");
-//         assert!(res.contains(&a0));
-//         assert!(!res.contains(&a1));
-//         assert!(!res.contains(&a2));
-
-//         let res = Asset::search("TDB6", u0.id, &mut connection).expect("panic");
-//         assert!(!res.contains(&a0));
-//         assert!(!res.contains(&a1));
-//         assert!(res.contains(&a2));
-//     }
-
-//     #[test]
-//     fn test_price() {
-//         let mut connection =
-//             Connection::open_in_memory().expect("fail to create database");
-//         database::migration::run_migration(&mut connection)
-//             .expect("database initialization fail");
-
-//         let mut a0 =
-//             Asset::new(AssetId::currency("CAD"), "Canadian Dollar", None);
-//         a0.id = a0.insert(&mut connection).expect("panic");
-
-//         let p0 = vec![
-//             (date!(2010, 1, 1), dec!(1.1), AssetId::currency("USD")),
-//             (date!(2010, 1, 2), dec!(1.2), AssetId::currency("USD")),
-//             (date!(2010, 1, 3), dec!(1.3), AssetId::currency("USD")),
-//             (date!(2010, 1, 5), dec!(1.4), AssetId::currency("USD")),
-//             (date!(2010, 1, 7), dec!(1.5), AssetId::currency("USD")),
-//         ];
-
-//         a0.insert_price(&p0, &mut connection).expect("panic");
-//         assert_eq!(None, a0.price(date!(2009, 12, 31), &mut connection).expect("panic"));
-//         assert_eq!(Some((dec!(1.1), AssetId::currency("USD"))), a0.price(date!(2010, 1, 1), &mut connection).expect("panic"));
-//     }
-
-//     // TODO: test delete
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, User};
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    #[test]
+    fn test_insert_and_select() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+
+        let (u0, a0, a1, a2) = {
+            let tran = conn.transaction()?;
+            database::migration::run_migration(&tran)?;
+            let mut u0 =
+                User::new("test_user", Sha256::digest("password").to_vec());
+            u0.id = u0.insert(&tran)?;
+
+            let mut a0 =
+                Asset::new(AssetId::currency("CAD"), "Canadian Dollar", None);
+            a0.id = a0.insert(&tran)?;
+            let mut a1 = Asset::new(
+                AssetId::unknown("TDB2606"),
+                "TD Global Tactical Monthly Income Fund - H8",
+                Some(u0.id),
+            );
+            a1.id = a1.insert(&tran)?;
+            let mut a2 = Asset::new(
+                AssetId::unknown("TDB627"),
+                "TD Dividend Income Fund - I",
+                Some(u0.id),
+            );
+            a2.id = a2.insert(&tran)?;
+            tran.commit()?;
+            (u0, a0, a1, a2)
+        };
+        {
+            let tran = conn.transaction()?;
+            for asset in [&a0, &a1, &a2] {
+                let res = Asset::by_id(asset.id, &tran)?.expect("no asset");
+                assert_eq!(asset.asset_id, res.asset_id);
+                assert_eq!(asset.name, res.name);
+                assert_eq!(asset.owner, res.owner);
+
+                let res = Asset::by_asset(
+                    asset.asset_id.clone(),
+                    asset.owner,
+                    &tran,
+                )?
+                .expect("no asset");
+                assert_eq!(asset.id, res.id);
+            }
+            assert_eq!(
+                None,
+                Asset::by_asset(a1.asset_id.clone(), None, &tran)?
+            );
+        }
+        {
+            let tran = conn.transaction()?;
+            let res = Asset::by_owner(u0.id, &tran)?;
+            assert!(!res.contains(&a0));
+            assert!(res.contains(&a1));
+            assert!(res.contains(&a2));
+
+            let res = Asset::search("", u0.id, &tran)?;
+            assert!(res.contains(&a0));
+            assert!(res.contains(&a1));
+            assert!(res.contains(&a2));
+
+            let res = Asset::search("", Uuid::nil(), &tran)?;
+            assert!(res.contains(&a0));
+            assert!(!res.contains(&a1));
+            assert!(!res.contains(&a2));
+
+            let res = Asset::search("C", u0.id, &tran)?;
+            assert!(res.contains(&a0));
+            assert!(!res.contains(&a1));
+            assert!(!res.contains(&a2));
+
+            let res = Asset::search("TDB6", u0.id, &tran)?;
+            assert!(!res.contains(&a0));
+            assert!(!res.contains(&a1));
+            assert!(res.contains(&a2));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let xyz = AssetId::stock("TSE", "XYZ");
+
+        let a0 = {
+            let tran = conn.transaction()?;
+            database::migration::run_migration(&tran)?;
+            let mut a0 = Asset::new(xyz.clone(), "XYZ Corp", None);
+            a0.id = a0.insert(&tran)?;
+            a0.insert_split(
+                &[(date!(2020, 6, 1), dec!(0.1)), (date!(2010, 1, 1), dec!(2))],
+                &tran,
+            )?;
+            // replaces the one on the same date
+            a0.insert_split(&[(date!(2020, 6, 1), dec!(0.2))], &tran)?;
+            tran.commit()?;
+            a0
+        };
+        {
+            let tran = conn.transaction()?;
+            let res: Vec<_> = a0
+                .splits(&tran)?
+                .into_iter()
+                .map(|x| (x.date, x.ratio))
+                .collect();
+            assert_eq!(
+                vec![
+                    (date!(2010, 1, 1), dec!(2)),
+                    (date!(2020, 6, 1), dec!(0.2))
+                ],
+                res
+            );
+            assert_eq!(2, AssetSplit::history(&xyz, None, &tran)?.len());
+
+            Asset::delete(a0.id, &tran)?;
+            assert!(AssetSplit::history(&xyz, None, &tran)?.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_price() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let (usd, eur) = (AssetId::currency("USD"), AssetId::currency("EUR"));
+
+        let a0 = {
+            let tran = conn.transaction()?;
+            database::migration::run_migration(&tran)?;
+            let mut a0 =
+                Asset::new(AssetId::currency("CAD"), "Canadian Dollar", None);
+            a0.id = a0.insert(&tran)?;
+
+            // inserted out of order on purpose
+            a0.insert_price(
+                &[
+                    (date!(2010, 1, 7), dec!(1.5), usd.clone()),
+                    (date!(2010, 1, 1), dec!(1.1), usd.clone()),
+                    (date!(2010, 1, 3), dec!(1.3), usd.clone()),
+                    (date!(2010, 1, 2), dec!(1.2), usd.clone()),
+                    (date!(2010, 1, 5), dec!(1.4), usd.clone()),
+                    (date!(2010, 1, 2), dec!(0.7), eur.clone()),
+                ],
+                &tran,
+            )?;
+            tran.commit()?;
+            a0
+        };
+        {
+            let tran = conn.transaction()?;
+            assert_eq!(None, a0.price(date!(2009, 12, 31), None, &tran)?);
+            assert_eq!(
+                Some((dec!(1.1), usd.clone())),
+                a0.price(date!(2010, 1, 1), None, &tran)?
+            );
+            assert_eq!(
+                Some((dec!(1.4), usd.clone())),
+                a0.price(date!(2010, 1, 6), None, &tran)?
+            );
+            assert_eq!(
+                Some((dec!(1.5), usd.clone())),
+                a0.price(date!(2011, 1, 1), Some(&usd), &tran)?
+            );
+            assert_eq!(
+                Some((dec!(0.7), eur.clone())),
+                a0.price(date!(2011, 1, 1), Some(&eur), &tran)?
+            );
+
+            let res = a0.prices(
+                date!(2010, 1, 2),
+                date!(2010, 1, 5),
+                Some(&usd),
+                &tran,
+            )?;
+            let res: Vec<_> = res.iter().map(|x| (x.date, x.price)).collect();
+            assert_eq!(
+                vec![
+                    (date!(2010, 1, 2), dec!(1.2)),
+                    (date!(2010, 1, 3), dec!(1.3)),
+                    (date!(2010, 1, 5), dec!(1.4)),
+                ],
+                res
+            );
+        }
+        {
+            let tran = conn.transaction()?;
+            Asset::delete(a0.id, &tran)?;
+            tran.commit()?;
+
+            let tran = conn.transaction()?;
+            assert_eq!(None, Asset::by_id(a0.id, &tran)?);
+            assert_eq!(None, a0.price(date!(2011, 1, 1), None, &tran)?);
+        }
+
+        Ok(())
+    }
+}
@@ -1,15 +1,15 @@
 use crate::error::ServerError;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use core::str;
 use rusqlite::types::{FromSql, FromSqlError, ValueRef};
 use rusqlite::Transaction as SqlTransaction;
-use sea_query::{enum_def, Expr, Query, SqliteQueryBuilder};
+use sea_query::{enum_def, Expr, OnConflict, Query, SqliteQueryBuilder};
 use sea_query_rusqlite::RusqliteBinder;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
-pub(super) enum AssetUpdateKind {
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum AssetUpdateKind {
     Price,
     Dividend,
     Split,
@@ -60,6 +60,8 @@ pub(super) struct AssetUpdate {
     pub asset: Uuid,
     pub query: AssetUpdateKind,
     pub updated_at: (),
+    // earliest date fetched, the data is complete from it
+    pub start: (),
 }
 
 impl AssetUpdate {
@@ -68,6 +70,7 @@ impl AssetUpdate {
             asset,
             query,
             updated_at: (),
+            start: (),
         }
     }
 
@@ -96,7 +99,6 @@ impl AssetUpdate {
         transaction: &SqlTransaction,
     ) -> Result<(), ServerError> {
         let (query, values) = Query::insert()
-            .replace()
             .into_table(AssetUpdateIden::Table)
             .columns([
                 AssetUpdateIden::Asset,
@@ -104,6 +106,51 @@ impl AssetUpdate {
                 AssetUpdateIden::UpdatedAt,
             ])
             .values([self.asset.into(), self.query.into(), updated_at.into()])?
+            .on_conflict(
+                OnConflict::columns([
+                    AssetUpdateIden::Asset,
+                    AssetUpdateIden::Query,
+                ])
+                .update_column(AssetUpdateIden::UpdatedAt)
+                .to_owned(),
+            )
+            .build_rusqlite(SqliteQueryBuilder);
+
+        transaction.execute(&query, &*values.as_params())?;
+        Ok(())
+    }
+
+    // none if the data was never fetched, or before the start was recorded
+    pub(super) fn get_start(
+        &self,
+        transaction: &SqlTransaction,
+    ) -> Result<Option<NaiveDate>, ServerError> {
+        let (query, values) = Query::select()
+            .columns([AssetUpdateIden::Start])
+            .from(AssetUpdateIden::Table)
+            .and_where(Expr::col(AssetUpdateIden::Asset).eq(self.asset))
+            .and_where(Expr::col(AssetUpdateIden::Query).eq(self.query))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let record = statement
+            .query_and_then(&*values.as_params(), |row| row.get(0))?
+            .next();
+
+        Ok(record.transpose()?.flatten())
+    }
+
+    // the update must be set first
+    pub(super) fn set_start(
+        &self,
+        start: NaiveDate,
+        transaction: &SqlTransaction,
+    ) -> Result<(), ServerError> {
+        let (query, values) = Query::update()
+            .table(AssetUpdateIden::Table)
+            .value(AssetUpdateIden::Start, start)
+            .and_where(Expr::col(AssetUpdateIden::Asset).eq(self.asset))
+            .and_where(Expr::col(AssetUpdateIden::Query).eq(self.query))
             .build_rusqlite(SqliteQueryBuilder);
 
         transaction.execute(&query, &*values.as_params())?;
@@ -180,6 +227,16 @@ mod tests {
             let t2 = up.get_update(&tran)?;
             assert_eq!(t, t2);
         }
+        {
+            let tran = conn.transaction()?;
+            assert_eq!(None, up.get_start(&tran)?);
+            let start = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+            up.set_start(start, &tran)?;
+
+            // a later update keeps the start
+            up.set_update(Utc::now(), &tran)?;
+            assert_eq!(Some(start), up.get_start(&tran)?);
+        }
 
         Ok(())
     }
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS `setting` (
+    `user` TEXT PRIMARY KEY NOT NULL REFERENCES `user` (`id`),
+    `base_currency` TEXT NOT NULL,
+    `date_range` TEXT NOT NULL,
+    `display` TEXT NOT NULL
+);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS `transaction_i0`;
+
+CREATE INDEX IF NOT EXISTS `transaction_i2` ON `transaction` (`account`, `date`, `id`);
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS `account_drip` (
+    `account` TEXT REFERENCES `account` (`id`) NOT NULL,
+    `asset` TEXT NOT NULL,
+    PRIMARY KEY (`account`, `asset`)
+);
@@ -0,0 +1 @@
+ALTER TABLE `asset_update` ADD COLUMN `start` DATE;
@@ -1,9 +1,11 @@
 use crate::error::ServerError;
 use log::info;
 
-const VERSION: u32 = 2;
+const VERSION: u32 = 6;
 
-pub fn run_migration(transaction: &rusqlite::Transaction) -> Result<(), ServerError> {
+pub fn run_migration(
+    transaction: &rusqlite::Transaction,
+) -> Result<(), ServerError> {
     let mut version =
         transaction.query_row("PRAGMA user_version;", (), |row| {
             row.get::<_, u32>(0)
@@ -30,6 +32,10 @@ pub fn run_migration(transaction: &rusqlite::Transaction) -> Result<(), ServerEr
 
     migrate!(1, "001_create_tables.sql");
     migrate!(2, "002_create_tables.sql");
+    migrate!(3, "003_create_tables.sql");
+    migrate!(4, "004_create_tables.sql");
+    migrate!(5, "005_create_tables.sql");
+    migrate!(6, "006_create_tables.sql");
 
     if version != VERSION {
         Err(ServerError::Internal(format!(
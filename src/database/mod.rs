@@ -1,6 +1,7 @@
 pub mod account;
 pub mod asset;
-mod migration;
+pub(crate) mod migration;
+pub mod setting;
 pub mod transaction;
 pub mod user;
 
@@ -19,12 +20,13 @@ pub fn init() -> Result<(), ServerError> {
     let tran = conn.transaction()?;
     migration::run_migration(&tran)?;
     tran.commit()?;
-    
+
     Ok(())
 }
 
 pub use account::Account;
 use rusqlite::Connection;
+pub use setting::Setting;
 pub use transaction::Transaction;
 pub use user::User;
 
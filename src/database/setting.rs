@@ -0,0 +1,242 @@
+use super::asset::AssetId;
+use crate::error::ServerError;
+use crate::portfolio::Period;
+use core::str;
+use rusqlite::types::{FromSql, FromSqlError, ValueRef};
+use rusqlite::{Row, Transaction as SqlTransaction};
+use sea_query::{enum_def, Expr, IdenStatic, Query, SqliteQueryBuilder};
+use sea_query_rusqlite::RusqliteBinder;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(
+    Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default,
+)]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct Display {
+    pub theme: Theme,
+    // number of decimal places shown for amounts
+    pub decimals: u32,
+    // mask amounts on screen
+    pub privacy: bool,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self {
+            theme: Theme::System,
+            decimals: 2,
+            privacy: false,
+        }
+    }
+}
+
+impl From<Display> for sea_query::value::Value {
+    fn from(value: Display) -> Self {
+        serde_json::to_value(value).unwrap().into()
+    }
+}
+
+impl FromSql for Display {
+    fn column_result(value: ValueRef<'_>) -> Result<Self, FromSqlError> {
+        if let ValueRef::Text(text) = value {
+            if let Ok(s) = str::from_utf8(text) {
+                if let Ok(display) = serde_json::from_str(s) {
+                    return Ok(display);
+                }
+            }
+        }
+
+        Err(FromSqlError::InvalidType)
+    }
+}
+
+// stored by its name in requests, e.g. YTD
+impl From<Period> for sea_query::value::Value {
+    fn from(value: Period) -> Self {
+        value.as_str().into()
+    }
+}
+
+impl FromSql for Period {
+    fn column_result(value: ValueRef<'_>) -> Result<Self, FromSqlError> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|_| FromSqlError::InvalidType)
+    }
+}
+
+// preferences of a user, the default one is used until the user saves any.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[enum_def]
+pub struct Setting {
+    #[serde(default)]
+    pub user: Uuid,
+    // reporting currency of aggregates
+    pub base_currency: AssetId,
+    // the period reports cover when none is requested
+    pub date_range: Period,
+    pub display: Display,
+}
+
+impl TryFrom<&Row<'_>> for Setting {
+    type Error = rusqlite::Error;
+
+    fn try_from(value: &Row<'_>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            user: value.get(SettingIden::User.as_str())?,
+            base_currency: value.get(SettingIden::BaseCurrency.as_str())?,
+            date_range: value.get(SettingIden::DateRange.as_str())?,
+            display: value.get(SettingIden::Display.as_str())?,
+        })
+    }
+}
+
+impl Setting {
+    pub fn new(user: Uuid) -> Self {
+        Self {
+            user,
+            base_currency: AssetId::currency("CAD"),
+            date_range: Period::SinceInception,
+            display: Display::default(),
+        }
+    }
+}
+
+impl Setting {
+    pub fn by_user(
+        user: Uuid,
+        transaction: &SqlTransaction,
+    ) -> Result<Setting, ServerError> {
+        let (query, values) = Query::select()
+            .columns([
+                SettingIden::User,
+                SettingIden::BaseCurrency,
+                SettingIden::DateRange,
+                SettingIden::Display,
+            ])
+            .from(SettingIden::Table)
+            .and_where(Expr::col(SettingIden::User).eq(user))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let record = statement
+            .query_and_then(&*values.as_params(), |row| Setting::try_from(row))?
+            .next();
+
+        Ok(record.transpose()?.unwrap_or(Setting::new(user)))
+    }
+
+    // inserts or replaces the setting of `self.user`
+    pub fn save(
+        &self,
+        transaction: &SqlTransaction,
+    ) -> Result<(), ServerError> {
+        let (query, values) = Query::insert()
+            .replace()
+            .into_table(SettingIden::Table)
+            .columns([
+                SettingIden::User,
+                SettingIden::BaseCurrency,
+                SettingIden::DateRange,
+                SettingIden::Display,
+            ])
+            .values([
+                self.user.into(),
+                self.base_currency.clone().into(),
+                self.date_range.into(),
+                self.display.clone().into(),
+            ])?
+            .build_rusqlite(SqliteQueryBuilder);
+
+        transaction.execute(&query, &*values.as_params())?;
+        Ok(())
+    }
+
+    pub fn delete(
+        user: Uuid,
+        transaction: &SqlTransaction,
+    ) -> Result<(), ServerError> {
+        let (query, values) = Query::delete()
+            .from_table(SettingIden::Table)
+            .and_where(Expr::col(SettingIden::User).eq(user))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        transaction.execute(&query, &*values.as_params())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, User};
+    use rusqlite::Connection;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_save_and_select() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+
+        let u0 = {
+            let tran = conn.transaction()?;
+            database::migration::run_migration(&tran)?;
+            let mut u0 =
+                User::new("test_user", Sha256::digest("password").to_vec());
+            u0.id = u0.insert(&tran)?;
+            tran.commit()?;
+            u0
+        };
+        {
+            let tran = conn.transaction()?;
+            let res = Setting::by_user(u0.id, &tran)?;
+            assert_eq!(Setting::new(u0.id), res);
+        }
+        let s0 = {
+            let tran = conn.transaction()?;
+            let mut s0 = Setting::new(u0.id);
+            s0.base_currency = AssetId::currency("USD");
+            s0.date_range = Period::YearToDate;
+            s0.display.theme = Theme::Dark;
+            s0.save(&tran)?;
+
+            // saving again replaces the previous one
+            s0.display.decimals = 4;
+            s0.save(&tran)?;
+            tran.commit()?;
+            s0
+        };
+        {
+            let tran = conn.transaction()?;
+            let res = Setting::by_user(u0.id, &tran)?;
+            assert_eq!(s0, res);
+
+            // the period is stored by its name
+            let stored: String = tran.query_row(
+                "SELECT date_range FROM setting WHERE user = ?1",
+                [u0.id],
+                |row| row.get(0),
+            )?;
+            assert_eq!("YTD", stored);
+        }
+        {
+            let tran = conn.transaction()?;
+            User::delete(u0.id, &tran)?;
+            tran.commit()?;
+
+            let tran = conn.transaction()?;
+            assert_eq!(Setting::new(u0.id), Setting::by_user(u0.id, &tran)?);
+        }
+
+        Ok(())
+    }
+}
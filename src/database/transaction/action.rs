@@ -3,6 +3,7 @@ use core::str;
 use rusqlite::types::{FromSql, FromSqlError, ValueRef};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 type Value = (Decimal, AssetId);
 
@@ -40,11 +41,133 @@ pub enum TxnAction {
         value: Value,
         fee: Value,
     },
+    // a dividend of `asset` spent on more units of it, `fee` is withheld
+    // from `value` and the rest is reinvested
+    ReinvestedDividend {
+        asset: Value,
+        value: Value,
+        fee: Value,
+    },
     Journal {
         source: AssetId,
         target: AssetId,
         fee: Value,
     },
+    // the outgoing leg of a transfer to another account of the same user,
+    // `value` is the fair market value of the asset moved
+    TransferOut {
+        asset: Value,
+        value: Value,
+        fee: Value,
+        // the incoming leg
+        counterpart: Uuid,
+    },
+    TransferIn {
+        asset: Value,
+        value: Value,
+        // the outgoing leg
+        counterpart: Uuid,
+    },
+    // `ratio` new shares for every share held, below one for a
+    // consolidation. the fraction of a share left over, if any, is sold for
+    // `cash` in lieu.
+    Split {
+        asset: AssetId,
+        ratio: Decimal,
+        #[serde(default)]
+        cash: Option<Value>,
+    },
+    // every share of `source` is exchanged for `ratio` shares of `target`
+    // and `cash` in total. `allocation` is the percentage of the ACB carried
+    // to `target`, the rest is disposed of for the cash.
+    Merger {
+        source: AssetId,
+        target: AssetId,
+        ratio: Decimal,
+        cash: Value,
+        allocation: Decimal,
+    },
+    // `target` received while `source` is kept, along with `allocation`
+    // percent of its ACB
+    SpinOff {
+        source: AssetId,
+        target: Value,
+        allocation: Decimal,
+    },
+    // the part of the distributions of `source` paid out of capital, which
+    // reduces its ACB. the cash itself is recorded as a dividend.
+    ReturnOfCapital {
+        source: AssetId,
+        value: Value,
+    },
+    // a capital gains distribution of `source` paid in units which are
+    // consolidated right away, so only the ACB increases
+    ReinvestedCapitalGain {
+        source: AssetId,
+        value: Value,
+    },
+}
+
+impl TxnAction {
+    // every asset the action refers to, fees included
+    pub fn assets(&self) -> Vec<AssetId> {
+        match self {
+            TxnAction::Deposit { value, fee }
+            | TxnAction::Withdrawal { value, fee } => {
+                vec![value.1.clone(), fee.1.clone()]
+            }
+            TxnAction::Income { value, .. } | TxnAction::Fee { value, .. } => {
+                vec![value.1.clone()]
+            }
+            TxnAction::Buy { asset, cash, fee }
+            | TxnAction::Sell { asset, cash, fee } => {
+                vec![asset.1.clone(), cash.1.clone(), fee.1.clone()]
+            }
+            TxnAction::Dividend { source, value, fee } => {
+                vec![source.clone(), value.1.clone(), fee.1.clone()]
+            }
+            TxnAction::ReinvestedDividend { asset, value, fee } => {
+                vec![asset.1.clone(), value.1.clone(), fee.1.clone()]
+            }
+            TxnAction::Journal {
+                source,
+                target,
+                fee,
+            } => vec![source.clone(), target.clone(), fee.1.clone()],
+            TxnAction::TransferOut {
+                asset, value, fee, ..
+            } => vec![asset.1.clone(), value.1.clone(), fee.1.clone()],
+            TxnAction::TransferIn { asset, value, .. } => {
+                vec![asset.1.clone(), value.1.clone()]
+            }
+            TxnAction::Split { asset, cash, .. } => std::iter::once(asset)
+                .chain(cash.iter().map(|x| &x.1))
+                .cloned()
+                .collect(),
+            TxnAction::Merger {
+                source,
+                target,
+                cash,
+                ..
+            } => vec![source.clone(), target.clone(), cash.1.clone()],
+            TxnAction::SpinOff { source, target, .. } => {
+                vec![source.clone(), target.1.clone()]
+            }
+            TxnAction::ReturnOfCapital { source, value }
+            | TxnAction::ReinvestedCapitalGain { source, value } => {
+                vec![source.clone(), value.1.clone()]
+            }
+        }
+    }
+
+    // the other leg of a transfer
+    pub fn counterpart(&self) -> Option<Uuid> {
+        match self {
+            TxnAction::TransferOut { counterpart, .. }
+            | TxnAction::TransferIn { counterpart, .. } => Some(*counterpart),
+            _ => None,
+        }
+    }
 }
 
 impl From<TxnAction> for sea_query::value::Value {
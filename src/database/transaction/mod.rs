@@ -1,12 +1,16 @@
 mod action;
+mod query;
+mod transfer;
 
 use crate::error::ServerError;
 pub use action::TxnAction;
 use chrono::NaiveDate;
+pub use query::{Cursor, Filter, Page, Sort, MAX_LIMIT};
 use rusqlite::Row;
-use sea_query::{enum_def, Expr, IdenStatic, Query, SqliteQueryBuilder};
+use sea_query::{enum_def, Expr, IdenStatic, Order, Query, SqliteQueryBuilder};
 use sea_query_rusqlite::RusqliteBinder;
 use serde::{Deserialize, Serialize};
+pub use transfer::Transfer;
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -100,6 +104,7 @@ impl Transaction {
             ])
             .from(TransactionIden::Table)
             .and_where(Expr::col(TransactionIden::Account).eq(account))
+            .order_by(TransactionIden::Date, Order::Asc)
             .build_rusqlite(SqliteQueryBuilder);
 
         let mut statement = transaction.prepare(&query)?;
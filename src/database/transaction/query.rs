@@ -0,0 +1,365 @@
+use super::{Transaction, TransactionIden};
+use crate::database::asset::AssetId;
+use crate::error::ServerError;
+use chrono::NaiveDate;
+use sea_query::{Cond, Expr, Order, Query, SqliteQueryBuilder, Value};
+use sea_query_rusqlite::RusqliteBinder;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt::Display;
+use std::str::FromStr;
+use uuid::Uuid;
+
+// the most transactions returned in a page
+pub const MAX_LIMIT: usize = 1000;
+
+// conditions of the transactions to find, an empty list matches everything
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Filter {
+    pub accounts: Vec<Uuid>,
+    // both inclusive
+    pub start: Option<NaiveDate>,
+    pub end: Option<NaiveDate>,
+    // names of `TxnAction` variants
+    pub actions: Vec<String>,
+    // any asset the action refers to, fees included
+    pub assets: Vec<AssetId>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    #[default]
+    Asc,
+    Desc,
+}
+
+// the position after the last transaction of a page, transactions are
+// ordered by date and then id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    date: NaiveDate,
+    id: Uuid,
+}
+
+impl Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}", self.date, self.id.simple())
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (date, id) = s.split_once('_').ok_or(())?;
+        Ok(Self {
+            date: NaiveDate::from_str(date).map_err(|_| ())?,
+            id: Uuid::from_str(id).map_err(|_| ())?,
+        })
+    }
+}
+
+impl Serialize for Cursor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_string().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cursor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let value = String::deserialize(deserializer)?;
+        Cursor::from_str(&value)
+            .map_err(|_| D::Error::custom("Unable to convert string"))
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct Page {
+    pub transactions: Vec<Transaction>,
+    // none on the last page
+    pub next: Option<Cursor>,
+}
+
+impl Transaction {
+    // a page of at most `limit` transactions matching `filter`, starting
+    // after `cursor`.
+    pub fn query(
+        filter: &Filter,
+        sort: Sort,
+        cursor: Option<&Cursor>,
+        limit: usize,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<Page, ServerError> {
+        let limit = limit.clamp(1, MAX_LIMIT);
+        let mut condition = Cond::all();
+        if !filter.accounts.is_empty() {
+            condition = condition.add(
+                Expr::col(TransactionIden::Account)
+                    .is_in(filter.accounts.iter().cloned()),
+            );
+        }
+        if let Some(start) = filter.start {
+            condition =
+                condition.add(Expr::col(TransactionIden::Date).gte(start));
+        }
+        if let Some(end) = filter.end {
+            condition =
+                condition.add(Expr::col(TransactionIden::Date).lte(end));
+        }
+        if !filter.actions.is_empty() {
+            condition = condition.add(
+                Expr::expr(Expr::cust("json_extract(`action`, '$.type')"))
+                    .is_in(filter.actions.iter().cloned()),
+            );
+        }
+        if !filter.assets.is_empty() {
+            // assets are the string leaves of the action but its reason
+            let assets: Vec<Value> = filter
+                .assets
+                .iter()
+                .map(|x| String::from(x.clone()).into())
+                .collect();
+            let placeholders = vec!["?"; assets.len()].join(", ");
+            condition = condition.add(Expr::cust_with_values(
+                format!(
+                    "EXISTS (SELECT 1 FROM json_tree(`action`) \
+                     WHERE `key` IS NOT 'reason' AND `atom` IN ({}))",
+                    placeholders
+                ),
+                assets,
+            ));
+        }
+        if let Some(cursor) = cursor {
+            let operator = match sort {
+                Sort::Asc => ">",
+                Sort::Desc => "<",
+            };
+            condition = condition.add(Expr::cust_with_values(
+                format!("(`date`, `id`) {} (?, ?)", operator),
+                [Value::from(cursor.date), Value::from(cursor.id)],
+            ));
+        }
+
+        let order = match sort {
+            Sort::Asc => Order::Asc,
+            Sort::Desc => Order::Desc,
+        };
+        let (query, values) = Query::select()
+            .columns([
+                TransactionIden::Id,
+                TransactionIden::Account,
+                TransactionIden::Date,
+                TransactionIden::Action,
+            ])
+            .from(TransactionIden::Table)
+            .cond_where(condition)
+            .order_by(TransactionIden::Date, order.clone())
+            .order_by(TransactionIden::Id, order)
+            // one more to tell whether there is a next page
+            .limit(limit as u64 + 1)
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut statement = transaction.prepare(&query)?;
+        let mut transactions = statement
+            .query_and_then(&*values.as_params(), |row| {
+                Transaction::try_from(row)
+            })?
+            .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+
+        let next = if transactions.len() > limit {
+            transactions.truncate(limit);
+            transactions.last().map(|x| Cursor {
+                date: x.date,
+                id: x.id,
+            })
+        } else {
+            None
+        };
+        Ok(Page { transactions, next })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::account::AccountKind;
+    use crate::database::transaction::TxnAction;
+    use crate::database::{self, Account, User};
+    use chrono::Datelike;
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    #[test]
+    fn test_query() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let tran = conn.transaction()?;
+        database::migration::run_migration(&tran)?;
+        let mut u0 = User::new(
+            String::from("test_user"),
+            Sha256::digest("password").to_vec(),
+        );
+        u0.id = u0.insert(&tran)?;
+        let mut a0 =
+            Account::new("test_account", "alias", u0.id, AccountKind::NRA);
+        a0.id = a0.insert(&tran)?;
+        let mut a1 =
+            Account::new("test_account", "alias", u0.id, AccountKind::TFSA);
+        a1.id = a1.insert(&tran)?;
+
+        let cad = |x| (x, AssetId::currency("CAD"));
+        let xeqt = AssetId::stock("TSE", "XEQT");
+        for day in 1..=10 {
+            let account = if day % 2 == 0 { a0.id } else { a1.id };
+            let action = if day % 3 == 0 {
+                TxnAction::Buy {
+                    asset: (dec!(1), xeqt.clone()),
+                    cash: cad(dec!(25)),
+                    fee: cad(dec!(0)),
+                }
+            } else {
+                TxnAction::Income {
+                    value: cad(dec!(1)),
+                    reason: String::from("XTSE:XEQT"),
+                }
+            };
+            let t = Transaction::new(account, date!(2021, 1, day), action);
+            t.insert(&tran)?;
+        }
+
+        // pages follow each other without gaps
+        let filter = Filter::default();
+        let mut cursor = None;
+        let mut dates = Vec::new();
+        loop {
+            let page = Transaction::query(
+                &filter,
+                Sort::Desc,
+                cursor.as_ref(),
+                3,
+                &tran,
+            )?;
+            dates.extend(page.transactions.iter().map(|x| x.date.day()));
+            cursor = match page.next {
+                Some(x) => Some(Cursor::from_str(&x.to_string()).unwrap()),
+                None => break,
+            };
+        }
+        assert_eq!((1..=10).rev().collect::<Vec<u32>>(), dates);
+
+        // transactions of the same day are ordered by id across pages
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let t = Transaction::new(
+                a0.id,
+                date!(2021, 1, 11),
+                TxnAction::Fee {
+                    value: cad(dec!(1)),
+                    reason: String::new(),
+                },
+            );
+            ids.push(t.insert(&tran)?);
+        }
+        ids.sort();
+        let filter = Filter {
+            start: Some(date!(2021, 1, 10)),
+            ..Default::default()
+        };
+        let first = Transaction::query(&filter, Sort::Asc, None, 2, &tran)?;
+        let second = Transaction::query(
+            &filter,
+            Sort::Asc,
+            first.next.as_ref(),
+            2,
+            &tran,
+        )?;
+        assert_eq!(vec![10, 11], days(&first));
+        assert_eq!(vec![11, 11], days(&second));
+        assert_eq!(
+            ids,
+            first.transactions[1..]
+                .iter()
+                .chain(&second.transactions)
+                .map(|x| x.id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(None, second.next);
+        for id in ids {
+            Transaction::delete(id, &tran)?;
+        }
+
+        let filter = Filter {
+            accounts: vec![a0.id],
+            start: Some(date!(2021, 1, 3)),
+            end: Some(date!(2021, 1, 8)),
+            ..Default::default()
+        };
+        let page = Transaction::query(&filter, Sort::Asc, None, 100, &tran)?;
+        assert_eq!(
+            vec![date!(2021, 1, 4), date!(2021, 1, 6), date!(2021, 1, 8)],
+            page.transactions.iter().map(|x| x.date).collect::<Vec<_>>()
+        );
+        assert_eq!(None, page.next);
+
+        // the reason of an income is not an asset
+        let filter = Filter {
+            assets: vec![xeqt.clone()],
+            ..Default::default()
+        };
+        let page = Transaction::query(&filter, Sort::Asc, None, 100, &tran)?;
+        assert_eq!(vec![3, 6, 9], days(&page));
+        let filter = Filter {
+            actions: vec![String::from("Buy")],
+            accounts: vec![a0.id],
+            ..Default::default()
+        };
+        let page = Transaction::query(&filter, Sort::Asc, None, 100, &tran)?;
+        assert_eq!(vec![6], days(&page));
+
+        Ok(())
+    }
+
+    fn days(page: &Page) -> Vec<u32> {
+        page.transactions.iter().map(|x| x.date.day()).collect()
+    }
+
+    #[test]
+    fn test_plan() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let tran = conn.transaction()?;
+        database::migration::run_migration(&tran)?;
+
+        let plan: Vec<String> = tran
+            .prepare(
+                "EXPLAIN QUERY PLAN SELECT * FROM `transaction` \
+                 WHERE `account` IN (?, ?) AND `date` >= ? \
+                 ORDER BY `date`, `id` LIMIT 10",
+            )?
+            .query_map(
+                (Uuid::new_v4(), Uuid::new_v4(), date!(2021, 1, 1)),
+                |row| row.get(3),
+            )?
+            .collect::<Result<_, _>>()?;
+        assert!(
+            plan.iter().any(|x| x.contains("transaction_i2")),
+            "{:?}",
+            plan
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use super::{Transaction, TxnAction};
+use crate::database::asset::AssetId;
+use crate::error::ServerError;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// an asset moved from `source` to `target`, both accounts of the same user.
+// it is stored as a `TransferOut` leg in `source` and a `TransferIn` leg in
+// `target`, which refer to each other.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    // the outgoing leg
+    #[serde(default)]
+    pub id: Uuid,
+    pub date: NaiveDate,
+    pub source: Uuid,
+    pub target: Uuid,
+    pub asset: (Decimal, AssetId),
+    // the fair market value of the asset moved
+    pub value: (Decimal, AssetId),
+    pub fee: (Decimal, AssetId),
+}
+
+impl Transfer {
+    // the outgoing and the incoming leg
+    fn legs(&self, out: Uuid, inc: Uuid) -> (Transaction, Transaction) {
+        let mut source = Transaction::new(
+            self.source,
+            self.date,
+            TxnAction::TransferOut {
+                asset: self.asset.clone(),
+                value: self.value.clone(),
+                fee: self.fee.clone(),
+                counterpart: inc,
+            },
+        );
+        source.id = out;
+        let mut target = Transaction::new(
+            self.target,
+            self.date,
+            TxnAction::TransferIn {
+                asset: self.asset.clone(),
+                value: self.value.clone(),
+                counterpart: out,
+            },
+        );
+        target.id = inc;
+        (source, target)
+    }
+
+    // the transfer which the transaction `id` is a leg of
+    pub fn by_leg(
+        id: Uuid,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<Option<Transfer>, ServerError> {
+        let leg = match Transaction::by_id(id, transaction)? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let (source, target) = match &leg.action {
+            TxnAction::TransferOut { counterpart, .. } => (
+                Some(leg.clone()),
+                Transaction::by_id(*counterpart, transaction)?,
+            ),
+            TxnAction::TransferIn { counterpart, .. } => (
+                Transaction::by_id(*counterpart, transaction)?,
+                Some(leg.clone()),
+            ),
+            _ => return Ok(None),
+        };
+        match (source, target) {
+            (
+                Some(Transaction {
+                    id,
+                    date,
+                    account,
+                    action:
+                        TxnAction::TransferOut {
+                            asset, value, fee, ..
+                        },
+                }),
+                Some(target),
+            ) => Ok(Some(Transfer {
+                id,
+                date,
+                source: account,
+                target: target.account,
+                asset,
+                value,
+                fee,
+            })),
+            _ => Err(ServerError::Internal(format!(
+                "transfer {} has a single leg",
+                id
+            ))),
+        }
+    }
+
+    // stores both legs, returns the id of the outgoing leg
+    pub fn insert(
+        &self,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<Uuid, ServerError> {
+        assert!(self.id.is_nil());
+
+        // the incoming leg refers to the outgoing one, which is only known
+        // once stored
+        let (source, _) = self.legs(Uuid::nil(), Uuid::nil());
+        let out = source.insert(transaction)?;
+        let (_, target) = self.legs(out, Uuid::nil());
+        let inc = target.insert(transaction)?;
+        let (source, _) = self.legs(out, inc);
+        source.update(transaction)?;
+        Ok(out)
+    }
+
+    // updates both legs, the accounts cannot be modified
+    pub fn update(
+        &self,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<(), ServerError> {
+        let inc = match Transaction::by_id(self.id, transaction)?
+            .and_then(|x| x.action.counterpart())
+        {
+            Some(x) => x,
+            None => {
+                return Err(ServerError::Internal(format!(
+                    "{} is not the outgoing leg of a transfer",
+                    self.id
+                )))
+            }
+        };
+        let (source, target) = self.legs(self.id, inc);
+        source.update(transaction)?;
+        target.update(transaction)?;
+        Ok(())
+    }
+
+    // deletes the transaction `id` along with the other leg
+    pub fn delete(
+        id: Uuid,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<(), ServerError> {
+        if let Some(counterpart) = Transaction::by_id(id, transaction)?
+            .and_then(|x| x.action.counterpart())
+        {
+            Transaction::delete(counterpart, transaction)?;
+        }
+        Transaction::delete(id, transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::account::AccountKind;
+    use crate::database::{self, Account, User};
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_transfer() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let tran = conn.transaction()?;
+        database::migration::run_migration(&tran)?;
+        let mut u0 = User::new(
+            String::from("test_user"),
+            Sha256::digest("password").to_vec(),
+        );
+        u0.id = u0.insert(&tran)?;
+        let mut a0 =
+            Account::new("test_account", "alias", u0.id, AccountKind::NRA);
+        a0.id = a0.insert(&tran)?;
+        let mut a1 =
+            Account::new("test_account", "alias", u0.id, AccountKind::TFSA);
+        a1.id = a1.insert(&tran)?;
+
+        let cad = |x| (x, AssetId::currency("CAD"));
+        let mut t0 = Transfer {
+            id: Uuid::nil(),
+            date: NaiveDate::from_ymd_opt(2021, 1, 5).unwrap(),
+            source: a0.id,
+            target: a1.id,
+            asset: (dec!(10), AssetId::stock("TSE", "XEQT")),
+            value: cad(dec!(250)),
+            fee: cad(dec!(0)),
+        };
+        t0.id = t0.insert(&tran)?;
+
+        let target = Transaction::by_account(a1.id, &tran)?;
+        assert_eq!(1, target.len());
+        assert_eq!(Some(t0.id), target[0].action.counterpart());
+        assert_eq!(Some(t0.clone()), Transfer::by_leg(target[0].id, &tran)?);
+        assert_eq!(Some(t0.clone()), Transfer::by_leg(t0.id, &tran)?);
+
+        t0.value = cad(dec!(260));
+        t0.update(&tran)?;
+        assert_eq!(Some(t0.clone()), Transfer::by_leg(target[0].id, &tran)?);
+
+        Transfer::delete(target[0].id, &tran)?;
+        assert!(Transaction::by_account(a0.id, &tran)?.is_empty());
+        assert!(Transaction::by_account(a1.id, &tran)?.is_empty());
+
+        Ok(())
+    }
+}
@@ -125,6 +125,13 @@ impl User {
                 })?;
         }
 
+        // delete private assets
+        for asset in super::asset::Asset::by_owner(id, transaction)? {
+            super::asset::Asset::delete(asset.id, transaction)?;
+        }
+
+        super::Setting::delete(id, transaction)?;
+
         // delete user
         let (query, values) = Query::delete()
             .from_table(UserIden::Table)
@@ -358,7 +365,7 @@ mod tests {
     #[test]
     fn test_delete() -> Result<(), ServerError> {
         use database::account::{Account, AccountKind};
-        use database::asset::AssetId;
+        use database::asset::{Asset, AssetId};
         use database::transaction::{Transaction, TxnAction};
         let mut conn = Connection::open_in_memory()?;
 
@@ -367,7 +374,7 @@ mod tests {
             database::migration::run_migration(&tran)?;
             tran.commit()?;
         }
-        let (u0, a0, t0) = {
+        let (u0, a0, t0, s0) = {
             let tran = conn.transaction()?;
             let mut u0 =
                 User::new("test_user", Sha256::digest("password").to_vec());
@@ -384,9 +391,11 @@ mod tests {
                 },
             );
             t0.id = t0.insert(&tran)?;
+            let mut s0 =
+                Asset::new(AssetId::unknown("TDB627"), "fund", Some(u0.id));
+            s0.id = s0.insert(&tran)?;
             tran.commit()?;
-            // TODO: test user-attached asset deletion here
-            (u0, a0, t0)
+            (u0, a0, t0, s0)
         };
         {
             let tran = conn.transaction()?;
@@ -397,6 +406,7 @@ mod tests {
             assert_eq!(None, Transaction::by_id(t0.id, &tran)?);
             assert_eq!(None, Account::by_id(a0.id, &tran)?);
             assert_eq!(None, User::by_id(u0.id, &tran)?);
+            assert_eq!(None, Asset::by_id(s0.id, &tran)?);
         }
 
         Ok(())
@@ -8,6 +8,7 @@ pub enum ServerError {
     Rusqlite(rusqlite::Error),
     SeaQuery(sea_query::error::Error),
     Json(serde_json::Error),
+    Csv(csv::Error),
     Jwt(jwt::Error),
     #[from(skip)]
     Internal(String),
@@ -0,0 +1,377 @@
+use crate::database::asset::AssetId;
+use crate::database::transaction::TxnAction;
+use crate::database::Transaction;
+use crate::error::ServerError;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// transactions written in a single chunk of the export
+pub const CHUNK: usize = 500;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    // one row per transaction, amounts are written as exact decimals
+    Csv,
+    // the serde representation of `Transaction`, which can be inserted back
+    Json,
+}
+
+// a transaction flattened into the columns of the csv export
+#[derive(Debug, Serialize)]
+struct Record {
+    id: Uuid,
+    account: Uuid,
+    date: NaiveDate,
+    action: &'static str,
+    asset: Option<AssetId>,
+    quantity: Option<String>,
+    target: Option<AssetId>,
+    value: Option<String>,
+    currency: Option<AssetId>,
+    fee: Option<String>,
+    fee_currency: Option<AssetId>,
+    reason: Option<String>,
+    counterpart: Option<Uuid>,
+    ratio: Option<String>,
+    allocation: Option<String>,
+}
+
+impl Record {
+    const COLUMNS: [&'static str; 15] = [
+        "id",
+        "account",
+        "date",
+        "action",
+        "asset",
+        "quantity",
+        "target",
+        "value",
+        "currency",
+        "fee",
+        "fee_currency",
+        "reason",
+        "counterpart",
+        "ratio",
+        "allocation",
+    ];
+
+    fn value(&mut self, (amount, asset): &(Decimal, AssetId)) {
+        self.value = Some(amount.to_string());
+        self.currency = Some(asset.clone());
+    }
+
+    fn fee(&mut self, (amount, asset): &(Decimal, AssetId)) {
+        self.fee = Some(amount.to_string());
+        self.fee_currency = Some(asset.clone());
+    }
+
+    fn asset(&mut self, (quantity, asset): &(Decimal, AssetId)) {
+        self.asset = Some(asset.clone());
+        self.quantity = Some(quantity.to_string());
+    }
+}
+
+impl From<&Transaction> for Record {
+    fn from(transaction: &Transaction) -> Self {
+        let mut record = Self {
+            id: transaction.id,
+            account: transaction.account,
+            date: transaction.date,
+            action: "",
+            asset: None,
+            quantity: None,
+            target: None,
+            value: None,
+            currency: None,
+            fee: None,
+            fee_currency: None,
+            reason: None,
+            counterpart: None,
+            ratio: None,
+            allocation: None,
+        };
+        match &transaction.action {
+            TxnAction::Deposit { value, fee } => {
+                record.action = "Deposit";
+                record.value(value);
+                record.fee(fee);
+            }
+            TxnAction::Withdrawal { value, fee } => {
+                record.action = "Withdrawal";
+                record.value(value);
+                record.fee(fee);
+            }
+            TxnAction::Income { value, reason } => {
+                record.action = "Income";
+                record.value(value);
+                record.reason = Some(reason.clone());
+            }
+            TxnAction::Fee { value, reason } => {
+                record.action = "Fee";
+                record.value(value);
+                record.reason = Some(reason.clone());
+            }
+            TxnAction::Buy { asset, cash, fee } => {
+                record.action = "Buy";
+                record.asset(asset);
+                record.value(cash);
+                record.fee(fee);
+            }
+            TxnAction::Sell { asset, cash, fee } => {
+                record.action = "Sell";
+                record.asset(asset);
+                record.value(cash);
+                record.fee(fee);
+            }
+            TxnAction::Dividend { source, value, fee } => {
+                record.action = "Dividend";
+                record.asset = Some(source.clone());
+                record.value(value);
+                record.fee(fee);
+            }
+            TxnAction::ReinvestedDividend { asset, value, fee } => {
+                record.action = "ReinvestedDividend";
+                record.asset(asset);
+                record.value(value);
+                record.fee(fee);
+            }
+            TxnAction::Journal {
+                source,
+                target,
+                fee,
+            } => {
+                record.action = "Journal";
+                record.asset = Some(source.clone());
+                record.target = Some(target.clone());
+                record.fee(fee);
+            }
+            TxnAction::TransferOut {
+                asset,
+                value,
+                fee,
+                counterpart,
+            } => {
+                record.action = "TransferOut";
+                record.asset(asset);
+                record.value(value);
+                record.fee(fee);
+                record.counterpart = Some(*counterpart);
+            }
+            TxnAction::TransferIn {
+                asset,
+                value,
+                counterpart,
+            } => {
+                record.action = "TransferIn";
+                record.asset(asset);
+                record.value(value);
+                record.counterpart = Some(*counterpart);
+            }
+            TxnAction::Split { asset, ratio, cash } => {
+                record.action = "Split";
+                record.asset = Some(asset.clone());
+                record.ratio = Some(ratio.to_string());
+                if let Some(cash) = cash {
+                    record.value(cash);
+                }
+            }
+            TxnAction::Merger {
+                source,
+                target,
+                ratio,
+                cash,
+                allocation,
+            } => {
+                record.action = "Merger";
+                record.asset = Some(source.clone());
+                record.target = Some(target.clone());
+                record.ratio = Some(ratio.to_string());
+                record.value(cash);
+                record.allocation = Some(allocation.to_string());
+            }
+            TxnAction::SpinOff {
+                source,
+                target,
+                allocation,
+            } => {
+                // the quantity is the one of the target
+                record.action = "SpinOff";
+                record.asset = Some(source.clone());
+                record.target = Some(target.1.clone());
+                record.quantity = Some(target.0.to_string());
+                record.allocation = Some(allocation.to_string());
+            }
+            TxnAction::ReturnOfCapital { source, value } => {
+                record.action = "ReturnOfCapital";
+                record.asset = Some(source.clone());
+                record.value(value);
+            }
+            TxnAction::ReinvestedCapitalGain { source, value } => {
+                record.action = "ReinvestedCapitalGain";
+                record.asset = Some(source.clone());
+                record.value(value);
+            }
+        }
+        record
+    }
+}
+
+impl Format {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv",
+            Format::Json => "application/json",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+        }
+    }
+
+    // the bytes of `transactions`, `first` and `last` tell the position of
+    // the chunk within the whole export.
+    fn chunk(
+        &self,
+        transactions: &[Transaction],
+        first: bool,
+        last: bool,
+    ) -> Result<Vec<u8>, ServerError> {
+        match self {
+            Format::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(Vec::new());
+                if first {
+                    writer.write_record(Record::COLUMNS)?;
+                }
+                for transaction in transactions {
+                    writer.serialize(Record::from(transaction))?;
+                }
+                writer
+                    .into_inner()
+                    .map_err(|e| ServerError::Internal(e.to_string()))
+            }
+            Format::Json => {
+                let mut bytes = Vec::from(if first { "[" } else { "" });
+                for (i, transaction) in transactions.iter().enumerate() {
+                    if !first || i > 0 {
+                        bytes.push(b',');
+                    }
+                    serde_json::to_writer(&mut bytes, transaction)?;
+                }
+                if last {
+                    bytes.push(b']');
+                }
+                Ok(bytes)
+            }
+        }
+    }
+
+    // the export of `pages`, one chunk per page. pages are read as the
+    // chunks are written, so that large accounts are streamed to the client
+    // without being loaded at once.
+    pub fn chunks(
+        self,
+        pages: impl Iterator<Item = Result<Vec<Transaction>, ServerError>>,
+    ) -> impl Iterator<Item = Result<Vec<u8>, ServerError>> {
+        let mut pages = pages.peekable();
+        let mut first = true;
+        std::iter::from_fn(move || {
+            let page = match pages.next() {
+                Some(x) => x,
+                // an empty export is still a valid document
+                None if first => Ok(Vec::new()),
+                None => return None,
+            };
+            let last = pages.peek().is_none();
+            let chunk = page.and_then(|x| self.chunk(&x, first, last));
+            first = false;
+            Some(chunk)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn export(format: Format, transactions: Vec<Transaction>) -> String {
+        let pages = transactions.chunks(CHUNK).map(|x| Ok(x.to_vec()));
+        let bytes: Vec<u8> = format
+            .chunks(pages)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+            .concat();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_export() -> Result<(), ServerError> {
+        let cad = |x| (x, AssetId::currency("CAD"));
+        let account = Uuid::new_v4();
+        let date = NaiveDate::from_ymd_opt(2021, 1, 5).unwrap();
+        let mut transactions = vec![
+            Transaction::new(
+                account,
+                date,
+                TxnAction::Buy {
+                    asset: (dec!(10), AssetId::stock("TSE", "XEQT")),
+                    cash: cad(dec!(250.10)),
+                    fee: cad(dec!(4.95)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date,
+                TxnAction::Fee {
+                    value: cad(dec!(1)),
+                    reason: String::from("wire, fee"),
+                },
+            ),
+        ];
+        transactions.iter_mut().for_each(|x| x.id = Uuid::new_v4());
+
+        let csv = export(Format::Csv, transactions.clone());
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(3, lines.len());
+        assert_eq!(Record::COLUMNS.join(","), lines[0]);
+        assert_eq!(
+            format!(
+                "{},{},2021-01-05,Buy,XTSE:XEQT,10,,250.10,CURRENCY:CAD,4.95,\
+                 CURRENCY:CAD,,,,",
+                transactions[0].id, account
+            ),
+            lines[1]
+        );
+        assert!(lines[2].ends_with(",Fee,,,,1,CURRENCY:CAD,,,\"wire, fee\",,,"));
+        assert_eq!(1, export(Format::Csv, Vec::new()).lines().count());
+
+        // many chunks still make a single array
+        let many: Vec<_> = (0..CHUNK * 2 + 1)
+            .map(|_| transactions[0].clone())
+            .collect();
+        let json: Vec<Transaction> =
+            serde_json::from_str(&export(Format::Json, many))?;
+        assert_eq!(CHUNK * 2 + 1, json.len());
+        let json: Vec<Transaction> =
+            serde_json::from_str(&export(Format::Json, transactions.clone()))?;
+        assert_eq!(transactions, json);
+        assert_eq!(
+            transactions
+                .iter()
+                .map(|x| x.action.clone())
+                .collect::<Vec<_>>(),
+            json.into_iter().map(|x| x.action).collect::<Vec<_>>()
+        );
+        assert_eq!("[]", export(Format::Json, Vec::new()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use super::{amount, currency, date, parse_rows, security, Parser, Row};
+use crate::database::transaction::TxnAction;
+use crate::error::ServerError;
+use crate::import::RowError;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+// the column holding every field, and how dates are written. actions are
+// named after `TxnAction`, amounts are the cash paid or received.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Mapping {
+    pub date: String,
+    pub date_format: String,
+    pub action: String,
+    pub symbol: String,
+    pub quantity: String,
+    pub amount: String,
+    pub fee: String,
+    pub currency: String,
+    pub description: String,
+}
+
+impl Default for Mapping {
+    fn default() -> Self {
+        Self {
+            date: String::from("date"),
+            date_format: String::from("%Y-%m-%d"),
+            action: String::from("action"),
+            symbol: String::from("symbol"),
+            quantity: String::from("quantity"),
+            amount: String::from("amount"),
+            fee: String::from("fee"),
+            currency: String::from("currency"),
+            description: String::from("description"),
+        }
+    }
+}
+
+pub struct Generic(pub Mapping);
+
+impl Parser for Generic {
+    fn parse(
+        &self,
+        data: &str,
+    ) -> Result<(Vec<Row>, Vec<RowError>), ServerError> {
+        let m = &self.0;
+        let required = [&m.date, &m.action, &m.amount, &m.currency];
+
+        parse_rows(data, &required, |columns, record| {
+            let get = |name: &str| columns.get(record, name);
+            let date = date(get(&m.date), &m.date_format)?;
+            let currency = currency(get(&m.currency))?;
+            let cash = |x: Decimal| (x.abs(), currency.clone());
+            let value = amount(get(&m.amount), "amount")?;
+            let fee = match get(&m.fee) {
+                "" => Decimal::ZERO,
+                x => amount(x, "fee")?,
+            };
+            let reason = get(&m.description).to_string();
+
+            let action = get(&m.action);
+            let action = match action.to_lowercase().as_str() {
+                "deposit" => TxnAction::Deposit {
+                    value: cash(value),
+                    fee: cash(fee),
+                },
+                "withdrawal" => TxnAction::Withdrawal {
+                    value: cash(value),
+                    fee: cash(fee),
+                },
+                "income" => TxnAction::Income {
+                    value: cash(value),
+                    reason,
+                },
+                "fee" => TxnAction::Fee {
+                    value: cash(value),
+                    reason,
+                },
+                "buy" | "sell" => {
+                    let asset = security(get(&m.symbol))?;
+                    let quantity = amount(get(&m.quantity), "quantity")?;
+                    let asset = (quantity.abs(), asset);
+                    if action.eq_ignore_ascii_case("buy") {
+                        TxnAction::Buy {
+                            asset,
+                            cash: cash(value),
+                            fee: cash(fee),
+                        }
+                    } else {
+                        TxnAction::Sell {
+                            asset,
+                            cash: cash(value),
+                            fee: cash(fee),
+                        }
+                    }
+                }
+                "dividend" => TxnAction::Dividend {
+                    source: security(get(&m.symbol))?,
+                    value: cash(value),
+                    fee: cash(fee),
+                },
+                _ => return Err(format!("unsupported action \"{}\"", action)),
+            };
+            Ok(Some((date, action)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::asset::AssetId;
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> Result<(), ServerError> {
+        let mapping = Mapping {
+            date: String::from("Trade Date"),
+            date_format: String::from("%m/%d/%Y"),
+            amount: String::from("Total"),
+            ..Default::default()
+        };
+        let (rows, errors) = Generic(mapping).parse(
+            "Trade Date,Action,Symbol,Quantity,Total,Fee,Currency\n\
+             01/05/2021,BUY,XTSE:XEQT,10,250,9.99,CAD\n\
+             01/06/2021,Dividend,XEQT.TO,,1.25,,cad\n\
+             2021-01-07,Deposit,,,100,,CAD\n",
+        )?;
+        let cad = |x| (x, AssetId::currency("CAD"));
+        assert_eq!(2, rows.len());
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 1, 5), Some(rows[0].date));
+        assert_eq!(
+            TxnAction::Buy {
+                asset: (dec!(10), AssetId::stock("TSE", "XEQT")),
+                cash: cad(dec!(250)),
+                fee: cad(dec!(9.99)),
+            },
+            rows[0].action
+        );
+        assert_eq!(
+            TxnAction::Dividend {
+                source: AssetId::stock("TSE", "XEQT"),
+                value: cad(dec!(1.25)),
+                fee: cad(dec!(0)),
+            },
+            rows[1].action
+        );
+        assert_eq!(
+            vec![RowError::new(4, "invalid date \"2021-01-07\"")],
+            errors
+        );
+
+        Ok(())
+    }
+}
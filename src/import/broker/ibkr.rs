@@ -0,0 +1,452 @@
+use super::{amount, currency, Parser, Positions, Row};
+use crate::database::asset::AssetId;
+use crate::database::transaction::TxnAction;
+use crate::error::ServerError;
+use crate::import::RowError;
+use chrono::NaiveDate;
+use roxmltree::{Document, Node};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+type Action = Result<Option<(NaiveDate, TxnAction)>, String>;
+
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> &'a str {
+    node.attribute(name).unwrap_or_default()
+}
+
+// dates are written as 20210105 or 2021-01-05, optionally followed by a time
+fn date(text: &str) -> Result<NaiveDate, String> {
+    let date: String = text
+        .chars()
+        .take_while(|x| x.is_ascii_digit() || *x == '-')
+        .filter(|x| *x != '-')
+        .collect();
+    NaiveDate::parse_from_str(&date, "%Y%m%d")
+        .map_err(|_| format!("invalid date \"{}\"", text))
+}
+
+// zero if the attribute is missing
+fn number(node: Node, name: &str) -> Result<Decimal, String> {
+    match attribute(node, name) {
+        "" => Ok(Decimal::ZERO),
+        x => amount(x, name),
+    }
+}
+
+// ibkr separates the class of a share with a space, e.g. BRK B
+fn security(node: Node) -> Result<AssetId, String> {
+    let symbol = attribute(node, "symbol").replace(' ', ".");
+    let exchange = match attribute(node, "listingExchange") {
+        "" => return super::security(&symbol),
+        "VENTURE" => "TSXV",
+        "ARCA" => "NYSEARCA",
+        x => x,
+    };
+    if symbol.is_empty() {
+        return Err(String::from("missing symbol"));
+    }
+    Ok(AssetId::stock(exchange, &symbol))
+}
+
+// the flex query statement of interactive brokers, saved as xml
+pub struct Ibkr;
+
+impl Ibkr {
+    fn trade(node: Node) -> Action {
+        // orders and summaries repeat the executions
+        if !matches!(attribute(node, "levelOfDetail"), "" | "EXECUTION") {
+            return Ok(None);
+        }
+        let date = date(attribute(node, "tradeDate"))?;
+        let currency = currency(attribute(node, "currency"))?;
+        let asset = match attribute(node, "assetCategory") {
+            // the symbol of a conversion is its pair, e.g. USD.CAD
+            "CASH" => {
+                let symbol = attribute(node, "symbol");
+                super::currency(symbol.split('.').next().unwrap_or_default())?
+            }
+            "STK" | "ETF" => security(node)?,
+            x => return Err(format!("unsupported asset category \"{}\"", x)),
+        };
+        let asset = (number(node, "quantity")?.abs(), asset);
+        let cash = (number(node, "proceeds")?.abs(), currency.clone());
+        // commissions may be charged in another currency
+        let fee = (
+            number(node, "ibCommission")?.abs(),
+            match attribute(node, "ibCommissionCurrency") {
+                "" => currency,
+                x => super::currency(x)?,
+            },
+        );
+
+        let action = match attribute(node, "buySell") {
+            "BUY" => TxnAction::Buy { asset, cash, fee },
+            "SELL" => TxnAction::Sell { asset, cash, fee },
+            x => return Err(format!("unsupported side \"{}\"", x)),
+        };
+        Ok(Some((date, action)))
+    }
+
+    fn cash_transaction(node: Node) -> Action {
+        let date = date(attribute(node, "dateTime"))?;
+        let currency = currency(attribute(node, "currency"))?;
+        let cash = |x: Decimal| (x.abs(), currency.clone());
+        let value = number(node, "amount")?;
+        let reason = attribute(node, "description").to_string();
+
+        let kind = attribute(node, "type");
+        let action = match kind {
+            _ if value.is_zero() => return Ok(None),
+            "Dividends" | "Payment In Lieu Of Dividends"
+                if value > Decimal::ZERO =>
+            {
+                TxnAction::Dividend {
+                    source: security(node)?,
+                    value: cash(value),
+                    fee: cash(Decimal::ZERO),
+                }
+            }
+            "Deposits/Withdrawals" | "Deposits & Withdrawals"
+                if value < Decimal::ZERO =>
+            {
+                TxnAction::Withdrawal {
+                    value: cash(value),
+                    fee: cash(Decimal::ZERO),
+                }
+            }
+            "Deposits/Withdrawals" | "Deposits & Withdrawals" => {
+                TxnAction::Deposit {
+                    value: cash(value),
+                    fee: cash(Decimal::ZERO),
+                }
+            }
+            // reversals and refunds are reported with the opposite sign
+            "Dividends"
+            | "Payment In Lieu Of Dividends"
+            | "Withholding Tax"
+            | "Broker Interest Received"
+            | "Broker Interest Paid"
+            | "Bond Interest Received"
+            | "Bond Interest Paid"
+            | "Other Fees"
+            | "Commission Adjustments" => {
+                if value < Decimal::ZERO {
+                    TxnAction::Fee {
+                        value: cash(value),
+                        reason,
+                    }
+                } else {
+                    TxnAction::Income {
+                        value: cash(value),
+                        reason,
+                    }
+                }
+            }
+            _ => return Err(format!("unsupported transaction \"{}\"", kind)),
+        };
+        Ok(Some((date, action)))
+    }
+
+    // the quantity of a security added or removed by a corporate action,
+    // splits are left to the split history of the security. the statement
+    // tells neither the ACB allocated by a merger or a spin-off nor the
+    // other side of it, they have to be recorded as such by hand.
+    fn corporate_action(node: Node) -> Action {
+        match attribute(node, "type") {
+            "FS" | "RS" => return Ok(None),
+            "TC" => return Err(String::from("record the merger by hand")),
+            "SO" => return Err(String::from("record the spin-off by hand")),
+            _ => (),
+        }
+        let date = match attribute(node, "dateTime") {
+            "" => date(attribute(node, "reportDate"))?,
+            x => date(x)?,
+        };
+        let currency = currency(attribute(node, "currency"))?;
+        let quantity = number(node, "quantity")?;
+        let asset = (quantity.abs(), security(node)?);
+        let cash = (number(node, "proceeds")?.abs(), currency.clone());
+        let fee = (Decimal::ZERO, currency);
+
+        let action = if quantity > Decimal::ZERO {
+            TxnAction::Buy { asset, cash, fee }
+        } else if quantity < Decimal::ZERO {
+            TxnAction::Sell { asset, cash, fee }
+        } else {
+            return Err(String::from("missing quantity"));
+        };
+        Ok(Some((date, action)))
+    }
+
+    // folds the tax withheld from a dividend into its fee
+    fn withhold(rows: &mut [Row], node: Node) -> Result<bool, String> {
+        let date = date(attribute(node, "dateTime"))?;
+        let tax = (
+            number(node, "amount")?.abs(),
+            currency(attribute(node, "currency"))?,
+        );
+        let source = match attribute(node, "symbol") {
+            "" => return Ok(false),
+            _ => security(node)?,
+        };
+
+        let dividend = rows.iter_mut().find_map(|x| match &mut x.action {
+            TxnAction::Dividend {
+                source: s,
+                value,
+                fee,
+            } if x.date == date
+                && *s == source
+                && value.1 == tax.1
+                && fee.1 == tax.1 =>
+            {
+                Some(fee)
+            }
+            _ => None,
+        });
+        match dividend {
+            Some(fee) => {
+                fee.0 += tax.0;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+impl Parser for Ibkr {
+    fn parse(
+        &self,
+        data: &str,
+    ) -> Result<(Vec<Row>, Vec<RowError>), ServerError> {
+        let document = match Document::parse(data) {
+            Ok(x) => x,
+            Err(e) => {
+                let error = RowError::new(e.pos().row as u64, e.to_string());
+                return Ok((Vec::new(), vec![error]));
+            }
+        };
+        let line =
+            |node: Node| document.text_pos_at(node.range().start).row as u64;
+
+        let mut rows = Vec::new();
+        let mut errors = Vec::new();
+        let mut withholding = Vec::new();
+        for node in document.descendants() {
+            let action = match node.tag_name().name() {
+                "Trade" => Self::trade(node),
+                "CashTransaction"
+                    if attribute(node, "type") == "Withholding Tax"
+                        && number(node, "amount")
+                            .is_ok_and(|x| x < Decimal::ZERO) =>
+                {
+                    withholding.push(node);
+                    continue;
+                }
+                "CashTransaction" => Self::cash_transaction(node),
+                "CorporateAction" => Self::corporate_action(node),
+                _ => continue,
+            };
+            match action {
+                Ok(Some((date, action))) => rows.push(Row {
+                    line: line(node),
+                    date,
+                    action,
+                }),
+                Ok(None) => (),
+                Err(e) => errors.push(RowError::new(line(node), e)),
+            }
+        }
+
+        // the tax withheld from other income is a fee of its own
+        for node in withholding {
+            match Self::withhold(&mut rows, node) {
+                Ok(true) => (),
+                Ok(false) => match Self::cash_transaction(node) {
+                    Ok(Some((date, action))) => rows.push(Row {
+                        line: line(node),
+                        date,
+                        action,
+                    }),
+                    Ok(None) => (),
+                    Err(e) => errors.push(RowError::new(line(node), e)),
+                },
+                Err(e) => errors.push(RowError::new(line(node), e)),
+            }
+        }
+        rows.sort_by_key(|x| x.line);
+        Ok((rows, errors))
+    }
+
+    fn positions(&self, data: &str) -> Result<Option<Positions>, ServerError> {
+        let document = match Document::parse(data) {
+            Ok(x) => x,
+            Err(_) => return Ok(None),
+        };
+        let nodes: Vec<_> = document
+            .descendants()
+            .filter(|x| x.has_tag_name("OpenPosition"))
+            .filter(|x| {
+                matches!(attribute(*x, "levelOfDetail"), "" | "SUMMARY")
+                    && matches!(attribute(*x, "assetCategory"), "STK" | "ETF")
+            })
+            .collect();
+        if !document
+            .descendants()
+            .any(|x| x.has_tag_name("OpenPositions"))
+        {
+            return Ok(None);
+        }
+        let statement = document
+            .descendants()
+            .find(|x| x.has_tag_name("FlexStatement"));
+        let date = nodes
+            .first()
+            .map(|x| attribute(*x, "reportDate"))
+            .or(statement.map(|x| attribute(x, "toDate")))
+            .and_then(|x| date(x).ok());
+        let date = match date {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+
+        let mut assets = BTreeMap::new();
+        for node in nodes {
+            if let (Ok(asset), Ok(quantity)) =
+                (security(node), number(node, "position"))
+            {
+                *assets.entry(asset).or_default() += quantity;
+            }
+        }
+        Ok(Some(Positions { date, assets }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::account::AccountKind;
+    use crate::database::{self, Account, User};
+    use crate::import::broker::{Mismatch, Preview};
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_parse() -> Result<(), ServerError> {
+        let (rows, errors) = Ibkr.parse(include_str!("fixture/flex.xml"))?;
+        let cad = |x| (x, AssetId::currency("CAD"));
+        let usd = |x| (x, AssetId::currency("USD"));
+        let aapl = AssetId::stock("NASDAQ", "AAPL");
+        assert_eq!(
+            vec![
+                TxnAction::Buy {
+                    asset: (dec!(1000), AssetId::currency("USD")),
+                    cash: cad(dec!(1270)),
+                    fee: usd(dec!(2)),
+                },
+                TxnAction::Buy {
+                    asset: (dec!(10), aapl.clone()),
+                    cash: usd(dec!(1300)),
+                    fee: usd(dec!(1)),
+                },
+                TxnAction::Sell {
+                    asset: (dec!(5), AssetId::stock("TSE", "XEQT")),
+                    cash: cad(dec!(130)),
+                    fee: usd(dec!(1.5)),
+                },
+                TxnAction::Deposit {
+                    value: cad(dec!(2000)),
+                    fee: cad(dec!(0)),
+                },
+                TxnAction::Dividend {
+                    source: aapl.clone(),
+                    value: usd(dec!(2.05)),
+                    fee: usd(dec!(0.31)),
+                },
+                TxnAction::Fee {
+                    value: usd(dec!(0.12)),
+                    reason: String::from(
+                        "USD CREDIT INT FOR FEB-2021 - US TAX"
+                    ),
+                },
+                TxnAction::Income {
+                    value: usd(dec!(0.8)),
+                    reason: String::from("USD CREDIT INT FOR FEB-2021"),
+                },
+                TxnAction::Fee {
+                    value: usd(dec!(10)),
+                    reason: String::from("MARKET DATA FEE"),
+                },
+            ],
+            rows.iter().map(|x| x.action.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2021, 3, 5),
+            rows.last().map(|x| x.date)
+        );
+        assert_eq!(
+            vec![
+                RowError::new(9, "unsupported asset category \"OPT\""),
+                RowError::new(18, "unsupported transaction \"Bill Pay\""),
+                RowError::new(22, "record the spin-off by hand"),
+                RowError::new(23, "record the merger by hand"),
+                RowError::new(24, "record the merger by hand"),
+            ],
+            errors
+        );
+
+        assert!(Ibkr.parse("<FlexQueryResponse>")?.0.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconcile() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let tran = conn.transaction()?;
+        database::migration::run_migration(&tran)?;
+        let mut u0 = User::new(
+            String::from("test_user"),
+            Sha256::digest("password").to_vec(),
+        );
+        u0.id = u0.insert(&tran)?;
+        let mut a0 =
+            Account::new("test_account", "alias", u0.id, AccountKind::NRA);
+        a0.id = a0.insert(&tran)?;
+
+        // the split of aapl is missing from its history, ibm was bought
+        // before the statement and the spin-off of kd is left out.
+        let preview = Preview::new(
+            &Ibkr,
+            a0.id,
+            include_str!("fixture/flex.xml"),
+            &tran,
+        )?;
+        assert_eq!(
+            vec![
+                Mismatch {
+                    asset: AssetId::stock("NASDAQ", "AAPL"),
+                    expected: dec!(40),
+                    actual: dec!(10),
+                },
+                Mismatch {
+                    asset: AssetId::stock("NYSE", "IBM"),
+                    expected: dec!(10),
+                    actual: dec!(0),
+                },
+                Mismatch {
+                    asset: AssetId::stock("NYSE", "KD"),
+                    expected: dec!(2),
+                    actual: dec!(0),
+                },
+                Mismatch {
+                    asset: AssetId::stock("TSE", "XEQT"),
+                    expected: dec!(0),
+                    actual: dec!(-5),
+                },
+            ],
+            preview.mismatches
+        );
+        Ok(())
+    }
+}
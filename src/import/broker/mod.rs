@@ -0,0 +1,392 @@
+mod generic;
+mod ibkr;
+mod ofx;
+mod questrade;
+mod wealthsimple;
+
+use super::RowError;
+use crate::database::asset::AssetId;
+use crate::database::transaction::TxnAction;
+use crate::database::{Account, Transaction};
+use crate::error::ServerError;
+use crate::portfolio::{Holding, Splits};
+use crate::repository::security;
+use chrono::NaiveDate;
+use csv::StringRecord;
+pub use generic::{Generic, Mapping};
+pub use ibkr::Ibkr;
+pub use ofx::Ofx;
+pub use questrade::Questrade;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::str::FromStr;
+use uuid::Uuid;
+pub use wealthsimple::Wealthsimple;
+
+// a transaction read from `line` of an activity export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub line: u64,
+    pub date: NaiveDate,
+    pub action: TxnAction,
+}
+
+// the quantity of every security held at the end of a statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Positions {
+    pub date: NaiveDate,
+    pub assets: BTreeMap<AssetId, Decimal>,
+}
+
+// maps the activity export of a broker into transactions
+pub trait Parser {
+    fn parse(
+        &self,
+        data: &str,
+    ) -> Result<(Vec<Row>, Vec<RowError>), ServerError>;
+
+    // the positions reported along with the transactions, if any
+    fn positions(&self, _data: &str) -> Result<Option<Positions>, ServerError> {
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum Format {
+    Questrade,
+    Wealthsimple,
+    Ofx,
+    Ibkr,
+    Generic(Box<Mapping>),
+}
+
+impl Parser for Format {
+    fn parse(
+        &self,
+        data: &str,
+    ) -> Result<(Vec<Row>, Vec<RowError>), ServerError> {
+        match self {
+            Format::Questrade => Questrade.parse(data),
+            Format::Wealthsimple => Wealthsimple.parse(data),
+            Format::Ofx => Ofx.parse(data),
+            Format::Ibkr => Ibkr.parse(data),
+            Format::Generic(mapping) => Generic(*mapping.clone()).parse(data),
+        }
+    }
+
+    fn positions(&self, data: &str) -> Result<Option<Positions>, ServerError> {
+        match self {
+            Format::Ibkr => Ibkr.positions(data),
+            _ => Ok(None),
+        }
+    }
+}
+
+// columns of a csv file, names are case insensitive
+struct Columns(HashMap<String, usize>);
+
+impl Columns {
+    // empty if the column is missing
+    fn get<'a>(&self, record: &'a StringRecord, name: &str) -> &'a str {
+        self.0
+            .get(&name.to_lowercase())
+            .and_then(|i| record.get(*i))
+            .unwrap_or_default()
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(&name.to_lowercase())
+    }
+}
+
+// reads every record of `data` with `row`, which returns none for the
+// records that are not transactions.
+fn parse_rows(
+    data: &str,
+    required: &[impl AsRef<str>],
+    row: impl Fn(
+        &Columns,
+        &StringRecord,
+    ) -> Result<Option<(NaiveDate, TxnAction)>, String>,
+) -> Result<(Vec<Row>, Vec<RowError>), ServerError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(data.as_bytes());
+    let columns = Columns(
+        reader
+            .headers()?
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (x.to_lowercase(), i))
+            .collect(),
+    );
+    if let Some(name) = required.iter().find(|x| !columns.contains(x.as_ref()))
+    {
+        let error =
+            RowError::new(1, format!("missing column \"{}\"", name.as_ref()));
+        return Ok((Vec::new(), vec![error]));
+    }
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(x) => x,
+            Err(e) => {
+                let line = e.position().map_or(0, |x| x.line());
+                errors.push(RowError::new(line, e.to_string()));
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |x| x.line());
+        match row(&columns, &record) {
+            Ok(Some((date, action))) => rows.push(Row { line, date, action }),
+            Ok(None) => (),
+            Err(e) => errors.push(RowError::new(line, e)),
+        }
+    }
+    Ok((rows, errors))
+}
+
+// the date part of `text`, which may be followed by a time
+fn date(text: &str, format: &str) -> Result<NaiveDate, String> {
+    let date = text.split_whitespace().next().unwrap_or_default();
+    NaiveDate::parse_from_str(date, format)
+        .map_err(|_| format!("invalid date \"{}\"", text))
+}
+
+// a number that may be written with a dollar sign and thousand separators
+fn amount(text: &str, name: &str) -> Result<Decimal, String> {
+    let value: String =
+        text.chars().filter(|x| *x != '$' && *x != ',').collect();
+    if value.is_empty() {
+        return Err(format!("missing {}", name));
+    }
+    Decimal::from_str(&value)
+        .or_else(|_| Decimal::from_scientific(&value))
+        .map_err(|_| format!("invalid {} \"{}\"", name, text))
+}
+
+fn currency(text: &str) -> Result<AssetId, String> {
+    super::currency(text)
+        .ok_or_else(|| format!("unknown currency \"{}\"", text))
+}
+
+// a security written as an `AssetId`, with the exchange suffix used by
+// brokers, e.g. XEQT.TO, or as a ticker known to the security catalog.
+fn security(text: &str) -> Result<AssetId, String> {
+    const SUFFIXES: [(&str, &str); 5] = [
+        (".TO", "TSE"),
+        (".VN", "TSXV"),
+        (".V", "TSXV"),
+        (".NE", "NEO"),
+        (".CN", "CNSX"),
+    ];
+
+    if let Ok(asset) = AssetId::try_from(text.to_string()) {
+        return Ok(asset);
+    }
+    let symbol = text.trim().to_uppercase();
+    if symbol.is_empty() {
+        return Err(String::from("missing symbol"));
+    }
+    for (suffix, exchange) in SUFFIXES {
+        if let Some(ticker) = symbol.strip_suffix(suffix) {
+            return Ok(AssetId::stock(exchange, ticker));
+        }
+    }
+    security::find(&symbol)
+        .ok_or_else(|| format!("unknown exchange of symbol \"{}\"", text))
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Entry {
+    pub line: u64,
+    pub transaction: Transaction,
+    // the same transaction is already stored in the account
+    pub duplicate: bool,
+}
+
+// a position of the statement which differs from the account once the
+// transactions are imported
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub asset: AssetId,
+    pub expected: Decimal,
+    pub actual: Decimal,
+}
+
+// transactions to be imported into an account, along with the rows which
+// cannot be.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct Preview {
+    pub entries: Vec<Entry>,
+    pub errors: Vec<RowError>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Preview {
+    // an entry is a duplicate when a stored transaction has the same date and
+    // action, and has not matched an earlier entry.
+    pub fn new(
+        parser: &(impl Parser + ?Sized),
+        account: Uuid,
+        data: &str,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<Self, ServerError> {
+        let (rows, errors) = parser.parse(data)?;
+        let mut stored = Transaction::by_account(account, transaction)?;
+
+        let entries =
+            rows.into_iter()
+                .map(|row| {
+                    let duplicate = match stored.iter().position(|x| {
+                        x.date == row.date && x.action == row.action
+                    }) {
+                        Some(i) => {
+                            stored.swap_remove(i);
+                            true
+                        }
+                        None => false,
+                    };
+                    Entry {
+                        line: row.line,
+                        transaction: Transaction::new(
+                            account, row.date, row.action,
+                        ),
+                        duplicate,
+                    }
+                })
+                .collect();
+        let mut preview = Self {
+            entries,
+            errors,
+            mismatches: Vec::new(),
+        };
+        if let Some(positions) = parser.positions(data)? {
+            preview.mismatches =
+                preview.reconcile(&positions, account, transaction)?;
+        }
+        Ok(preview)
+    }
+
+    // replays the account with the entries up to the end of the statement
+    fn reconcile(
+        &self,
+        positions: &Positions,
+        account: Uuid,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<Vec<Mismatch>, ServerError> {
+        let owner = Account::by_id(account, transaction)?.map(|x| x.owner);
+        let mut transactions = Transaction::by_account(account, transaction)?;
+        transactions.extend(
+            self.entries
+                .iter()
+                .filter(|x| !x.duplicate)
+                .map(|x| x.transaction.clone()),
+        );
+        let splits =
+            Splits::by_transactions(&transactions, owner, transaction)?;
+        let holding = Holding::replay(&transactions, &splits, positions.date);
+
+        let assets: BTreeSet<_> = positions
+            .assets
+            .keys()
+            .chain(holding.assets.keys())
+            .collect();
+        Ok(assets
+            .into_iter()
+            .map(|asset| Mismatch {
+                asset: asset.clone(),
+                expected: positions
+                    .assets
+                    .get(asset)
+                    .cloned()
+                    .unwrap_or_default(),
+                actual: holding.quantity(asset),
+            })
+            .filter(|x| x.expected != x.actual)
+            .collect())
+    }
+
+    // stores every entry but the duplicates, returns how many are stored
+    pub fn commit(
+        &self,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<usize, ServerError> {
+        let mut count = 0;
+        for entry in self.entries.iter().filter(|x| !x.duplicate) {
+            entry.transaction.insert(transaction)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::account::AccountKind;
+    use crate::database::{self, Account, User};
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_security() {
+        assert_eq!(Ok(AssetId::stock("TSE", "XEQT")), security("xeqt.to"));
+        assert_eq!(Ok(AssetId::stock("TSE", "DLR.U")), security("DLR.U.TO"));
+        assert_eq!(Ok(AssetId::stock("TSE", "RY")), security("XTSE:RY"));
+        assert_eq!(Ok(AssetId::stock("TSE", "RY")), security("RY"));
+        assert!(security("ZZZZZ").is_err());
+        assert_eq!(Ok(dec!(-1234.5)), amount("-$1,234.50", "amount"));
+    }
+
+    #[test]
+    fn test_preview() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let tran = conn.transaction()?;
+        database::migration::run_migration(&tran)?;
+        let mut u0 = User::new(
+            String::from("test_user"),
+            Sha256::digest("password").to_vec(),
+        );
+        u0.id = u0.insert(&tran)?;
+        let mut a0 =
+            Account::new("test_account", "alias", u0.id, AccountKind::NRA);
+        a0.id = a0.insert(&tran)?;
+
+        let data = "date,action,symbol,quantity,amount,currency\n\
+                    2021-01-04,Deposit,,,1000,CAD\n\
+                    2021-01-04,Deposit,,,1000,CAD\n\
+                    2021-01-05,Buy,XEQT.TO,10,250,CAD\n\
+                    2021-01-06,Transfer,,,1,CAD\n";
+        let format = Format::Generic(Box::default());
+        let preview = Preview::new(&format, a0.id, data, &tran)?;
+        assert_eq!(3, preview.entries.len());
+        assert_eq!(
+            vec![RowError::new(5, "unsupported action \"Transfer\"")],
+            preview.errors
+        );
+        assert!(preview.entries.iter().all(|x| !x.duplicate));
+
+        // one of the two deposits is stored already
+        preview.entries[0].transaction.insert(&tran)?;
+        let preview = Preview::new(&format, a0.id, data, &tran)?;
+        assert_eq!(
+            vec![true, false, false],
+            preview
+                .entries
+                .iter()
+                .map(|x| x.duplicate)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, preview.commit(&tran)?);
+        assert_eq!(3, Transaction::by_account(a0.id, &tran)?.len());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,513 @@
+use super::{amount, currency, security, Parser, Row};
+use crate::database::asset::AssetId;
+use crate::database::transaction::TxnAction;
+use crate::error::ServerError;
+use crate::import::RowError;
+use crate::repository::security::find_cusip;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+// an element of an ofx document. ofx 1.x is sgml where leaf elements are
+// not closed, ofx 2.x is xml, both are read into the same tree.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    line: u64,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|x| x.name == name)
+    }
+
+    // text of the descendant at `path`, empty if missing
+    fn value(&self, path: &[&str]) -> &str {
+        let mut element = self;
+        for name in path {
+            match element.child(name) {
+                Some(x) => element = x,
+                None => return "",
+            }
+        }
+        &element.text
+    }
+
+    // every descendant named `name`, outer ones first
+    fn find_all<'a>(&'a self, name: &str, result: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if child.name == name {
+                result.push(child);
+            }
+            child.find_all(name, result);
+        }
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn parse_document(data: &str) -> Result<Element, String> {
+    let start = data
+        .find("<OFX>")
+        .ok_or_else(|| String::from("missing OFX element"))?;
+    let mut line = data[..start].matches('\n').count() as u64 + 1;
+    let mut rest = &data[start..];
+
+    let mut stack = vec![Element::default()];
+    // a leaf closed at its text, its closing tag is optional
+    let mut leaf: Option<String> = None;
+    while let Some(open) = rest.find('<') {
+        let text = rest[..open].trim();
+        line += rest[..open].matches('\n').count() as u64;
+        if !text.is_empty() && stack.len() > 1 {
+            let mut element = stack.pop().unwrap_or_default();
+            element.text = unescape(text);
+            leaf = Some(element.name.clone());
+            if let Some(parent) = stack.last_mut() {
+                parent.children.push(element);
+            }
+        }
+
+        let close = rest[open..]
+            .find('>')
+            .ok_or_else(|| format!("unclosed tag on line {}", line))?;
+        let tag = &rest[open + 1..open + close];
+        rest = &rest[open + close + 1..];
+
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        } else if let Some(name) = tag.strip_prefix('/') {
+            if leaf.take().as_deref() == Some(name) {
+                continue;
+            }
+            // closes every element left open in between
+            while stack.len() > 1 {
+                let element = stack.pop().unwrap_or_default();
+                let done = element.name == name;
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(element);
+                }
+                if done {
+                    break;
+                }
+            }
+        } else {
+            leaf = None;
+            let name = tag.trim_end_matches('/').trim();
+            let element = Element {
+                name: name.to_string(),
+                line,
+                ..Default::default()
+            };
+            if tag.ends_with('/') {
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(element);
+                }
+            } else {
+                stack.push(element);
+            }
+        }
+    }
+    while stack.len() > 1 {
+        let element = stack.pop().unwrap_or_default();
+        if let Some(parent) = stack.last_mut() {
+            parent.children.push(element);
+        }
+    }
+
+    let mut root = stack.pop().unwrap_or_default();
+    root.children
+        .pop()
+        .ok_or_else(|| String::from("missing OFX element"))
+}
+
+// dates are written as YYYYMMDD followed by an optional time and timezone
+fn date(text: &str) -> Result<NaiveDate, String> {
+    text.get(..8)
+        .and_then(|x| NaiveDate::parse_from_str(x, "%Y%m%d").ok())
+        .ok_or_else(|| format!("invalid date \"{}\"", text))
+}
+
+// investment statements in ofx or qfx, securities are looked up by the
+// ticker listed in the statement, or by their CUSIP in the catalog.
+pub struct Ofx;
+
+struct Statement<'a> {
+    currency: &'a str,
+    // ticker of every security id
+    tickers: &'a HashMap<&'a str, &'a str>,
+}
+
+impl Statement<'_> {
+    fn currency(&self, element: &Element) -> Result<AssetId, String> {
+        let symbol = match element.value(&["CURRENCY", "CURSYM"]) {
+            "" => match element.value(&["ORIGCURRENCY", "CURSYM"]) {
+                "" => self.currency,
+                x => x,
+            },
+            x => x,
+        };
+        currency(symbol)
+    }
+
+    fn security(&self, element: &Element) -> Result<AssetId, String> {
+        let id = element.value(&["SECID", "UNIQUEID"]);
+        let kind = element.value(&["SECID", "UNIQUEIDTYPE"]);
+        match self.tickers.get(id) {
+            Some(ticker) => security(ticker),
+            None if kind == "TICKER" => security(id),
+            None if kind == "CUSIP" => find_cusip(id)
+                .ok_or_else(|| format!("unknown security \"{}\"", id)),
+            None => Err(format!("unknown security \"{}\"", id)),
+        }
+    }
+
+    fn number(element: &Element, name: &str) -> Result<Decimal, String> {
+        match element.value(&[name]) {
+            "" => Ok(Decimal::ZERO),
+            x => amount(x, &name.to_lowercase()),
+        }
+    }
+
+    // the transaction in `element`, two for a reinvestment or a return of
+    // capital
+    fn parse(
+        &self,
+        element: &Element,
+    ) -> Result<Vec<(NaiveDate, TxnAction)>, String> {
+        // trades keep their currency with the rest of their detail
+        let currency =
+            match element.child("INVBUY").or(element.child("INVSELL")) {
+                Some(x) => self.currency(x)?,
+                None => self.currency(element)?,
+            };
+        let cash = |x: Decimal| (x.abs(), currency.clone());
+        let memo = element.value(&["INVTRAN", "MEMO"]).to_string();
+
+        match element.name.as_str() {
+            "BUYSTOCK" | "BUYMF" | "SELLSTOCK" | "SELLMF" => {
+                let buy = element.name.starts_with("BUY");
+                let detail = element
+                    .child(if buy { "INVBUY" } else { "INVSELL" })
+                    .ok_or_else(|| String::from("missing trade detail"))?;
+                let date = date(detail.value(&["INVTRAN", "DTTRADE"]))?;
+                let asset = (
+                    Self::number(detail, "UNITS")?.abs(),
+                    self.security(detail)?,
+                );
+                let fee = Self::number(detail, "COMMISSION")?
+                    + Self::number(detail, "FEES")?;
+                let total = Self::number(detail, "TOTAL")?.abs();
+                let action = if buy {
+                    TxnAction::Buy {
+                        asset,
+                        cash: cash(total - fee),
+                        fee: cash(fee),
+                    }
+                } else {
+                    TxnAction::Sell {
+                        asset,
+                        cash: cash(total + fee),
+                        fee: cash(fee),
+                    }
+                };
+                Ok(vec![(date, action)])
+            }
+            "INCOME" => {
+                let date = date(element.value(&["INVTRAN", "DTTRADE"]))?;
+                let total = Self::number(element, "TOTAL")?;
+                let action = match element.value(&["INCOMETYPE"]) {
+                    _ if total < Decimal::ZERO => TxnAction::Fee {
+                        value: cash(total),
+                        reason: memo,
+                    },
+                    "DIV" | "CGLONG" | "CGSHORT" => TxnAction::Dividend {
+                        source: self.security(element)?,
+                        value: cash(total),
+                        fee: cash(Decimal::ZERO),
+                    },
+                    "INTEREST" => TxnAction::Income {
+                        value: cash(total),
+                        reason: String::from("Interest"),
+                    },
+                    _ => TxnAction::Income {
+                        value: cash(total),
+                        reason: memo,
+                    },
+                };
+                Ok(vec![(date, action)])
+            }
+            "REINVEST" => {
+                let date = date(element.value(&["INVTRAN", "DTTRADE"]))?;
+                let asset = self.security(element)?;
+                let fee = Self::number(element, "COMMISSION")?
+                    + Self::number(element, "FEES")?;
+                let total = Self::number(element, "TOTAL")?.abs();
+                Ok(vec![
+                    (
+                        date,
+                        TxnAction::Dividend {
+                            source: asset.clone(),
+                            value: cash(total),
+                            fee: cash(Decimal::ZERO),
+                        },
+                    ),
+                    (
+                        date,
+                        TxnAction::Buy {
+                            asset: (Self::number(element, "UNITS")?, asset),
+                            cash: cash(total - fee),
+                            fee: cash(fee),
+                        },
+                    ),
+                ])
+            }
+            // paid in cash, which also reduces the ACB
+            "RETOFCAP" => {
+                let date = date(element.value(&["INVTRAN", "DTTRADE"]))?;
+                let source = self.security(element)?;
+                let total = Self::number(element, "TOTAL")?;
+                Ok(vec![
+                    (
+                        date,
+                        TxnAction::Dividend {
+                            source: source.clone(),
+                            value: cash(total),
+                            fee: cash(Decimal::ZERO),
+                        },
+                    ),
+                    (
+                        date,
+                        TxnAction::ReturnOfCapital {
+                            source,
+                            value: cash(total),
+                        },
+                    ),
+                ])
+            }
+            "INVBANKTRAN" => {
+                let detail = element
+                    .child("STMTTRN")
+                    .ok_or_else(|| String::from("missing bank transaction"))?;
+                let date = date(detail.value(&["DTPOSTED"]))?;
+                let total = Self::number(detail, "TRNAMT")?;
+                let reason = match detail.value(&["MEMO"]) {
+                    "" => detail.value(&["NAME"]),
+                    x => x,
+                }
+                .to_string();
+                let action = match detail.value(&["TRNTYPE"]) {
+                    "INT" | "DIV" => TxnAction::Income {
+                        value: cash(total),
+                        reason,
+                    },
+                    "FEE" | "SRVCHG" => TxnAction::Fee {
+                        value: cash(total),
+                        reason,
+                    },
+                    _ if total < Decimal::ZERO => TxnAction::Withdrawal {
+                        value: cash(total),
+                        fee: cash(Decimal::ZERO),
+                    },
+                    _ => TxnAction::Deposit {
+                        value: cash(total),
+                        fee: cash(Decimal::ZERO),
+                    },
+                };
+                Ok(vec![(date, action)])
+            }
+            x => Err(format!("unsupported transaction \"{}\"", x)),
+        }
+    }
+}
+
+impl Parser for Ofx {
+    fn parse(
+        &self,
+        data: &str,
+    ) -> Result<(Vec<Row>, Vec<RowError>), ServerError> {
+        let document = match parse_document(data) {
+            Ok(x) => x,
+            Err(e) => return Ok((Vec::new(), vec![RowError::new(1, e)])),
+        };
+
+        let mut securities = Vec::new();
+        document.find_all("SECINFO", &mut securities);
+        let tickers: HashMap<_, _> = securities
+            .iter()
+            .filter(|x| !x.value(&["TICKER"]).is_empty())
+            .map(|x| (x.value(&["SECID", "UNIQUEID"]), x.value(&["TICKER"])))
+            .collect();
+
+        let mut rows = Vec::new();
+        let mut errors = Vec::new();
+        let mut statements = Vec::new();
+        document.find_all("INVSTMTRS", &mut statements);
+        for element in statements {
+            let statement = Statement {
+                currency: element.value(&["CURDEF"]),
+                tickers: &tickers,
+            };
+            let list = match element.child("INVTRANLIST") {
+                Some(x) => x,
+                None => continue,
+            };
+            for record in list
+                .children
+                .iter()
+                .filter(|x| !matches!(x.name.as_str(), "DTSTART" | "DTEND"))
+            {
+                match statement.parse(record) {
+                    Ok(x) => {
+                        rows.extend(x.into_iter().map(|(date, action)| Row {
+                            line: record.line,
+                            date,
+                            action,
+                        }))
+                    }
+                    Err(e) => errors.push(RowError::new(record.line, e)),
+                }
+            }
+        }
+        Ok((rows, errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    fn expected() -> Vec<(NaiveDate, TxnAction)> {
+        let cad = |x| (x, AssetId::currency("CAD"));
+        let usd = |x| (x, AssetId::currency("USD"));
+        let xeqt = AssetId::stock("TSE", "XEQT");
+        vec![
+            (
+                date!(2021, 1, 4),
+                TxnAction::Deposit {
+                    value: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            (
+                date!(2021, 1, 5),
+                TxnAction::Buy {
+                    asset: (dec!(10), xeqt.clone()),
+                    cash: cad(dec!(250)),
+                    fee: cad(dec!(4.95)),
+                },
+            ),
+            // listed without a ticker
+            (
+                date!(2021, 1, 6),
+                TxnAction::Buy {
+                    asset: (dec!(5), AssetId::stock("TSE", "RY")),
+                    cash: cad(dec!(600)),
+                    fee: cad(dec!(4.95)),
+                },
+            ),
+            (
+                date!(2021, 2, 5),
+                TxnAction::Sell {
+                    asset: (dec!(5), AssetId::stock("NASDAQ", "AAPL")),
+                    cash: usd(dec!(650)),
+                    fee: usd(dec!(4.95)),
+                },
+            ),
+            (
+                date!(2021, 3, 31),
+                TxnAction::Dividend {
+                    source: xeqt.clone(),
+                    value: cad(dec!(1.25)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            (
+                date!(2021, 6, 30),
+                TxnAction::Dividend {
+                    source: xeqt.clone(),
+                    value: cad(dec!(12.5)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            (
+                date!(2021, 6, 30),
+                TxnAction::Buy {
+                    asset: (dec!(0.5), xeqt.clone()),
+                    cash: cad(dec!(12.5)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            (
+                date!(2021, 12, 31),
+                TxnAction::Dividend {
+                    source: xeqt.clone(),
+                    value: cad(dec!(0.80)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            (
+                date!(2021, 12, 31),
+                TxnAction::ReturnOfCapital {
+                    source: xeqt.clone(),
+                    value: cad(dec!(0.80)),
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_sgml() -> Result<(), ServerError> {
+        let (rows, errors) = Ofx.parse(include_str!("fixture/sgml.ofx"))?;
+        assert_eq!(
+            expected(),
+            rows.into_iter()
+                .map(|x| (x.date, x.action))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![RowError::new(141, "unsupported transaction \"TRANSFER\"")],
+            errors
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_xml() -> Result<(), ServerError> {
+        let (rows, errors) = Ofx.parse(include_str!("fixture/xml.qfx"))?;
+        assert!(errors.is_empty());
+        assert_eq!(
+            expected(),
+            rows.into_iter()
+                .map(|x| (x.date, x.action))
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_document() {
+        let document =
+            parse_document("<OFX><A><B>x &amp; y<C>1</C></A><D/></OFX>")
+                .unwrap();
+        assert_eq!("x & y", document.value(&["A", "B"]));
+        assert_eq!("1", document.value(&["A", "C"]));
+        assert!(document.child("D").is_some());
+        assert!(parse_document("OFXHEADER:100").is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use super::{amount, currency, date, parse_rows, security, Parser, Row};
+use crate::database::transaction::TxnAction;
+use crate::error::ServerError;
+use crate::import::RowError;
+use rust_decimal::Decimal;
+
+// the activity report of questrade, saved as csv
+pub struct Questrade;
+
+impl Questrade {
+    const COLUMNS: [&'static str; 10] = [
+        "transaction date",
+        "action",
+        "symbol",
+        "description",
+        "quantity",
+        "gross amount",
+        "commission",
+        "net amount",
+        "currency",
+        "activity type",
+    ];
+}
+
+impl Parser for Questrade {
+    fn parse(
+        &self,
+        data: &str,
+    ) -> Result<(Vec<Row>, Vec<RowError>), ServerError> {
+        parse_rows(data, &Self::COLUMNS, |columns, record| {
+            let get = |name: &str| columns.get(record, name);
+            let date = date(get("transaction date"), "%Y-%m-%d")?;
+            let currency = currency(get("currency"))?;
+            let cash = |x: Decimal| (x.abs(), currency.clone());
+            let net = amount(get("net amount"), "net amount")?;
+            let reason = get("description").to_string();
+
+            let activity = get("activity type");
+            let action = match activity {
+                "Trades" => {
+                    let asset = security(get("symbol"))?;
+                    let quantity = amount(get("quantity"), "quantity")?.abs();
+                    let gross = amount(get("gross amount"), "gross amount")?;
+                    let commission = amount(get("commission"), "commission")?;
+                    match get("action") {
+                        "Buy" => TxnAction::Buy {
+                            asset: (quantity, asset),
+                            cash: cash(gross),
+                            fee: cash(commission),
+                        },
+                        "Sell" => TxnAction::Sell {
+                            asset: (quantity, asset),
+                            cash: cash(gross),
+                            fee: cash(commission),
+                        },
+                        x => {
+                            return Err(format!("unsupported action \"{}\"", x))
+                        }
+                    }
+                }
+                _ if net.is_zero() => return Ok(None),
+                // withholding tax is reported as a negative dividend
+                "Dividends" if net < Decimal::ZERO => TxnAction::Fee {
+                    value: cash(net),
+                    reason,
+                },
+                "Dividends" => TxnAction::Dividend {
+                    source: security(get("symbol"))?,
+                    value: cash(net),
+                    fee: cash(Decimal::ZERO),
+                },
+                "Deposits" => TxnAction::Deposit {
+                    value: cash(net),
+                    fee: cash(Decimal::ZERO),
+                },
+                "Withdrawals" => TxnAction::Withdrawal {
+                    value: cash(net),
+                    fee: cash(Decimal::ZERO),
+                },
+                // margin interest is charged as a negative one
+                "Interest" | "Fees and rebates" if net < Decimal::ZERO => {
+                    TxnAction::Fee {
+                        value: cash(net),
+                        reason,
+                    }
+                }
+                "Interest" | "Fees and rebates" => TxnAction::Income {
+                    value: cash(net),
+                    reason,
+                },
+                _ => {
+                    return Err(format!(
+                        "unsupported activity \"{}\"",
+                        activity
+                    ))
+                }
+            };
+            Ok(Some((date, action)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::asset::AssetId;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> Result<(), ServerError> {
+        let data = "\
+Transaction Date,Settlement Date,Action,Symbol,Description,Quantity,Price,Gross Amount,Commission,Net Amount,Currency,Account #,Activity Type,Account Type
+2021-01-04 12:00:00 AM,2021-01-04 12:00:00 AM,CON,,CONTRIBUTION,0.00000,0.00000000,0.00,0.00,\"1,000.00\",CAD,12345678,Deposits,Individual TFSA
+2021-01-05 12:00:00 AM,2021-01-07 12:00:00 AM,Buy,XEQT.TO,ISHARES CORE EQUITY ETF,10.00000,25.00000000,-250.00,-4.95,-254.95,CAD,12345678,Trades,Individual TFSA
+2021-03-31 12:00:00 AM,2021-03-31 12:00:00 AM,DIS,XEQT.TO,ISHARES CORE EQUITY ETF DIST,0.00000,0.00000000,0.00,0.00,1.25,CAD,12345678,Dividends,Individual TFSA
+2021-04-01 12:00:00 AM,2021-04-01 12:00:00 AM,,,INTEREST,0.00000,0.00000000,0.00,0.00,0.00,CAD,12345678,Interest,Individual TFSA
+2021-04-02 12:00:00 AM,2021-04-02 12:00:00 AM,FXT,,FX CONVERSION,0.00000,0.00000000,0.00,0.00,-10.00,CAD,12345678,FX conversion,Individual TFSA
+";
+        let (rows, errors) = Questrade.parse(data)?;
+        let cad = |x| (x, AssetId::currency("CAD"));
+        let xeqt = AssetId::stock("TSE", "XEQT");
+        assert_eq!(
+            vec![
+                TxnAction::Deposit {
+                    value: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+                TxnAction::Buy {
+                    asset: (dec!(10), xeqt.clone()),
+                    cash: cad(dec!(250)),
+                    fee: cad(dec!(4.95)),
+                },
+                TxnAction::Dividend {
+                    source: xeqt.clone(),
+                    value: cad(dec!(1.25)),
+                    fee: cad(dec!(0)),
+                },
+            ],
+            rows.into_iter().map(|x| x.action).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![RowError::new(6, "unsupported activity \"FX conversion\"")],
+            errors
+        );
+
+        Ok(())
+    }
+}
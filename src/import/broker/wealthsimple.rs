@@ -0,0 +1,140 @@
+use super::{amount, currency, date, parse_rows, security, Parser, Row};
+use crate::database::transaction::TxnAction;
+use crate::error::ServerError;
+use crate::import::RowError;
+use rust_decimal::Decimal;
+
+// the monthly activity export of wealthsimple. trades only tell the symbol
+// and quantity in their description, e.g.
+// "XEQT - iShares Core Equity ETF Portfolio: Bought 10.0000 shares ..."
+pub struct Wealthsimple;
+
+impl Wealthsimple {
+    const COLUMNS: [&'static str; 5] =
+        ["date", "transaction", "description", "amount", "currency"];
+
+    fn symbol(description: &str) -> &str {
+        description.split(" - ").next().unwrap_or_default()
+    }
+
+    fn quantity(description: &str) -> Result<Decimal, String> {
+        let quantity = ["Bought ", "Sold "]
+            .iter()
+            .find_map(|x| description.split_once(x))
+            .and_then(|(_, x)| x.split_whitespace().next())
+            .ok_or_else(|| String::from("missing quantity in description"))?;
+        amount(quantity, "quantity")
+    }
+}
+
+impl Parser for Wealthsimple {
+    fn parse(
+        &self,
+        data: &str,
+    ) -> Result<(Vec<Row>, Vec<RowError>), ServerError> {
+        parse_rows(data, &Self::COLUMNS, |columns, record| {
+            let get = |name: &str| columns.get(record, name);
+            let date = date(get("date"), "%Y-%m-%d")?;
+            let currency = currency(get("currency"))?;
+            let cash = |x: Decimal| (x.abs(), currency.clone());
+            let value = amount(get("amount"), "amount")?;
+            let description = get("description");
+
+            let kind = get("transaction");
+            let action = match kind {
+                "BUY" => TxnAction::Buy {
+                    asset: (
+                        Self::quantity(description)?,
+                        security(Self::symbol(description))?,
+                    ),
+                    cash: cash(value),
+                    fee: cash(Decimal::ZERO),
+                },
+                "SELL" => TxnAction::Sell {
+                    asset: (
+                        Self::quantity(description)?,
+                        security(Self::symbol(description))?,
+                    ),
+                    cash: cash(value),
+                    fee: cash(Decimal::ZERO),
+                },
+                "DIV" => TxnAction::Dividend {
+                    source: security(Self::symbol(description))?,
+                    value: cash(value),
+                    fee: cash(Decimal::ZERO),
+                },
+                "CONT" | "DEP" => TxnAction::Deposit {
+                    value: cash(value),
+                    fee: cash(Decimal::ZERO),
+                },
+                "WD" | "WITHDRAWAL" => TxnAction::Withdrawal {
+                    value: cash(value),
+                    fee: cash(Decimal::ZERO),
+                },
+                "INT" => TxnAction::Income {
+                    value: cash(value),
+                    reason: String::from("Interest"),
+                },
+                // non-resident withholding tax
+                "FEE" | "NRT" => TxnAction::Fee {
+                    value: cash(value),
+                    reason: description.to_string(),
+                },
+                _ => {
+                    return Err(format!("unsupported transaction \"{}\"", kind))
+                }
+            };
+            Ok(Some((date, action)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::asset::AssetId;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> Result<(), ServerError> {
+        let data = "\
+\"date\",\"transaction\",\"description\",\"amount\",\"balance\",\"currency\"
+\"2021-01-04\",\"CONT\",\"Contribution (executed at 2021-01-04)\",\"1000.00\",\"1000.00\",\"CAD\"
+\"2021-01-05\",\"BUY\",\"XEQT - iShares Core Equity ETF Portfolio: Bought 10.0000 shares (executed at 2021-01-05)\",\"-250.00\",\"750.00\",\"CAD\"
+\"2021-02-05\",\"SELL\",\"XEQT - iShares Core Equity ETF Portfolio: Sold 2.5000 shares (executed at 2021-02-05)\",\"65.00\",\"815.00\",\"CAD\"
+\"2021-02-06\",\"BUY\",\"XEQT - iShares Core Equity ETF Portfolio\",\"-10.00\",\"805.00\",\"CAD\"
+\"2021-02-07\",\"LOAN\",\"Stock lending\",\"0.01\",\"805.01\",\"CAD\"
+";
+        let (rows, errors) = Wealthsimple.parse(data)?;
+        let cad = |x| (x, AssetId::currency("CAD"));
+        let xeqt = AssetId::stock("TSE", "XEQT");
+        assert_eq!(
+            vec![
+                TxnAction::Deposit {
+                    value: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+                TxnAction::Buy {
+                    asset: (dec!(10), xeqt.clone()),
+                    cash: cad(dec!(250)),
+                    fee: cad(dec!(0)),
+                },
+                TxnAction::Sell {
+                    asset: (dec!(2.5), xeqt.clone()),
+                    cash: cad(dec!(65)),
+                    fee: cad(dec!(0)),
+                },
+            ],
+            rows.into_iter().map(|x| x.action).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                RowError::new(5, "missing quantity in description"),
+                RowError::new(6, "unsupported transaction \"LOAN\""),
+            ],
+            errors
+        );
+
+        Ok(())
+    }
+}
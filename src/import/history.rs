@@ -0,0 +1,287 @@
+use super::RowError;
+use crate::database::asset::{Asset, AssetId, AssetUpdateKind};
+use crate::database::get_connection;
+use crate::error::ServerError;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+use uuid::Uuid;
+
+// rows written by a single statement, well below the sqlite variable limit
+const CHUNK: usize = 1000;
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub imported: usize,
+    pub errors: Vec<RowError>,
+}
+
+type Record = (NaiveDate, Decimal, Option<AssetId>);
+
+// columns expected in the header, in any order
+fn columns(kind: AssetUpdateKind) -> &'static [&'static str] {
+    match kind {
+        AssetUpdateKind::Price => &["date", "price", "currency"],
+        AssetUpdateKind::Dividend => &["date", "dividend", "currency"],
+        AssetUpdateKind::Split => &["date", "ratio"],
+    }
+}
+
+fn parse_row(fields: &[&str], names: &[&str]) -> Result<Record, String> {
+    let date = NaiveDate::parse_from_str(fields[0], "%Y-%m-%d")
+        .map_err(|_| format!("invalid date \"{}\"", fields[0]))?;
+    let value = Decimal::from_str(fields[1])
+        .map_err(|_| format!("invalid {} \"{}\"", names[1], fields[1]))?;
+    if value <= Decimal::ZERO {
+        return Err(format!("{} must be positive", names[1]));
+    }
+
+    let currency = match fields.get(2) {
+        None => None,
+        Some(x) => Some(
+            super::currency(x)
+                .ok_or_else(|| format!("unknown currency \"{}\"", x))?,
+        ),
+    };
+    Ok((date, value, currency))
+}
+
+// every row of `data` with a header, along with the rows that cannot be
+// imported.
+fn parse(
+    kind: AssetUpdateKind,
+    data: &str,
+) -> Result<(Vec<Record>, Vec<RowError>), ServerError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data.as_bytes());
+    let headers: Vec<String> =
+        reader.headers()?.iter().map(|x| x.to_lowercase()).collect();
+
+    let names = columns(kind);
+    let mut index = Vec::new();
+    for name in names {
+        match headers.iter().position(|x| x == name) {
+            Some(i) => index.push(i),
+            None => {
+                let error =
+                    RowError::new(1, format!("missing column \"{}\"", name));
+                return Ok((Vec::new(), vec![error]));
+            }
+        }
+    }
+
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+    let mut dates = HashSet::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(x) => x,
+            Err(e) => {
+                let line = e.position().map_or(0, |x| x.line());
+                errors.push(RowError::new(line, e.to_string()));
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |x| x.line());
+        let fields: Vec<_> = index
+            .iter()
+            .map(|i| record.get(*i).unwrap_or_default())
+            .collect();
+
+        match parse_row(&fields, names) {
+            Ok(x) if !dates.insert(x.0) => {
+                errors.push(RowError::new(line, "duplicated date"))
+            }
+            Ok(x) => records.push(x),
+            Err(e) => errors.push(RowError::new(line, e)),
+        }
+    }
+    Ok((records, errors))
+}
+
+// stores the `kind` history in csv `data` into `asset`, replacing the
+// existing records of the same dates. nothing is stored if any row is
+// invalid.
+pub fn import(
+    asset: &Asset,
+    kind: AssetUpdateKind,
+    data: &str,
+    transaction: &rusqlite::Transaction,
+) -> Result<Summary, ServerError> {
+    let (records, errors) = parse(kind, data)?;
+    if !errors.is_empty() {
+        return Ok(Summary {
+            imported: 0,
+            errors,
+        });
+    }
+
+    for chunk in records.chunks(CHUNK) {
+        match kind {
+            AssetUpdateKind::Split => {
+                let data: Vec<_> = chunk.iter().map(|x| (x.0, x.1)).collect();
+                asset.insert_split(&data, transaction)?;
+            }
+            _ => {
+                let data: Vec<_> = chunk
+                    .iter()
+                    .filter_map(|x| Some((x.0, x.1, x.2.clone()?)))
+                    .collect();
+                if kind == AssetUpdateKind::Price {
+                    asset.insert_price(&data, transaction)?;
+                } else {
+                    asset.insert_dividend(&data, transaction)?;
+                }
+            }
+        }
+    }
+    Ok(Summary {
+        imported: records.len(),
+        errors,
+    })
+}
+
+// `import` reading from a file, for the command line
+pub fn import_file(
+    asset: Uuid,
+    kind: AssetUpdateKind,
+    path: impl AsRef<Path>,
+) -> Result<Summary, ServerError> {
+    let data = std::fs::read_to_string(path)?;
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let asset = Asset::by_id(asset, &tran)?
+        .ok_or(ServerError::Internal(String::from("asset not found")))?;
+    let summary = import(&asset, kind, &data, &tran)?;
+    tran.commit()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database;
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    #[test]
+    fn test_parse() -> Result<(), ServerError> {
+        let (records, errors) = parse(
+            AssetUpdateKind::Price,
+            "Currency, Date, Price\n\
+             CAD, 2021-01-04, 10.5\n\
+             CURRENCY:usd, 2021-01-05, 8\n",
+        )?;
+        assert!(errors.is_empty());
+        assert_eq!(
+            vec![
+                (
+                    date!(2021, 1, 4),
+                    dec!(10.5),
+                    Some(AssetId::currency("CAD"))
+                ),
+                (date!(2021, 1, 5), dec!(8), Some(AssetId::currency("USD"))),
+            ],
+            records
+        );
+
+        let (_, errors) = parse(
+            AssetUpdateKind::Dividend,
+            "date,dividend,currency\n\
+             2021-01-04,0.1,CAD\n\
+             2021/01/05,0.1,CAD\n\
+             2021-01-06,abc,CAD\n\
+             2021-01-07,0,CAD\n\
+             2021-01-08,0.1,XYZ\n\
+             2021-01-04,0.2,CAD\n\
+             2021-01-09,0.1\n",
+        )?;
+        assert_eq!(
+            vec![3, 4, 5, 6, 7, 8],
+            errors.iter().map(|x| x.line).collect::<Vec<_>>()
+        );
+        assert_eq!("invalid date \"2021/01/05\"", errors[0].message);
+        assert_eq!("dividend must be positive", errors[2].message);
+        assert_eq!("duplicated date", errors[4].message);
+
+        let (_, errors) = parse(AssetUpdateKind::Split, "date,price\n")?;
+        assert_eq!(vec![RowError::new(1, "missing column \"ratio\"")], errors);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let tran = conn.transaction()?;
+        database::migration::run_migration(&tran)?;
+        let mut asset =
+            Asset::new(AssetId::unknown("TDB627"), "TD Dividend Income", None);
+        asset.id = asset.insert(&tran)?;
+
+        let res = import(
+            &asset,
+            AssetUpdateKind::Price,
+            "date,price,currency\n2021-01-04,10,CAD\n2021-01-05,-1,CAD\n",
+            &tran,
+        )?;
+        assert_eq!(0, res.imported);
+        assert_eq!(1, res.errors.len());
+        assert!(asset
+            .prices(date!(2021, 1, 1), date!(2021, 12, 31), None, &tran)?
+            .is_empty());
+
+        let res = import(
+            &asset,
+            AssetUpdateKind::Price,
+            "date,price,currency\n2021-01-04,10,CAD\n2021-01-05,11,CAD\n",
+            &tran,
+        )?;
+        assert_eq!(
+            Summary {
+                imported: 2,
+                errors: Vec::new()
+            },
+            res
+        );
+        // the same dates are replaced
+        import(
+            &asset,
+            AssetUpdateKind::Price,
+            "date,price,currency\n2021-01-05,12,CAD\n",
+            &tran,
+        )?;
+        let prices = asset.prices(
+            date!(2021, 1, 1),
+            date!(2021, 12, 31),
+            None,
+            &tran,
+        )?;
+        assert_eq!(
+            vec![dec!(10), dec!(12)],
+            prices.iter().map(|x| x.price).collect::<Vec<_>>()
+        );
+
+        let res = import(
+            &asset,
+            AssetUpdateKind::Split,
+            "date,ratio\n2021-06-01,2\n",
+            &tran,
+        )?;
+        assert_eq!(1, res.imported);
+        assert_eq!(dec!(2), asset.splits(&tran)?[0].ratio);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+pub mod broker;
+pub mod history;
+
+use crate::database::asset::AssetId;
+use crate::repository::currency;
+use serde::Serialize;
+
+// a problem found on `line` of an imported file, the header being line 1
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+impl RowError {
+    pub(crate) fn new(line: u64, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+// a known currency, written either as its code or as an `AssetId`
+fn currency(symbol: &str) -> Option<AssetId> {
+    let symbol = symbol.trim_start_matches("CURRENCY:").to_uppercase();
+    currency::exists(&symbol).then(|| AssetId::currency(symbol))
+}
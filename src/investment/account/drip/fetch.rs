@@ -0,0 +1,36 @@
+use crate::database::account::AccountDrip;
+use crate::database::{get_connection, Account};
+use crate::error::ServerError;
+use crate::investment::account::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    account: Uuid,
+}
+
+// the assets of which dividends are reinvested in the account
+#[post("/api/investment/account/drip/fetch")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let account = match Account::by_id(request.account, &tran)? {
+        None => {
+            return Ok(HttpResponse::BadRequest().body("account does not exist"))
+        }
+        Some(a) => a,
+    };
+
+    if !authenticate(&account, &request.token, &tran)? {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    let assets = AccountDrip::by_account(account.id, &tran)?;
+    Ok(HttpResponse::Ok().json(assets))
+}
@@ -0,0 +1,49 @@
+use crate::database::account::AccountDrip;
+use crate::database::asset::AssetId;
+use crate::database::{get_connection, Account};
+use crate::error::ServerError;
+use crate::investment::account::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    account: Uuid,
+    asset: AssetId,
+    // whether dividends of the asset are reinvested
+    enabled: bool,
+}
+
+#[post("/api/investment/account/drip/update")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let account = match Account::by_id(request.account, &tran)? {
+        None => {
+            return Ok(HttpResponse::BadRequest().body("account does not exist"))
+        }
+        Some(a) => a,
+    };
+
+    if !authenticate(&account, &request.token, &tran)? {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    if matches!(request.asset, AssetId::CURRENCY(_)) {
+        return Ok(HttpResponse::BadRequest().body("currency has no dividend"));
+    }
+
+    let drip = AccountDrip::new(account.id, request.asset.clone());
+    if request.enabled {
+        drip.insert(&tran)?;
+    } else {
+        drip.delete(&tran)?;
+    }
+    tran.commit()?;
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,39 @@
+use crate::database::{get_connection, Account};
+use crate::error::ServerError;
+use crate::investment::account::authenticate;
+use crate::portfolio::Holding;
+use actix_web::{post, web, HttpResponse, Responder};
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    account_id: Uuid,
+    #[serde(default)]
+    date: Option<NaiveDate>,
+}
+
+#[post("/api/investment/account/holdings")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let account = match Account::by_id(request.account_id, &tran)? {
+        None => {
+            return Ok(HttpResponse::BadRequest().body("account does not exist"))
+        }
+        Some(a) => a,
+    };
+
+    if !authenticate(&account, &request.token, &tran)? {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    let date = request.date.unwrap_or(Utc::now().date_naive());
+    let holding = Holding::by_account(account.id, date, &tran)?;
+    Ok(HttpResponse::Ok().json(holding))
+}
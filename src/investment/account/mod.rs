@@ -3,7 +3,9 @@ use crate::user;
 use std::time::SystemTimeError;
 
 pub mod delete;
+pub mod drip;
 pub mod fetch;
+pub mod holdings;
 pub mod insert;
 pub mod update;
 
@@ -0,0 +1,49 @@
+use crate::database::asset::Asset;
+use crate::database::get_connection;
+use crate::error::ServerError;
+use crate::investment::asset::visible;
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    // all private assets of the user if not given
+    #[serde(default)]
+    asset_id: Option<Uuid>,
+}
+
+#[post("/api/investment/asset/fetch")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let user_id = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+
+    let asset_id = match request.asset_id {
+        None => {
+            let assets = Asset::by_owner(user_id, &tran)?;
+            return Ok(HttpResponse::Ok().json(assets));
+        }
+        Some(i) => i,
+    };
+    let asset = match Asset::by_id(asset_id, &tran)? {
+        None => {
+            return Ok(HttpResponse::BadRequest().body("asset does not exist"))
+        }
+        Some(a) => a,
+    };
+
+    if !visible(&asset, &request.token, &tran)? {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    Ok(HttpResponse::Ok().json(asset))
+}
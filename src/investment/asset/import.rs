@@ -0,0 +1,43 @@
+use crate::database::asset::{Asset, AssetUpdateKind};
+use crate::database::get_connection;
+use crate::error::ServerError;
+use crate::import::history;
+use crate::investment::asset::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    asset_id: Uuid,
+    kind: AssetUpdateKind,
+    // csv content with a header
+    data: String,
+}
+
+#[post("/api/investment/asset/import")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let asset = match Asset::by_id(request.asset_id, &tran)? {
+        None => {
+            return Ok(HttpResponse::BadRequest().body("asset does not exist"))
+        }
+        Some(a) => a,
+    };
+
+    if !authenticate(&asset, &request.token, &tran)? {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    let summary = history::import(&asset, request.kind, &request.data, &tran)?;
+    if !summary.errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(summary));
+    }
+    tran.commit()?;
+    Ok(HttpResponse::Ok().json(summary))
+}
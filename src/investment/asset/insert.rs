@@ -0,0 +1,43 @@
+use crate::database::asset::Asset;
+use crate::database::get_connection;
+use crate::error::ServerError;
+use crate::investment::asset::{authenticate, validate};
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    asset: Asset,
+}
+
+#[post("/api/investment/asset/insert")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    if !authenticate(&request.asset, &request.token, &tran)? {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    // input check
+    if !request.asset.id.is_nil() {
+        return Ok(HttpResponse::BadRequest().body("asset id should be nil"));
+    } else if let Some(err) = validate(&request.asset, &tran) {
+        return Ok(HttpResponse::BadRequest().body(err));
+    } else if Asset::by_asset(
+        request.asset.asset_id.clone(),
+        request.asset.owner,
+        &tran,
+    )?
+    .is_some()
+    {
+        return Ok(HttpResponse::BadRequest().body("asset already exists"));
+    }
+
+    let id = request.asset.insert(&tran)?;
+    tran.commit()?;
+    Ok(HttpResponse::Ok().json(id))
+}
@@ -0,0 +1,47 @@
+use crate::database::asset::Asset;
+use crate::user;
+use std::time::SystemTimeError;
+
+pub mod delete;
+pub mod fetch;
+pub mod import;
+pub mod insert;
+pub mod search;
+
+// only private assets can be changed, and only by their owner
+pub fn authenticate(
+    asset: &Asset,
+    token: &str,
+    _: &rusqlite::Transaction,
+) -> Result<bool, SystemTimeError> {
+    Ok(user::authenticate(token)?
+        .map(|user_id| asset.owner == Some(user_id))
+        .unwrap_or(false))
+}
+
+// whether the asset can be seen by the owner of `token`
+pub fn visible(
+    asset: &Asset,
+    token: &str,
+    _: &rusqlite::Transaction,
+) -> Result<bool, SystemTimeError> {
+    Ok(user::authenticate(token)?
+        .map(|user_id| asset.owner.is_none() || asset.owner == Some(user_id))
+        .unwrap_or(false))
+}
+
+pub fn validate(
+    asset: &Asset,
+    _: &rusqlite::Transaction,
+) -> Option<&'static str> {
+    if asset.name.is_empty() {
+        Some("asset name is empty")
+    } else if String::from(asset.asset_id.clone())
+        .split(':')
+        .any(|x| x.is_empty())
+    {
+        Some("asset symbol is empty")
+    } else {
+        None
+    }
+}
@@ -0,0 +1,30 @@
+use crate::error::ServerError;
+use crate::repository::{AssetKind, IRepository, Repository};
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    query: String,
+    // every kind if not given
+    #[serde(default)]
+    kinds: Option<Vec<AssetKind>>,
+}
+
+#[post("/api/investment/asset/search")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let user_id = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+
+    let kinds = request.kinds.clone().unwrap_or(AssetKind::ALL.to_vec());
+    let assets = Repository
+        .search(user_id, request.query.clone(), kinds)
+        .await?;
+    Ok(HttpResponse::Ok().json(assets))
+}
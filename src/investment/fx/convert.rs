@@ -0,0 +1,55 @@
+use crate::database::asset::AssetId;
+use crate::database::get_connection;
+use crate::error::ServerError;
+use crate::portfolio::{Exchange, SqlExchange};
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    amount: Decimal,
+    from: AssetId,
+    to: AssetId,
+    #[serde(default)]
+    date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    amount: Decimal,
+    currency: AssetId,
+    rate: Decimal,
+    date: NaiveDate,
+}
+
+#[post("/api/investment/fx/convert")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let user_id = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+
+    let date = request.date.unwrap_or(Utc::now().date_naive());
+    let exchange = SqlExchange::new(Some(user_id), &tran);
+    let rate = match exchange.rate(&request.from, &request.to, date) {
+        Ok(rate) => rate,
+        Err(_) => {
+            return Ok(HttpResponse::BadRequest().body("no exchange rate"))
+        }
+    };
+    Ok(HttpResponse::Ok().json(Response {
+        amount: request.amount * rate,
+        currency: request.to.clone(),
+        rate,
+        date,
+    }))
+}
@@ -0,0 +1,60 @@
+use crate::database::asset::AssetId;
+use crate::database::get_connection;
+use crate::error::ServerError;
+use crate::portfolio::SqlExchange;
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Rate {
+    date: NaiveDate,
+    // how many `to` one unit of `from` is worth
+    rate: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    from: AssetId,
+    to: AssetId,
+    rates: Vec<Rate>,
+}
+
+#[post("/api/investment/fx/insert")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let user_id = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+
+    // input check
+    if !matches!(request.from, AssetId::CURRENCY(_))
+        || !matches!(request.to, AssetId::CURRENCY(_))
+    {
+        return Ok(HttpResponse::BadRequest().body("not a currency"));
+    }
+    if request.from == request.to {
+        return Ok(HttpResponse::BadRequest().body("same currency"));
+    }
+    if request.rates.iter().any(|x| x.rate <= Decimal::ZERO) {
+        return Ok(HttpResponse::BadRequest().body("rate must be positive"));
+    }
+
+    let rates: Vec<_> =
+        request.rates.iter().map(|x| (x.date, x.rate)).collect();
+    SqlExchange::new(Some(user_id), &tran).insert(
+        &request.from,
+        &request.to,
+        &rates,
+    )?;
+    tran.commit()?;
+    Ok(HttpResponse::Ok().finish())
+}
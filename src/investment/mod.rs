@@ -1,2 +1,6 @@
 pub mod account;
+pub mod asset;
+pub mod fx;
+pub mod provider;
+pub mod report;
 pub mod transaction;
@@ -0,0 +1,21 @@
+use crate::error::ServerError;
+use crate::provider;
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+}
+
+#[post("/api/investment/provider/status")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    if authenticate(&request.token)?.is_none() {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    Ok(HttpResponse::Ok().json(provider::status()))
+}
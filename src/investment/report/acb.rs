@@ -0,0 +1,39 @@
+use crate::database::asset::AssetId;
+use crate::database::get_connection;
+use crate::error::ServerError;
+use crate::portfolio::{AcbLedger, SqlExchange};
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    asset: AssetId,
+}
+
+#[post("/api/investment/report/acb")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let user_id = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+
+    // ACB is always reported in Canadian dollar for tax purposes
+    let currency = AssetId::currency("CAD");
+    let mut ledgers = AcbLedger::by_owner(
+        user_id,
+        &currency,
+        &SqlExchange::new(Some(user_id), &tran),
+        &tran,
+    )?;
+    let ledger = ledgers
+        .remove(&request.asset)
+        .unwrap_or(AcbLedger::new(request.asset.clone(), currency));
+    Ok(HttpResponse::Ok().json(ledger))
+}
@@ -0,0 +1,45 @@
+use super::Format;
+use crate::database::asset::AssetId;
+use crate::database::get_connection;
+use crate::error::ServerError;
+use crate::portfolio::{GainReport, SqlExchange};
+use crate::user::authenticate;
+use actix_web::http::header::ContentDisposition;
+use actix_web::{post, web, HttpResponse, Responder};
+use chrono::Utc;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    #[serde(default)]
+    format: Format,
+}
+
+#[post("/api/investment/report/gain")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let user_id = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+
+    let report = GainReport::by_owner(
+        user_id,
+        &AssetId::currency("CAD"),
+        Utc::now().date_naive(),
+        &SqlExchange::new(Some(user_id), &tran),
+        &tran,
+    )?;
+    match request.format {
+        Format::Json => Ok(HttpResponse::Ok().json(report)),
+        Format::Csv => Ok(HttpResponse::Ok()
+            .content_type("text/csv")
+            .insert_header(ContentDisposition::attachment("capital_gains.csv"))
+            .body(report.to_csv()?)),
+    }
+}
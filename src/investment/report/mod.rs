@@ -0,0 +1,42 @@
+pub mod acb;
+pub mod gain;
+pub mod performance;
+pub mod series;
+
+use crate::database::{Account, Transaction};
+use crate::error::ServerError;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Json,
+    Csv,
+}
+
+// transactions of `accounts`, or of all accounts of `owner` if not given.
+// none if any of the accounts is not owned by `owner`.
+fn transactions(
+    owner: Uuid,
+    accounts: Option<&[Uuid]>,
+    transaction: &rusqlite::Transaction,
+) -> Result<Option<Vec<Transaction>>, ServerError> {
+    let owned: Vec<_> = Account::by_owner(owner, transaction)?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+    let accounts = match accounts {
+        Some(accounts) if accounts.iter().all(|x| owned.contains(x)) => {
+            accounts.to_vec()
+        }
+        Some(_) => return Ok(None),
+        None => owned,
+    };
+
+    let mut transactions = Vec::new();
+    for account in accounts {
+        transactions.extend(Transaction::by_account(account, transaction)?);
+    }
+    Ok(Some(transactions))
+}
@@ -0,0 +1,72 @@
+use crate::database::asset::AssetId;
+use crate::database::{get_connection, Setting};
+use crate::error::ServerError;
+use crate::portfolio::{Performance, Period, Splits, SqlExchange};
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    // all accounts of the user if not given
+    #[serde(default)]
+    accounts: Option<Vec<Uuid>>,
+    #[serde(default)]
+    period: Option<Period>,
+    // overrides the period if given
+    #[serde(default)]
+    start: Option<NaiveDate>,
+    #[serde(default)]
+    end: Option<NaiveDate>,
+    #[serde(default)]
+    currency: Option<AssetId>,
+}
+
+#[post("/api/investment/report/performance")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let user_id = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+
+    // permission check
+    let transactions =
+        match super::transactions(user_id, request.accounts.as_deref(), &tran)?
+        {
+            None => return Ok(HttpResponse::Forbidden().finish()),
+            Some(x) => x,
+        };
+
+    let setting = Setting::by_user(user_id, &tran)?;
+    let end = request.end.unwrap_or(Utc::now().date_naive());
+    let inception = transactions.iter().map(|x| x.date).min().unwrap_or(end);
+    let start = request.start.unwrap_or(
+        request
+            .period
+            .unwrap_or(setting.date_range)
+            .start(end, inception),
+    );
+    if start > end {
+        return Ok(HttpResponse::BadRequest().body("start is after end"));
+    }
+
+    let currency = request.currency.clone().unwrap_or(setting.base_currency);
+    let splits = Splits::by_transactions(&transactions, Some(user_id), &tran)?;
+    let performance = Performance::build(
+        &transactions,
+        start,
+        end,
+        &currency,
+        &splits,
+        &SqlExchange::new(Some(user_id), &tran),
+    )?;
+    Ok(HttpResponse::Ok().json(performance))
+}
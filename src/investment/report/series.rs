@@ -0,0 +1,77 @@
+use crate::database::asset::AssetId;
+use crate::database::{get_connection, Setting};
+use crate::error::ServerError;
+use crate::portfolio::{CachedExchange, Interval, Series, Splits};
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    // all accounts of the user if not given
+    #[serde(default)]
+    accounts: Option<Vec<Uuid>>,
+    #[serde(default)]
+    start: Option<NaiveDate>,
+    #[serde(default)]
+    end: Option<NaiveDate>,
+    #[serde(default)]
+    interval: Interval,
+    #[serde(default)]
+    currency: Option<AssetId>,
+}
+
+#[post("/api/investment/report/series")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let user_id = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+
+    // permission check
+    let transactions =
+        match super::transactions(user_id, request.accounts.as_deref(), &tran)?
+        {
+            None => return Ok(HttpResponse::Forbidden().finish()),
+            Some(x) => x,
+        };
+
+    let setting = Setting::by_user(user_id, &tran)?;
+    let end = request.end.unwrap_or(Utc::now().date_naive());
+    let inception = transactions.iter().map(|x| x.date).min().unwrap_or(end);
+    let start = request
+        .start
+        .unwrap_or(setting.date_range.start(end, inception));
+    if start > end {
+        return Ok(HttpResponse::BadRequest().body("start is after end"));
+    }
+
+    // every price needed is read once instead of once per day
+    let currency = request.currency.clone().unwrap_or(setting.base_currency);
+    let mut assets = BTreeSet::from([currency.clone()]);
+    for txn in &transactions {
+        assets.extend(txn.action.assets());
+    }
+    let exchange = CachedExchange::load(&assets, Some(user_id), &tran)?;
+
+    let splits = Splits::by_transactions(&transactions, Some(user_id), &tran)?;
+    let series = Series::build(
+        &transactions,
+        start,
+        end,
+        request.interval,
+        &currency,
+        &splits,
+        &exchange,
+    )?;
+    Ok(HttpResponse::Ok().json(series))
+}
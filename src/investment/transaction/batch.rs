@@ -0,0 +1,130 @@
+use super::validate_input;
+use crate::database::transaction::Transfer;
+use crate::database::{get_connection, Account, Transaction};
+use crate::error::ServerError;
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum Operation {
+    Insert { transaction: Transaction },
+    Update { transaction: Transaction },
+    Delete { transaction_id: Uuid },
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    // applied in order
+    operations: Vec<Operation>,
+}
+
+// the reason why the operation at `index` is rejected
+#[derive(Debug, Serialize)]
+struct ItemError {
+    index: usize,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    // the transaction affected by every operation
+    ids: Vec<Uuid>,
+}
+
+fn owns(
+    account: Uuid,
+    user: Uuid,
+    transaction: &rusqlite::Transaction,
+) -> Result<bool, ServerError> {
+    Ok(Account::by_id(account, transaction)?.is_some_and(|x| x.owner == user))
+}
+
+// the id of the transaction affected, or why the operation is rejected
+fn apply(
+    operation: &Operation,
+    user: Uuid,
+    tran: &rusqlite::Transaction,
+) -> Result<Result<Uuid, String>, ServerError> {
+    // the account the transaction belongs to before the operation
+    let account = match operation {
+        Operation::Insert { transaction } => Some(transaction.account),
+        Operation::Update { transaction } => {
+            Transaction::by_id(transaction.id, tran)?.map(|x| x.account)
+        }
+        Operation::Delete { transaction_id } => {
+            Transaction::by_id(*transaction_id, tran)?.map(|x| x.account)
+        }
+    };
+    let account = match account {
+        None => return Ok(Err(String::from("transaction does not exist"))),
+        Some(a) => a,
+    };
+
+    // permission check
+    if !owns(account, user, tran)? {
+        return Ok(Err(String::from("permission denied")));
+    }
+
+    match operation {
+        Operation::Insert { transaction } => {
+            if !transaction.id.is_nil() {
+                return Ok(Err(String::from("transaction id should be nil")));
+            } else if let Some(err) = validate_input(transaction, tran) {
+                return Ok(Err(String::from(err)));
+            }
+            Ok(Ok(transaction.insert(tran)?))
+        }
+        Operation::Update { transaction } => {
+            if Transaction::by_id(transaction.id, tran)?
+                .is_some_and(|x| x.action.counterpart().is_some())
+            {
+                return Ok(Err(String::from(
+                    "transfers are managed through the transfer endpoints",
+                )));
+            } else if transaction.account != account {
+                return Ok(Err(String::from("account cannot be modified")));
+            } else if let Some(err) = validate_input(transaction, tran) {
+                return Ok(Err(String::from(err)));
+            }
+            transaction.update(tran)?;
+            Ok(Ok(transaction.id))
+        }
+        Operation::Delete { transaction_id } => {
+            Transfer::delete(*transaction_id, tran)?;
+            Ok(Ok(*transaction_id))
+        }
+    }
+}
+
+#[post("/api/investment/transaction/batch")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let user = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+
+    let mut ids = Vec::new();
+    let mut errors = Vec::new();
+    for (index, operation) in request.operations.iter().enumerate() {
+        match apply(operation, user, &tran)? {
+            Ok(id) => ids.push(id),
+            Err(message) => errors.push(ItemError { index, message }),
+        }
+    }
+
+    // all or nothing, nothing is committed if any operation is rejected
+    if !errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(errors));
+    }
+    tran.commit()?;
+    Ok(HttpResponse::Ok().json(Response { ids }))
+}
@@ -1,4 +1,5 @@
 use super::has_permission;
+use crate::database::transaction::Transfer;
 use crate::database::{get_connection, Transaction};
 use crate::error::ServerError;
 use actix_web::{post, web, HttpResponse, Responder};
@@ -7,7 +8,6 @@ use uuid::Uuid;
 
 #[derive(Debug, Deserialize)]
 struct Request {
-    #[allow(unused)]
     token: String,
     transaction_id: Uuid,
 }
@@ -19,19 +19,21 @@ pub async fn handler(
     let mut conn = get_connection()?;
     let tran = conn.transaction()?;
 
-    let transaction =
-        match Transaction::by_id(request.transaction_id, &tran)? {
-            None => {
-                return Ok(HttpResponse::BadRequest()
-                    .body("transaction does not exist"))
-            }
-            Some(t) => t,
-        };
+    let transaction = match Transaction::by_id(request.transaction_id, &tran)? {
+        None => {
+            return Ok(
+                HttpResponse::BadRequest().body("transaction does not exist")
+            )
+        }
+        Some(t) => t,
+    };
 
     if !has_permission(&transaction, &request.token, &tran)? {
         return Ok(HttpResponse::Forbidden().finish());
     }
 
-    Transaction::delete(transaction.id, &tran)?;
+    // both legs of a transfer go together
+    Transfer::delete(transaction.id, &tran)?;
+    tran.commit()?;
     Ok(HttpResponse::Ok().finish())
 }
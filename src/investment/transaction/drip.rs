@@ -0,0 +1,69 @@
+use super::validate_input;
+use crate::database::{get_connection, Account};
+use crate::error::ServerError;
+use crate::portfolio::reinvested_dividends;
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    account: Uuid,
+    // both inclusive
+    start: NaiveDate,
+    end: NaiveDate,
+    // only returns the transactions without storing anything
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    generated: usize,
+}
+
+// records the dividends reinvested in the assets flagged as DRIP
+#[post("/api/investment/transaction/drip")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let account = match Account::by_id(request.account, &tran)? {
+        None => {
+            return Ok(HttpResponse::BadRequest().body("account does not exist"))
+        }
+        Some(a) => a,
+    };
+
+    // permission check
+    match authenticate(&request.token)? {
+        Some(user) if account.owner == user => (),
+        _ => return Ok(HttpResponse::Forbidden().finish()),
+    };
+
+    if request.start > request.end {
+        return Ok(HttpResponse::BadRequest().body("start is after end"));
+    }
+
+    let transactions =
+        reinvested_dividends(&account, request.start, request.end, &tran)?;
+    if request.dry_run {
+        return Ok(HttpResponse::Ok().json(transactions));
+    }
+
+    for transaction in &transactions {
+        if let Some(err) = validate_input(transaction, &tran) {
+            return Ok(HttpResponse::BadRequest().body(err));
+        }
+        transaction.insert(&tran)?;
+    }
+    tran.commit()?;
+    Ok(HttpResponse::Ok().json(Response {
+        generated: transactions.len(),
+    }))
+}
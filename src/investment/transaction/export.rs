@@ -0,0 +1,85 @@
+use crate::database::transaction::{Cursor, Filter, Page, Sort};
+use crate::database::{get_connection, Account, Transaction};
+use crate::error::ServerError;
+use crate::export::{Format, CHUNK};
+use crate::user::authenticate;
+use actix_web::http::header::ContentDisposition;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    // every account of the user when missing
+    account: Option<Uuid>,
+    format: Format,
+}
+
+#[post("/api/investment/transaction/export")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let user = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+
+    let accounts = match request.account {
+        None => Account::by_owner(user, &tran)?,
+        Some(id) => match Account::by_id(id, &tran)? {
+            None => {
+                return Ok(
+                    HttpResponse::BadRequest().body("account does not exist")
+                )
+            }
+            // permission check
+            Some(a) if a.owner != user => {
+                return Ok(HttpResponse::Forbidden().finish())
+            }
+            Some(a) => vec![a],
+        },
+    };
+
+    let filter = Filter {
+        accounts: accounts.into_iter().map(|x| x.id).collect(),
+        ..Default::default()
+    };
+    tran.commit()?;
+
+    // no filter on accounts would be every transaction of every user
+    let mut cursor = None;
+    let mut done = filter.accounts.is_empty();
+    let pages = std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let page = page(&filter, cursor.as_ref());
+        cursor = page.as_ref().ok().and_then(|x| x.next.clone());
+        done = cursor.is_none();
+        Some(page.map(|x| x.transactions))
+    });
+
+    let format = request.format;
+    let chunks = format
+        .chunks(pages)
+        .map(|x| x.map(web::Bytes::from).map_err(actix_web::Error::from));
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition::attachment(format!(
+            "transactions.{}",
+            format.extension()
+        )))
+        .streaming(futures_util::stream::iter(chunks)))
+}
+
+// the page after `cursor`, read with a connection of its own as the previous
+// one is released once the response starts
+fn page(filter: &Filter, cursor: Option<&Cursor>) -> Result<Page, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+    Transaction::query(filter, Sort::Asc, cursor, CHUNK, &tran)
+}
@@ -1,3 +1,4 @@
+use crate::database::transaction::{Cursor, Filter, Page, Sort, MAX_LIMIT};
 use crate::database::{get_connection, Account, Transaction};
 use crate::error::ServerError;
 use crate::user::authenticate;
@@ -8,7 +9,16 @@ use uuid::Uuid;
 #[derive(Debug, Deserialize)]
 struct Request {
     token: String,
-    account: Uuid,
+    // a single account, every account of the user when neither this nor
+    // the accounts of the filter are given
+    account: Option<Uuid>,
+    #[serde(flatten)]
+    filter: Filter,
+    #[serde(default)]
+    sort: Sort,
+    cursor: Option<Cursor>,
+    // returns a page of transactions when given, otherwise all of them
+    limit: Option<usize>,
 }
 
 #[post("/api/investment/transaction/fetch")]
@@ -18,20 +28,67 @@ pub async fn handler(
     let mut conn = get_connection()?;
     let tran = conn.transaction()?;
 
-    let account = match Account::by_id(request.account, &tran)? {
-        None => {
-            return Ok(HttpResponse::BadRequest().body("account does not exist"))
-        }
-        Some(a) => a,
+    let user = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
     };
 
-    // permission check
-    match authenticate(&request.token)? {
-        Some(user) if account.owner == user => (),
-        _ => return Ok(HttpResponse::Forbidden().finish()),
-    };
+    let mut filter = request.filter.clone();
+    filter.accounts.extend(request.account);
+    for id in &filter.accounts {
+        match Account::by_id(*id, &tran)? {
+            None => {
+                return Ok(
+                    HttpResponse::BadRequest().body("account does not exist")
+                )
+            }
+            // permission check
+            Some(a) if a.owner != user => {
+                return Ok(HttpResponse::Forbidden().finish())
+            }
+            Some(_) => (),
+        }
+    }
+    if filter.accounts.is_empty() {
+        filter.accounts = Account::by_owner(user, &tran)?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        // same shape as when there are accounts
+        if filter.accounts.is_empty() {
+            return Ok(match request.limit {
+                Some(_) => HttpResponse::Ok().json(Page::default()),
+                None => HttpResponse::Ok().json(Vec::<Transaction>::new()),
+            });
+        }
+    }
+
+    if let Some(limit) = request.limit {
+        let page = Transaction::query(
+            &filter,
+            request.sort,
+            request.cursor.as_ref(),
+            limit,
+            &tran,
+        )?;
+        return Ok(HttpResponse::Ok().json(page));
+    }
 
-    let transactions = Transaction::by_account(request.account, &tran)?;
-    tran.commit()?;
+    let mut transactions = Vec::new();
+    let mut cursor = request.cursor.clone();
+    loop {
+        let page = Transaction::query(
+            &filter,
+            request.sort,
+            cursor.as_ref(),
+            MAX_LIMIT,
+            &tran,
+        )?;
+        transactions.extend(page.transactions);
+        cursor = page.next;
+        if cursor.is_none() {
+            break;
+        }
+    }
     Ok(HttpResponse::Ok().json(transactions))
 }
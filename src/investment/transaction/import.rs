@@ -0,0 +1,71 @@
+use super::validate_input;
+use crate::database::{get_connection, Account};
+use crate::error::ServerError;
+use crate::import::broker::{Format, Preview};
+use crate::import::RowError;
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    account: Uuid,
+    format: Format,
+    // csv content of the activity export
+    data: String,
+    // only returns the preview without storing anything
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    imported: usize,
+    duplicates: usize,
+}
+
+#[post("/api/investment/transaction/import")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let account = match Account::by_id(request.account, &tran)? {
+        None => {
+            return Ok(HttpResponse::BadRequest().body("account does not exist"))
+        }
+        Some(a) => a,
+    };
+
+    // permission check
+    match authenticate(&request.token)? {
+        Some(user) if account.owner == user => (),
+        _ => return Ok(HttpResponse::Forbidden().finish()),
+    };
+
+    let mut preview =
+        Preview::new(&request.format, account.id, &request.data, &tran)?;
+    for entry in &preview.entries {
+        if let Some(err) = validate_input(&entry.transaction, &tran) {
+            preview.errors.push(RowError::new(entry.line, err));
+        }
+    }
+    preview.errors.sort_by_key(|x| x.line);
+
+    if request.dry_run {
+        return Ok(HttpResponse::Ok().json(preview));
+    } else if !preview.errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(preview));
+    }
+
+    // all or nothing
+    let imported = preview.commit(&tran)?;
+    tran.commit()?;
+    Ok(HttpResponse::Ok().json(Response {
+        imported,
+        duplicates: preview.entries.len() - imported,
+    }))
+}
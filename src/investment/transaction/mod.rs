@@ -1,6 +1,11 @@
+pub mod batch;
 pub mod delete;
+pub mod drip;
+pub mod export;
 pub mod fetch;
+pub mod import;
 pub mod insert;
+pub mod transfer;
 pub mod update;
 
 use crate::database::account::AccountKind;
@@ -9,6 +14,7 @@ use crate::database::transaction::TxnAction;
 use crate::database::Transaction;
 use crate::error::ServerError;
 use crate::user::authenticate;
+use rust_decimal::Decimal;
 
 fn has_permission(
     transaction: &Transaction,
@@ -29,6 +35,13 @@ fn validate_input(
     transaction: &Transaction,
     sql_transaction: &rusqlite::Transaction,
 ) -> Option<&'static str> {
+    // the legs of a transfer are only modified together
+    if transaction.action.counterpart().is_some() {
+        return Some("transfers are managed through the transfer endpoints");
+    }
+    if let Some(err) = rule_corporate_action(transaction) {
+        return Some(err);
+    }
     if let Some(account) = transaction.account(sql_transaction) {
         match account.kind {
             AccountKind::TFSA | AccountKind::RRSP | AccountKind::FHSA if rule_dep_wdl_cad(transaction) => {
@@ -58,3 +71,29 @@ fn rule_dep_wdl_cad(transaction: &Transaction) -> bool {
 
     false
 }
+
+fn rule_corporate_action(transaction: &Transaction) -> Option<&'static str> {
+    let (ratio, allocation) = match &transaction.action {
+        TxnAction::Split { ratio, .. } => (*ratio, Decimal::ZERO),
+        // a cash merger exchanges no shares and carries no cost
+        TxnAction::Merger {
+            ratio, allocation, ..
+        } if ratio.is_zero() => {
+            return (!allocation.is_zero())
+                .then_some("cash merger should allocate no cost");
+        }
+        TxnAction::Merger {
+            ratio, allocation, ..
+        } => (*ratio, *allocation),
+        TxnAction::SpinOff { allocation, .. } => (Decimal::ONE, *allocation),
+        _ => return None,
+    };
+
+    if ratio <= Decimal::ZERO {
+        Some("ratio should be positive")
+    } else if allocation < Decimal::ZERO || allocation > Decimal::ONE_HUNDRED {
+        Some("allocation should be a percentage")
+    } else {
+        None
+    }
+}
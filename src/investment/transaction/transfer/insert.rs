@@ -0,0 +1,44 @@
+use super::{has_permission, validate_input};
+use crate::database::get_connection;
+use crate::database::transaction::Transfer;
+use crate::error::ServerError;
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    transfer: Transfer,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    // the outgoing leg
+    id: Uuid,
+}
+
+#[post("/api/investment/transaction/transfer/insert")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    // permission check
+    match authenticate(&request.token)? {
+        Some(user) if has_permission(&request.transfer, user, &tran)? => (),
+        _ => return Ok(HttpResponse::Forbidden().finish()),
+    };
+
+    if !request.transfer.id.is_nil() {
+        return Ok(HttpResponse::BadRequest().body("transfer id should be nil"));
+    } else if let Some(err) = validate_input(&request.transfer) {
+        return Ok(HttpResponse::BadRequest().body(err));
+    }
+
+    let id = request.transfer.insert(&tran)?;
+    tran.commit()?;
+    Ok(HttpResponse::Ok().json(Response { id }))
+}
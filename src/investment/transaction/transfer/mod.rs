@@ -0,0 +1,36 @@
+pub mod insert;
+pub mod update;
+
+use crate::database::transaction::Transfer;
+use crate::database::Account;
+use crate::error::ServerError;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+// whether both accounts of the transfer belong to `user`
+fn has_permission(
+    transfer: &Transfer,
+    user: Uuid,
+    transaction: &rusqlite::Transaction,
+) -> Result<bool, ServerError> {
+    for account in [transfer.source, transfer.target] {
+        match Account::by_id(account, transaction)? {
+            Some(account) if account.owner == user => (),
+            _ => return Ok(false),
+        }
+    }
+    Ok(true)
+}
+
+fn validate_input(transfer: &Transfer) -> Option<&'static str> {
+    if transfer.source == transfer.target {
+        Some("transfer should be between two accounts")
+    } else if transfer.asset.0 <= Decimal::ZERO {
+        Some("quantity should be positive")
+    } else if transfer.value.0 < Decimal::ZERO || transfer.fee.0 < Decimal::ZERO
+    {
+        Some("value and fee cannot be negative")
+    } else {
+        None
+    }
+}
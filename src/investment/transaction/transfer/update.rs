@@ -0,0 +1,51 @@
+use super::{has_permission, validate_input};
+use crate::database::get_connection;
+use crate::database::transaction::Transfer;
+use crate::error::ServerError;
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    token: String,
+    transfer: Transfer,
+}
+
+#[post("/api/investment/transaction/transfer/update")]
+pub async fn handler(
+    request: web::Json<Request>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    let transfer = match Transfer::by_leg(request.transfer.id, &tran)? {
+        Some(t) if t.id == request.transfer.id => t,
+        _ => {
+            return Ok(
+                HttpResponse::BadRequest().body("transfer does not exist")
+            )
+        }
+    };
+
+    // permission check
+    match authenticate(&request.token)? {
+        Some(user) if has_permission(&transfer, user, &tran)? => (),
+        _ => return Ok(HttpResponse::Forbidden().finish()),
+    };
+
+    // input check
+    if request.transfer.source != transfer.source
+        || request.transfer.target != transfer.target
+    {
+        return Ok(
+            HttpResponse::BadRequest().body("accounts cannot be modified")
+        );
+    } else if let Some(err) = validate_input(&request.transfer) {
+        return Ok(HttpResponse::BadRequest().body(err));
+    }
+
+    request.transfer.update(&tran)?;
+    tran.commit()?;
+    Ok(HttpResponse::Ok().finish())
+}
@@ -17,14 +17,14 @@ pub async fn handler(
     let mut conn = get_connection()?;
     let tran = conn.transaction()?;
 
-    let transaction =
-        match Transaction::by_id(request.transaction.id, &tran)? {
-            None => {
-                return Ok(HttpResponse::BadRequest()
-                    .body("transaction does not exist"))
-            }
-            Some(t) => t,
-        };
+    let transaction = match Transaction::by_id(request.transaction.id, &tran)? {
+        None => {
+            return Ok(
+                HttpResponse::BadRequest().body("transaction does not exist")
+            )
+        }
+        Some(t) => t,
+    };
 
     // permission check
     if !has_permission(&transaction, &request.token, &tran)? {
@@ -32,13 +32,14 @@ pub async fn handler(
     }
 
     // input check
-    if request.transaction.account != transaction.account {
+    if transaction.action.counterpart().is_some() {
+        return Ok(HttpResponse::BadRequest()
+            .body("transfers are managed through the transfer endpoints"));
+    } else if request.transaction.account != transaction.account {
         return Ok(
             HttpResponse::BadRequest().body("account cannot be modified")
         );
-    } else if let Some(err) =
-        validate_input(&request.transaction, &tran)
-    {
+    } else if let Some(err) = validate_input(&request.transaction, &tran) {
         return Ok(HttpResponse::BadRequest().body(err));
     }
 
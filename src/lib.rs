@@ -2,7 +2,11 @@ mod database;
 mod error;
 mod repository;
 mod auth;
+pub mod export;
+pub mod import;
 pub mod investment;
+pub mod portfolio;
+pub mod provider;
 pub mod user;
 
 // pub mod auth;
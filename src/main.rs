@@ -1,6 +1,7 @@
 use actix_files::Files;
 use actix_web::{web, App, HttpServer};
-use flexfolio::{investment, user};
+use flexfolio::import::history;
+use flexfolio::{investment, provider, user};
 // use server::{auth, constant, investment};
 
 #[actix_web::main]
@@ -11,6 +12,25 @@ async fn main() -> std::io::Result<()> {
         panic!("Fail to initialize the server with error: {}", error)
     }
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("import-history") {
+        import_history(&args[1..]);
+        return Ok(());
+    }
+
+    match provider::Scheduler::from_env() {
+        Ok(Some(scheduler)) => {
+            actix_web::rt::spawn(scheduler.start());
+        }
+        Ok(None) => log::info!("No market data provider configured"),
+        Err(error) => {
+            panic!(
+                "Fail to load the market data provider with error: {}",
+                error
+            )
+        }
+    }
+
     HttpServer::new(move || {
         App::new()
             .service(user::register::handler)
@@ -19,12 +39,37 @@ async fn main() -> std::io::Result<()> {
             .service(user::update::handler)
             .service(user::delete::handler)
             .service(user::exist::handler)
+            .service(user::settings::fetch::handler)
+            .service(user::settings::update::handler)
             .service(investment::account::insert::handler)
             .service(investment::account::fetch::handler)
             .service(investment::account::update::handler)
             .service(investment::account::delete::handler)
+            .service(investment::account::holdings::handler)
+            .service(investment::account::drip::fetch::handler)
+            .service(investment::account::drip::update::handler)
+            .service(investment::asset::insert::handler)
+            .service(investment::asset::import::handler)
+            .service(investment::asset::search::handler)
+            .service(investment::asset::fetch::handler)
+            .service(investment::asset::delete::handler)
             .service(investment::transaction::insert::handler)
             .service(investment::transaction::fetch::handler)
+            .service(investment::transaction::update::handler)
+            .service(investment::transaction::delete::handler)
+            .service(investment::transaction::batch::handler)
+            .service(investment::transaction::drip::handler)
+            .service(investment::transaction::transfer::insert::handler)
+            .service(investment::transaction::transfer::update::handler)
+            .service(investment::transaction::export::handler)
+            .service(investment::transaction::import::handler)
+            .service(investment::fx::insert::handler)
+            .service(investment::fx::convert::handler)
+            .service(investment::provider::status::handler)
+            .service(investment::report::acb::handler)
+            .service(investment::report::gain::handler)
+            .service(investment::report::performance::handler)
+            .service(investment::report::series::handler)
             // .service(investment::account::delete)
             .service(Files::new("/", "dist/").index_file("index.html"))
             .default_service(web::to(flexfolio::index))
@@ -33,3 +78,41 @@ async fn main() -> std::io::Result<()> {
     .run()
     .await
 }
+
+// flexfolio import-history <asset id> <Price|Dividend|Split> <csv file>
+fn import_history(args: &[String]) {
+    let (asset, kind, path) = match args {
+        [asset, kind, path] => (asset, kind, path),
+        _ => {
+            eprintln!(
+                "usage: flexfolio import-history <asset id> \
+                 <Price|Dividend|Split> <csv file>"
+            );
+            std::process::exit(2);
+        }
+    };
+    let asset = asset.parse().unwrap_or_else(|_| {
+        eprintln!("invalid asset id {}", asset);
+        std::process::exit(2);
+    });
+    let kind = kind.clone().try_into().unwrap_or_else(|_| {
+        eprintln!("invalid kind {}, expect Price, Dividend or Split", kind);
+        std::process::exit(2);
+    });
+
+    match history::import_file(asset, kind, path) {
+        Ok(summary) if summary.errors.is_empty() => {
+            println!("imported {} rows", summary.imported);
+        }
+        Ok(summary) => {
+            for error in summary.errors {
+                eprintln!("line {}: {}", error.line, error.message);
+            }
+            std::process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("fail to import with error: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
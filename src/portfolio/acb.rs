@@ -0,0 +1,1552 @@
+use super::split::Event;
+use super::superficial::SuperficialLoss;
+use super::{Exchange, Splits};
+use crate::database::account::AccountKind;
+use crate::database::asset::AssetId;
+use crate::database::transaction::TxnAction;
+use crate::database::{Account, Transaction};
+use crate::error::ServerError;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum AcbAction {
+    Buy,
+    Sell,
+    // units bought by a dividend reinvestment plan
+    ReinvestedDividend,
+    Deposit,
+    Withdrawal,
+    JournalIn,
+    JournalOut,
+    // moved between a registered and a non-registered account
+    TransferIn,
+    TransferOut,
+    // share count multiplied, the total cost is unchanged
+    Split,
+    // shares exchanged for the shares of another company
+    MergerIn,
+    MergerOut,
+    // part of the cost moved to the shares of a new company
+    SpinOffIn,
+    SpinOffOut,
+    // cost adjusted by distributions, a return of capital beyond the ACB is
+    // a capital gain
+    ReturnOfCapital,
+    ReinvestedCapitalGain,
+    // denied superficial loss added to the cost of substituted property
+    SuperficialLoss,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct AcbEntry {
+    pub transaction: Uuid,
+    pub account: Uuid,
+    pub date: NaiveDate,
+    pub action: AcbAction,
+    // changes caused by this entry
+    pub quantity: Decimal,
+    pub acb: Decimal,
+    // only non-zero for dispositions
+    pub proceeds: Decimal,
+    pub outlays: Decimal,
+    pub gain: Decimal,
+    // part of the loss denied by the superficial loss rule
+    pub superficial_loss: Decimal,
+    // loss denied for good on a transfer to a registered account
+    pub denied_loss: Decimal,
+    // the pool holds shares deposited in kind while it was empty, whose
+    // cost is unknown and counted as zero
+    pub unknown_cost: bool,
+    // running totals after this entry
+    pub total_quantity: Decimal,
+    pub total_acb: Decimal,
+    pub acb_per_share: Decimal,
+}
+
+// adjusted cost base of one asset, pooled across all non-registered
+// accounts of a user, in `currency`.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct AcbLedger {
+    pub asset: AssetId,
+    pub currency: AssetId,
+    pub entries: Vec<AcbEntry>,
+}
+
+impl AcbLedger {
+    pub fn new(asset: AssetId, currency: AssetId) -> Self {
+        Self {
+            asset,
+            currency,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn quantity(&self) -> Decimal {
+        self.entries
+            .last()
+            .map(|x| x.total_quantity)
+            .unwrap_or_default()
+    }
+
+    pub fn acb(&self) -> Decimal {
+        self.entries.last().map(|x| x.total_acb).unwrap_or_default()
+    }
+
+    // true if the ACB counts shares of unknown cost as free
+    pub fn unknown_cost(&self) -> bool {
+        self.entries
+            .last()
+            .is_some_and(|x| x.unknown_cost && !x.total_quantity.is_zero())
+    }
+
+    pub fn acb_per_share(&self) -> Decimal {
+        self.entries
+            .last()
+            .map(|x| x.acb_per_share)
+            .unwrap_or_default()
+    }
+
+    // ACB attributed to `quantity` shares at the current average cost
+    fn portion(&self, quantity: Decimal) -> Decimal {
+        let total = self.quantity();
+        if quantity == total {
+            self.acb()
+        } else if total.is_zero() {
+            Decimal::ZERO
+        } else {
+            self.acb() * quantity / total
+        }
+    }
+
+    fn push(
+        &mut self,
+        transaction: &Transaction,
+        action: AcbAction,
+        quantity: Decimal,
+        acb: Decimal,
+    ) -> &mut AcbEntry {
+        // the ACB of an empty pool is kept, a denied superficial loss is
+        // carried to the substituted property acquired later
+        let total_quantity = self.quantity() + quantity;
+        let total_acb = self.acb() + acb;
+        let unknown_cost = self.unknown_cost();
+        let acb_per_share = if total_quantity.is_zero() {
+            Decimal::ZERO
+        } else {
+            total_acb / total_quantity
+        };
+
+        self.entries.push(AcbEntry {
+            transaction: transaction.id,
+            account: transaction.account,
+            date: transaction.date,
+            action,
+            quantity,
+            acb,
+            proceeds: Decimal::ZERO,
+            outlays: Decimal::ZERO,
+            gain: Decimal::ZERO,
+            superficial_loss: Decimal::ZERO,
+            denied_loss: Decimal::ZERO,
+            unknown_cost,
+            total_quantity,
+            total_acb,
+            acb_per_share,
+        });
+        self.entries.last_mut().unwrap()
+    }
+
+    fn split(&mut self, date: NaiveDate, ratio: Decimal) {
+        let quantity = self.quantity() * ratio - self.quantity();
+        let total_quantity = self.quantity() + quantity;
+        let total_acb = self.acb();
+        let unknown_cost = self.unknown_cost();
+        let acb_per_share = if total_quantity.is_zero() {
+            Decimal::ZERO
+        } else {
+            total_acb / total_quantity
+        };
+
+        self.entries.push(AcbEntry {
+            transaction: Uuid::nil(),
+            account: Uuid::nil(),
+            date,
+            action: AcbAction::Split,
+            quantity,
+            acb: Decimal::ZERO,
+            proceeds: Decimal::ZERO,
+            outlays: Decimal::ZERO,
+            gain: Decimal::ZERO,
+            superficial_loss: Decimal::ZERO,
+            denied_loss: Decimal::ZERO,
+            unknown_cost,
+            total_quantity,
+            total_acb,
+            acb_per_share,
+        });
+    }
+}
+
+impl AcbLedger {
+    // build the ledgers of every asset appearing in `transactions`.
+    // transactions of `registered` accounts have no ACB, they are only used
+    // to detect superficial losses.
+    pub fn build(
+        transactions: &[Transaction],
+        registered: &HashSet<Uuid>,
+        currency: &AssetId,
+        splits: &Splits,
+        exchange: &impl Exchange,
+    ) -> Result<BTreeMap<AssetId, AcbLedger>, ServerError> {
+        let events = splits.timeline(transactions);
+        let superficial = SuperficialLoss::new(&events, registered);
+
+        let mut ledgers = BTreeMap::<AssetId, AcbLedger>::new();
+        // quantity held by each account, needed to resolve journals
+        let mut held = HashMap::<(Uuid, AssetId), Decimal>::new();
+        // account of every transaction, needed to resolve transfers
+        let accounts: HashMap<_, _> =
+            transactions.iter().map(|x| (x.id, x.account)).collect();
+        let from_registered = |counterpart: &Uuid| {
+            accounts
+                .get(counterpart)
+                .is_some_and(|x| registered.contains(x))
+        };
+
+        macro_rules! ledger {
+            ($asset:expr) => {
+                ledgers.entry($asset.clone()).or_insert_with(|| {
+                    AcbLedger::new($asset.clone(), currency.clone())
+                })
+            };
+        }
+
+        for event in &events {
+            let txn = match event {
+                Event::Split { date, asset, ratio } => {
+                    if let Some(ledger) = ledgers.get_mut(*asset) {
+                        if !ledger.quantity().is_zero() {
+                            ledger.split(*date, *ratio);
+                        }
+                    }
+                    held.iter_mut()
+                        .filter(|(key, _)| key.1 == **asset)
+                        .for_each(|(_, quantity)| *quantity *= *ratio);
+                    continue;
+                }
+                Event::Transaction(txn)
+                    if registered.contains(&txn.account) =>
+                {
+                    continue
+                }
+                Event::Transaction(txn) => txn,
+            };
+            match &txn.action {
+                TxnAction::Buy { asset, cash, fee }
+                    if !is_currency(&asset.1) =>
+                {
+                    let cost = exchange.convert(cash, currency, txn.date)?
+                        + exchange.convert(fee, currency, txn.date)?;
+                    ledger!(asset.1).push(txn, AcbAction::Buy, asset.0, cost);
+                    *held.entry((txn.account, asset.1.clone())).or_default() +=
+                        asset.0;
+                }
+                TxnAction::ReinvestedDividend { asset, value, fee }
+                    if !is_currency(&asset.1) =>
+                {
+                    // the tax withheld is not reinvested
+                    let cost = exchange.convert(value, currency, txn.date)?
+                        - exchange.convert(fee, currency, txn.date)?;
+                    ledger!(asset.1).push(
+                        txn,
+                        AcbAction::ReinvestedDividend,
+                        asset.0,
+                        cost,
+                    );
+                    *held.entry((txn.account, asset.1.clone())).or_default() +=
+                        asset.0;
+                }
+                TxnAction::Sell { asset, cash, fee }
+                    if !is_currency(&asset.1) =>
+                {
+                    let proceeds =
+                        exchange.convert(cash, currency, txn.date)?;
+                    let outlays = exchange.convert(fee, currency, txn.date)?;
+                    let ledger = ledger!(asset.1);
+                    let acb = ledger.portion(asset.0);
+                    let gain = proceeds - outlays - acb;
+                    let denied = superficial
+                        .check(&asset.1, txn.date, asset.0)
+                        .filter(|_| gain < Decimal::ZERO)
+                        .map(|(denied, unregistered)| {
+                            (-gain * denied, unregistered)
+                        });
+
+                    let entry =
+                        ledger.push(txn, AcbAction::Sell, -asset.0, -acb);
+                    entry.proceeds = proceeds;
+                    entry.outlays = outlays;
+                    entry.gain = gain;
+                    if let Some((denied, unregistered)) = denied {
+                        entry.gain += denied;
+                        entry.superficial_loss = denied;
+                        // substituted property in registered accounts has no
+                        // ACB, that part of the loss is lost for good
+                        ledger.push(
+                            txn,
+                            AcbAction::SuperficialLoss,
+                            Decimal::ZERO,
+                            denied * unregistered,
+                        );
+                    }
+                    *held.entry((txn.account, asset.1.clone())).or_default() -=
+                        asset.0;
+                }
+                TxnAction::Deposit { value, .. } if !is_currency(&value.1) => {
+                    // in-kind transfers keep the average cost of the pool,
+                    // which is unknown if there is none
+                    let ledger = ledger!(value.1);
+                    let acb = ledger.acb_per_share() * value.0;
+                    let empty = ledger.quantity() <= Decimal::ZERO;
+                    ledger
+                        .push(txn, AcbAction::Deposit, value.0, acb)
+                        .unknown_cost |= empty;
+                    *held.entry((txn.account, value.1.clone())).or_default() +=
+                        value.0;
+                }
+                TxnAction::Withdrawal { value, .. }
+                    if !is_currency(&value.1) =>
+                {
+                    let ledger = ledger!(value.1);
+                    let acb = ledger.portion(value.0);
+                    ledger.push(txn, AcbAction::Withdrawal, -value.0, -acb);
+                    *held.entry((txn.account, value.1.clone())).or_default() -=
+                        value.0;
+                }
+                TxnAction::TransferOut {
+                    asset,
+                    value,
+                    fee,
+                    counterpart,
+                } if !is_currency(&asset.1) => {
+                    *held.entry((txn.account, asset.1.clone())).or_default() -=
+                        asset.0;
+                    // moving within the pooled accounts changes nothing
+                    if !from_registered(counterpart) {
+                        continue;
+                    }
+
+                    // a deemed disposition at fair market value, where a
+                    // loss is denied for good
+                    let proceeds =
+                        exchange.convert(value, currency, txn.date)?;
+                    let outlays = exchange.convert(fee, currency, txn.date)?;
+                    let ledger = ledger!(asset.1);
+                    let acb = ledger.portion(asset.0);
+                    let gain = proceeds - outlays - acb;
+
+                    let entry = ledger.push(
+                        txn,
+                        AcbAction::TransferOut,
+                        -asset.0,
+                        -acb,
+                    );
+                    entry.proceeds = proceeds;
+                    entry.outlays = outlays;
+                    entry.gain = gain.max(Decimal::ZERO);
+                    entry.denied_loss = (-gain).max(Decimal::ZERO);
+                }
+                TxnAction::TransferIn {
+                    asset,
+                    value,
+                    counterpart,
+                } if !is_currency(&asset.1) => {
+                    *held.entry((txn.account, asset.1.clone())).or_default() +=
+                        asset.0;
+                    if !from_registered(counterpart) {
+                        continue;
+                    }
+
+                    // acquired at fair market value
+                    let cost = exchange.convert(value, currency, txn.date)?;
+                    ledger!(asset.1).push(
+                        txn,
+                        AcbAction::TransferIn,
+                        asset.0,
+                        cost,
+                    );
+                }
+                TxnAction::Split { asset, ratio, cash }
+                    if !is_currency(asset) =>
+                {
+                    // only the shares of this account are split
+                    let quantity =
+                        held.entry((txn.account, asset.clone())).or_default();
+                    let delta = *quantity * ratio - *quantity;
+                    *quantity += delta;
+                    if !delta.is_zero() {
+                        ledger!(asset).push(
+                            txn,
+                            AcbAction::Split,
+                            delta,
+                            Decimal::ZERO,
+                        );
+                    }
+
+                    // the fraction left over is disposed of for the cash
+                    let fraction = quantity.fract();
+                    let cash = match cash {
+                        Some(x) if !fraction.is_zero() => x,
+                        _ => continue,
+                    };
+                    *quantity -= fraction;
+                    let proceeds =
+                        exchange.convert(cash, currency, txn.date)?;
+                    let ledger = ledger!(asset);
+                    let acb = ledger.portion(fraction);
+                    let entry =
+                        ledger.push(txn, AcbAction::Sell, -fraction, -acb);
+                    entry.proceeds = proceeds;
+                    entry.gain = proceeds - acb;
+                }
+                TxnAction::Merger {
+                    source,
+                    target,
+                    ratio,
+                    cash,
+                    allocation,
+                } if !is_currency(source) && !is_currency(target) => {
+                    let quantity = held
+                        .remove(&(txn.account, source.clone()))
+                        .unwrap_or_default();
+                    if quantity.is_zero() {
+                        continue;
+                    }
+
+                    let source_ledger = ledger!(source);
+                    let acb = source_ledger.portion(quantity);
+                    let carried = acb * allocation / Decimal::ONE_HUNDRED;
+                    let exchanged =
+                        quantity * allocation / Decimal::ONE_HUNDRED;
+
+                    // the shares exchanged for cash are disposed of
+                    let proceeds =
+                        exchange.convert(cash, currency, txn.date)?;
+                    if exchanged != quantity || !proceeds.is_zero() {
+                        let entry = source_ledger.push(
+                            txn,
+                            AcbAction::Sell,
+                            exchanged - quantity,
+                            carried - acb,
+                        );
+                        entry.proceeds = proceeds;
+                        entry.gain = proceeds - (acb - carried);
+                    }
+                    if !exchanged.is_zero() {
+                        source_ledger.push(
+                            txn,
+                            AcbAction::MergerOut,
+                            -exchanged,
+                            -carried,
+                        );
+                    }
+
+                    // the new shares are received even when no cost is
+                    // allocated to them, nothing is received in a cash merger
+                    let received = quantity * ratio;
+                    if received.is_zero() && carried.is_zero() {
+                        continue;
+                    }
+                    ledger!(target).push(
+                        txn,
+                        AcbAction::MergerIn,
+                        received,
+                        carried,
+                    );
+                    *held.entry((txn.account, target.clone())).or_default() +=
+                        received;
+                }
+                TxnAction::SpinOff {
+                    source,
+                    target,
+                    allocation,
+                } if !is_currency(source) && !is_currency(&target.1) => {
+                    let quantity = held
+                        .get(&(txn.account, source.clone()))
+                        .cloned()
+                        .unwrap_or_default();
+                    let source_ledger = ledger!(source);
+                    let moved = source_ledger.portion(quantity) * allocation
+                        / Decimal::ONE_HUNDRED;
+                    source_ledger.push(
+                        txn,
+                        AcbAction::SpinOffOut,
+                        Decimal::ZERO,
+                        -moved,
+                    );
+                    ledger!(target.1).push(
+                        txn,
+                        AcbAction::SpinOffIn,
+                        target.0,
+                        moved,
+                    );
+                    *held
+                        .entry((txn.account, target.1.clone()))
+                        .or_default() += target.0;
+                }
+                TxnAction::ReturnOfCapital { source, value }
+                    if !is_currency(source) =>
+                {
+                    let value = exchange.convert(value, currency, txn.date)?;
+                    let ledger = ledger!(source);
+                    let reduced = value.min(ledger.acb().max(Decimal::ZERO));
+                    let entry = ledger.push(
+                        txn,
+                        AcbAction::ReturnOfCapital,
+                        Decimal::ZERO,
+                        -reduced,
+                    );
+                    // the ACB cannot go below zero
+                    entry.proceeds = value - reduced;
+                    entry.gain = value - reduced;
+                }
+                TxnAction::ReinvestedCapitalGain { source, value }
+                    if !is_currency(source) =>
+                {
+                    let value = exchange.convert(value, currency, txn.date)?;
+                    ledger!(source).push(
+                        txn,
+                        AcbAction::ReinvestedCapitalGain,
+                        Decimal::ZERO,
+                        value,
+                    );
+                }
+                TxnAction::Journal { source, target, .. }
+                    if !is_currency(source) && !is_currency(target) =>
+                {
+                    let quantity = held
+                        .remove(&(txn.account, source.clone()))
+                        .unwrap_or_default();
+                    if quantity.is_zero() {
+                        continue;
+                    }
+
+                    let source_ledger = ledger!(source);
+                    let acb = source_ledger.portion(quantity);
+                    source_ledger.push(
+                        txn,
+                        AcbAction::JournalOut,
+                        -quantity,
+                        -acb,
+                    );
+                    ledger!(target).push(
+                        txn,
+                        AcbAction::JournalIn,
+                        quantity,
+                        acb,
+                    );
+                    *held.entry((txn.account, target.clone())).or_default() +=
+                        quantity;
+                }
+                _ => (),
+            }
+        }
+
+        Ok(ledgers)
+    }
+
+    pub fn by_owner(
+        owner: Uuid,
+        currency: &AssetId,
+        exchange: &impl Exchange,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<BTreeMap<AssetId, AcbLedger>, ServerError> {
+        let mut transactions = Vec::new();
+        let mut registered = HashSet::new();
+        for account in Account::by_owner(owner, transaction)? {
+            if account.kind != AccountKind::NRA {
+                registered.insert(account.id);
+            }
+            transactions
+                .extend(Transaction::by_account(account.id, transaction)?);
+        }
+        let splits =
+            Splits::by_transactions(&transactions, Some(owner), transaction)?;
+
+        Self::build(&transactions, &registered, currency, &splits, exchange)
+    }
+}
+
+fn is_currency(asset: &AssetId) -> bool {
+    matches!(asset, AssetId::CURRENCY(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, User};
+    use crate::portfolio::Holding;
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    struct FixedExchange;
+
+    impl Exchange for FixedExchange {
+        fn rate(
+            &self,
+            from: &AssetId,
+            to: &AssetId,
+            _: NaiveDate,
+        ) -> Result<Decimal, ServerError> {
+            match (from, to) {
+                (AssetId::CURRENCY(f), AssetId::CURRENCY(t))
+                    if f == "USD" && t == "CAD" =>
+                {
+                    Ok(dec!(1.3))
+                }
+                _ => Err(ServerError::Internal(String::from("no rate"))),
+            }
+        }
+    }
+
+    fn cad(value: Decimal) -> (Decimal, AssetId) {
+        (value, AssetId::currency("CAD"))
+    }
+
+    fn usd(value: Decimal) -> (Decimal, AssetId) {
+        (value, AssetId::currency("USD"))
+    }
+
+    #[test]
+    fn test_build() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let account = Uuid::new_v4();
+        let transactions = vec![
+            Transaction::new(
+                account,
+                date!(2020, 3, 1),
+                TxnAction::Sell {
+                    asset: (dec!(75), xyz.clone()),
+                    cash: cad(dec!(1125)),
+                    fee: cad(dec!(10)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), xyz.clone()),
+                    cash: cad(dec!(1000)),
+                    fee: cad(dec!(10)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2020, 2, 1),
+                TxnAction::Buy {
+                    asset: (dec!(50), xyz.clone()),
+                    cash: cad(dec!(600)),
+                    fee: cad(dec!(10)),
+                },
+            ),
+        ];
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &FixedExchange,
+        )?;
+        let ledger = ledgers.get(&xyz).expect("no ledger");
+        assert_eq!(3, ledger.entries.len());
+
+        assert_eq!(AcbAction::Buy, ledger.entries[0].action);
+        assert_eq!(dec!(1010), ledger.entries[0].total_acb);
+        assert_eq!(dec!(10.10), ledger.entries[0].acb_per_share);
+
+        assert_eq!(dec!(1620), ledger.entries[1].total_acb);
+        assert_eq!(dec!(10.80), ledger.entries[1].acb_per_share);
+
+        let sell = &ledger.entries[2];
+        assert_eq!(AcbAction::Sell, sell.action);
+        assert_eq!(dec!(-75), sell.quantity);
+        assert_eq!(dec!(-810), sell.acb);
+        assert_eq!(dec!(1125), sell.proceeds);
+        assert_eq!(dec!(10), sell.outlays);
+        assert_eq!(dec!(305), sell.gain);
+        assert_eq!(dec!(75), ledger.quantity());
+        assert_eq!(dec!(810), ledger.acb());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let account = Uuid::new_v4();
+        let splits = Splits::new(HashMap::from([(
+            xyz.clone(),
+            vec![
+                (date!(2020, 2, 1), dec!(2)),
+                (date!(2020, 6, 1), dec!(0.25)),
+            ],
+        )]));
+        let transactions = vec![
+            Transaction::new(
+                account,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(15), xyz.clone()),
+                    cash: cad(dec!(300)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            // 7.5 shares left after the reverse split, 0.5 paid in cash
+            Transaction::new(
+                account,
+                date!(2020, 6, 1),
+                TxnAction::Sell {
+                    asset: (dec!(0.5), xyz.clone()),
+                    cash: cad(dec!(40)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+        ];
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &splits,
+            &FixedExchange,
+        )?;
+        let ledger = ledgers.get(&xyz).expect("no ledger");
+        assert_eq!(4, ledger.entries.len());
+
+        let split = &ledger.entries[1];
+        assert_eq!(AcbAction::Split, split.action);
+        assert_eq!(dec!(15), split.quantity);
+        assert_eq!(dec!(300), split.total_acb);
+        assert_eq!(dec!(10), split.acb_per_share);
+
+        let split = &ledger.entries[2];
+        assert_eq!(dec!(-22.5), split.quantity);
+        assert_eq!(dec!(40), split.acb_per_share);
+
+        let sell = &ledger.entries[3];
+        assert_eq!(dec!(-20), sell.acb);
+        assert_eq!(dec!(20), sell.gain);
+        assert_eq!(dec!(7), ledger.quantity());
+        assert_eq!(dec!(280), ledger.acb());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cash_in_lieu() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let abc = AssetId::stock("TSE", "ABC");
+        let account = Uuid::new_v4();
+        // the split of ABC is known already
+        let splits = Splits::new(HashMap::from([(
+            abc.clone(),
+            vec![(date!(2020, 6, 1), dec!(0.25))],
+        )]));
+        let mut transactions = Vec::new();
+        for asset in [&xyz, &abc] {
+            transactions.push(Transaction::new(
+                account,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(15), asset.clone()),
+                    cash: cad(dec!(300)),
+                    fee: cad(dec!(0)),
+                },
+            ));
+            transactions.push(Transaction::new(
+                account,
+                date!(2020, 6, 1),
+                TxnAction::Split {
+                    asset: asset.clone(),
+                    ratio: dec!(0.25),
+                    cash: Some(cad(dec!(40))),
+                },
+            ));
+        }
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &splits,
+            &FixedExchange,
+        )?;
+        for asset in [&xyz, &abc] {
+            let ledger = ledgers.get(asset).expect("no ledger");
+            assert_eq!(
+                vec![AcbAction::Buy, AcbAction::Split, AcbAction::Sell],
+                ledger.entries.iter().map(|x| x.action).collect::<Vec<_>>()
+            );
+            // 3.75 shares at 80, the fraction is sold at a loss
+            let sell = &ledger.entries[2];
+            assert_eq!(dec!(-0.75), sell.quantity);
+            assert_eq!(dec!(-60), sell.acb);
+            assert_eq!(dec!(40), sell.proceeds);
+            assert_eq!(dec!(-20), sell.gain);
+            assert_eq!(dec!(3), ledger.quantity());
+            assert_eq!(dec!(240), ledger.acb());
+        }
+
+        let holding =
+            Holding::replay(&transactions, &splits, date!(2020, 6, 1));
+        assert_eq!(dec!(3), holding.quantity(&xyz));
+        assert_eq!(dec!(3), holding.quantity(&abc));
+        assert_eq!(dec!(-520), holding.quantity(&AssetId::currency("CAD")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_foreign_currency() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("NYSE", "XYZ");
+        let account = Uuid::new_v4();
+        let transactions = vec![
+            Transaction::new(
+                account,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(10), xyz.clone()),
+                    cash: usd(dec!(100)),
+                    fee: cad(dec!(5)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2020, 2, 1),
+                TxnAction::Sell {
+                    asset: (dec!(10), xyz.clone()),
+                    cash: usd(dec!(200)),
+                    fee: usd(dec!(1)),
+                },
+            ),
+        ];
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &FixedExchange,
+        )?;
+        let ledger = ledgers.get(&xyz).expect("no ledger");
+        assert_eq!(dec!(135), ledger.entries[0].total_acb);
+        assert_eq!(dec!(260), ledger.entries[1].proceeds);
+        assert_eq!(dec!(1.3), ledger.entries[1].outlays);
+        assert_eq!(dec!(123.7), ledger.entries[1].gain);
+        assert_eq!(Decimal::ZERO, ledger.acb());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_journal() -> Result<(), ServerError> {
+        let dlr = AssetId::stock("TSE", "DLR");
+        let dlr_u = AssetId::stock("TSE", "DLR.U");
+        let (a0, a1) = (Uuid::new_v4(), Uuid::new_v4());
+        let transactions = vec![
+            Transaction::new(
+                a0,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(10), dlr.clone()),
+                    cash: cad(dec!(100)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                a1,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(30), dlr.clone()),
+                    cash: cad(dec!(340)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                a0,
+                date!(2020, 1, 5),
+                TxnAction::Journal {
+                    source: dlr.clone(),
+                    target: dlr_u.clone(),
+                    fee: cad(dec!(0)),
+                },
+            ),
+        ];
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &FixedExchange,
+        )?;
+        let source = ledgers.get(&dlr).expect("no ledger");
+        assert_eq!(AcbAction::JournalOut, source.entries[2].action);
+        assert_eq!(dec!(30), source.quantity());
+        assert_eq!(dec!(330), source.acb());
+
+        let target = ledgers.get(&dlr_u).expect("no ledger");
+        assert_eq!(AcbAction::JournalIn, target.entries[0].action);
+        assert_eq!(dec!(10), target.quantity());
+        assert_eq!(dec!(110), target.acb());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_cost() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let account = Uuid::new_v4();
+        let transactions: Vec<_> = [
+            (
+                date!(2020, 1, 1),
+                TxnAction::Deposit {
+                    value: (dec!(10), xyz.clone()),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            (
+                date!(2020, 2, 1),
+                TxnAction::Sell {
+                    asset: (dec!(10), xyz.clone()),
+                    cash: cad(dec!(100)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            (
+                date!(2020, 3, 1),
+                TxnAction::Buy {
+                    asset: (dec!(10), xyz.clone()),
+                    cash: cad(dec!(100)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            (
+                date!(2020, 4, 1),
+                TxnAction::Deposit {
+                    value: (dec!(10), xyz.clone()),
+                    fee: cad(dec!(0)),
+                },
+            ),
+        ]
+        .into_iter()
+        .map(|(date, action)| Transaction::new(account, date, action))
+        .collect();
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &FixedExchange,
+        )?;
+        let ledger = ledgers.get(&xyz).expect("no ledger");
+        // the sale of shares deposited into an empty pool has no known cost
+        assert!(ledger.entries[0].unknown_cost);
+        assert!(ledger.entries[1].unknown_cost);
+        assert_eq!(dec!(100), ledger.entries[1].gain);
+        // a deposit into a pool with a cost takes its average cost
+        assert!(!ledger.entries[2].unknown_cost);
+        assert!(!ledger.entries[3].unknown_cost);
+        assert!(!ledger.unknown_cost());
+        assert_eq!(dec!(200), ledger.acb());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transfer() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let (a0, a1, a2) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut transactions = vec![
+            Transaction::new(
+                a0,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), xyz.clone()),
+                    cash: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            // to another non-registered account
+            Transaction::new(
+                a0,
+                date!(2020, 2, 1),
+                TxnAction::TransferOut {
+                    asset: (dec!(20), xyz.clone()),
+                    value: cad(dec!(300)),
+                    fee: cad(dec!(0)),
+                    counterpart: Uuid::new_v4(),
+                },
+            ),
+            Transaction::new(
+                a2,
+                date!(2020, 2, 1),
+                TxnAction::TransferIn {
+                    asset: (dec!(20), xyz.clone()),
+                    value: cad(dec!(300)),
+                    counterpart: Uuid::new_v4(),
+                },
+            ),
+            // contributed in kind to a registered account
+            Transaction::new(
+                a0,
+                date!(2020, 3, 1),
+                TxnAction::TransferOut {
+                    asset: (dec!(40), xyz.clone()),
+                    value: cad(dec!(600)),
+                    fee: cad(dec!(5)),
+                    counterpart: Uuid::new_v4(),
+                },
+            ),
+            Transaction::new(
+                a1,
+                date!(2020, 3, 1),
+                TxnAction::TransferIn {
+                    asset: (dec!(40), xyz.clone()),
+                    value: cad(dec!(600)),
+                    counterpart: Uuid::new_v4(),
+                },
+            ),
+            // a loss on the way in is denied
+            Transaction::new(
+                a2,
+                date!(2020, 4, 1),
+                TxnAction::TransferOut {
+                    asset: (dec!(20), xyz.clone()),
+                    value: cad(dec!(150)),
+                    fee: cad(dec!(0)),
+                    counterpart: Uuid::new_v4(),
+                },
+            ),
+            Transaction::new(
+                a1,
+                date!(2020, 4, 1),
+                TxnAction::TransferIn {
+                    asset: (dec!(20), xyz.clone()),
+                    value: cad(dec!(150)),
+                    counterpart: Uuid::new_v4(),
+                },
+            ),
+            // withdrawn in kind from the registered account
+            Transaction::new(
+                a1,
+                date!(2020, 5, 1),
+                TxnAction::TransferOut {
+                    asset: (dec!(10), xyz.clone()),
+                    value: cad(dec!(120)),
+                    fee: cad(dec!(0)),
+                    counterpart: Uuid::new_v4(),
+                },
+            ),
+            Transaction::new(
+                a0,
+                date!(2020, 5, 1),
+                TxnAction::TransferIn {
+                    asset: (dec!(10), xyz.clone()),
+                    value: cad(dec!(120)),
+                    counterpart: Uuid::new_v4(),
+                },
+            ),
+        ];
+        transactions.iter_mut().for_each(|x| x.id = Uuid::new_v4());
+        for i in (1..transactions.len()).step_by(2) {
+            let (out, inc) = (transactions[i].id, transactions[i + 1].id);
+            for (txn, id) in [(i, inc), (i + 1, out)] {
+                match &mut transactions[txn].action {
+                    TxnAction::TransferOut { counterpart, .. }
+                    | TxnAction::TransferIn { counterpart, .. } => {
+                        *counterpart = id
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::from([a1]),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &FixedExchange,
+        )?;
+        let ledger = ledgers.get(&xyz).expect("no ledger");
+        assert_eq!(
+            vec![
+                AcbAction::Buy,
+                AcbAction::TransferOut,
+                AcbAction::TransferOut,
+                AcbAction::TransferIn
+            ],
+            ledger.entries.iter().map(|x| x.action).collect::<Vec<_>>()
+        );
+        let out = &ledger.entries[1];
+        assert_eq!(dec!(-400), out.acb);
+        assert_eq!(dec!(195), out.gain);
+        let out = &ledger.entries[2];
+        assert_eq!(dec!(-200), out.acb);
+        assert_eq!(dec!(0), out.gain);
+        assert_eq!(dec!(50), out.denied_loss);
+        assert_eq!(dec!(0), out.superficial_loss);
+        assert_eq!(dec!(50), ledger.quantity());
+        assert_eq!(dec!(520), ledger.acb());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_corporate_action() -> Result<(), ServerError> {
+        let abc = AssetId::stock("TSE", "ABC");
+        let def = AssetId::stock("TSE", "DEF");
+        let ghi = AssetId::stock("TSE", "GHI");
+        let account = Uuid::new_v4();
+        let transactions = vec![
+            Transaction::new(
+                account,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), abc.clone()),
+                    cash: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2020, 2, 1),
+                TxnAction::Split {
+                    asset: abc.clone(),
+                    ratio: dec!(2),
+                    cash: None,
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2020, 3, 1),
+                TxnAction::Merger {
+                    source: abc.clone(),
+                    target: def.clone(),
+                    ratio: dec!(0.5),
+                    cash: cad(dec!(300)),
+                    allocation: dec!(80),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2020, 4, 1),
+                TxnAction::SpinOff {
+                    source: def.clone(),
+                    target: (dec!(20), ghi.clone()),
+                    allocation: dec!(25),
+                },
+            ),
+        ];
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &FixedExchange,
+        )?;
+        let source = ledgers.get(&abc).expect("no ledger");
+        assert_eq!(
+            vec![
+                AcbAction::Buy,
+                AcbAction::Split,
+                AcbAction::Sell,
+                AcbAction::MergerOut
+            ],
+            source.entries.iter().map(|x| x.action).collect::<Vec<_>>()
+        );
+        assert_eq!(dec!(5), source.entries[1].acb_per_share);
+        // the cash is paid for a fifth of the shares
+        let sell = &source.entries[2];
+        assert_eq!(dec!(-40), sell.quantity);
+        assert_eq!(dec!(-200), sell.acb);
+        assert_eq!(dec!(100), sell.gain);
+        assert_eq!(dec!(0), source.quantity());
+        assert_eq!(dec!(0), source.acb());
+
+        let target = ledgers.get(&def).expect("no ledger");
+        assert_eq!(AcbAction::MergerIn, target.entries[0].action);
+        assert_eq!(dec!(100), target.entries[0].quantity);
+        assert_eq!(dec!(800), target.entries[0].acb);
+        assert_eq!(AcbAction::SpinOffOut, target.entries[1].action);
+        assert_eq!(dec!(100), target.quantity());
+        assert_eq!(dec!(600), target.acb());
+
+        let spun = ledgers.get(&ghi).expect("no ledger");
+        assert_eq!(dec!(20), spun.quantity());
+        assert_eq!(dec!(200), spun.acb());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merger() -> Result<(), ServerError> {
+        let abc = AssetId::stock("TSE", "ABC");
+        let def = AssetId::stock("TSE", "DEF");
+        let ghi = AssetId::stock("TSE", "GHI");
+        let (a0, a1) = (Uuid::new_v4(), Uuid::new_v4());
+        let buy = |account, asset: &AssetId| {
+            Transaction::new(
+                account,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), asset.clone()),
+                    cash: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            )
+        };
+        let transactions = vec![
+            buy(a0, &abc),
+            buy(a1, &ghi),
+            // bought out for cash only
+            Transaction::new(
+                a0,
+                date!(2020, 3, 1),
+                TxnAction::Merger {
+                    source: abc.clone(),
+                    target: def.clone(),
+                    ratio: dec!(0),
+                    cash: cad(dec!(1200)),
+                    allocation: dec!(0),
+                },
+            ),
+            // the whole cost is attributed to the cash
+            Transaction::new(
+                a1,
+                date!(2020, 3, 1),
+                TxnAction::Merger {
+                    source: ghi.clone(),
+                    target: def.clone(),
+                    ratio: dec!(0.5),
+                    cash: cad(dec!(900)),
+                    allocation: dec!(0),
+                },
+            ),
+            Transaction::new(
+                a1,
+                date!(2020, 4, 1),
+                TxnAction::Sell {
+                    asset: (dec!(50), def.clone()),
+                    cash: cad(dec!(100)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+        ];
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &FixedExchange,
+        )?;
+        let source = ledgers.get(&abc).expect("no ledger");
+        assert_eq!(AcbAction::Sell, source.entries[1].action);
+        assert_eq!(dec!(-100), source.entries[1].quantity);
+        assert_eq!(dec!(200), source.entries[1].gain);
+        assert_eq!(dec!(0), source.quantity());
+
+        let source = ledgers.get(&ghi).expect("no ledger");
+        assert_eq!(2, source.entries.len());
+        assert_eq!(dec!(-100), source.entries[1].gain);
+        assert_eq!(dec!(0), source.quantity());
+
+        // only the shares of the second merger, received at no cost
+        let target = ledgers.get(&def).expect("no ledger");
+        assert_eq!(AcbAction::MergerIn, target.entries[0].action);
+        assert_eq!(dec!(50), target.entries[0].quantity);
+        assert_eq!(dec!(0), target.entries[0].acb);
+        assert_eq!(dec!(100), target.entries[1].gain);
+        assert_eq!(dec!(0), target.quantity());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recorded_split() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let (a0, a1) = (Uuid::new_v4(), Uuid::new_v4());
+        let splits = Splits::new(HashMap::from([(
+            xyz.clone(),
+            vec![(date!(2020, 2, 1), dec!(2))],
+        )]));
+        let buy = |account| {
+            Transaction::new(
+                account,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), xyz.clone()),
+                    cash: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            )
+        };
+        let sell = |account| {
+            Transaction::new(
+                account,
+                date!(2020, 3, 1),
+                TxnAction::Sell {
+                    asset: (dec!(200), xyz.clone()),
+                    cash: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            )
+        };
+        // the split is known already, only one of the accounts recorded it
+        let transactions = vec![
+            buy(a0),
+            buy(a1),
+            Transaction::new(
+                a0,
+                date!(2020, 2, 1),
+                TxnAction::Split {
+                    asset: xyz.clone(),
+                    ratio: dec!(2),
+                    cash: None,
+                },
+            ),
+            sell(a0),
+            sell(a1),
+        ];
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &splits,
+            &FixedExchange,
+        )?;
+        let ledger = ledgers.get(&xyz).expect("no ledger");
+        assert_eq!(
+            vec![
+                AcbAction::Buy,
+                AcbAction::Buy,
+                AcbAction::Split,
+                AcbAction::Sell,
+                AcbAction::Sell
+            ],
+            ledger.entries.iter().map(|x| x.action).collect::<Vec<_>>()
+        );
+        assert_eq!(dec!(400), ledger.entries[2].total_quantity);
+        assert_eq!(dec!(0), ledger.quantity());
+        assert_eq!(dec!(0), ledger.acb());
+
+        let holding = Holding::replay(
+            transactions.iter().filter(|x| x.account == a0),
+            &splits,
+            date!(2020, 2, 1),
+        );
+        assert_eq!(dec!(200), holding.quantity(&xyz));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_distribution() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let account = Uuid::new_v4();
+        let transactions = vec![
+            Transaction::new(
+                account,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), xyz.clone()),
+                    cash: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2020, 12, 31),
+                TxnAction::ReturnOfCapital {
+                    source: xyz.clone(),
+                    value: cad(dec!(300)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2020, 12, 31),
+                TxnAction::ReinvestedCapitalGain {
+                    source: xyz.clone(),
+                    value: usd(dec!(100)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2021, 12, 31),
+                TxnAction::ReturnOfCapital {
+                    source: xyz.clone(),
+                    value: cad(dec!(900)),
+                },
+            ),
+        ];
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &FixedExchange,
+        )?;
+        let ledger = ledgers.get(&xyz).expect("no ledger");
+        assert_eq!(dec!(700), ledger.entries[1].total_acb);
+        assert_eq!(dec!(0), ledger.entries[1].gain);
+        assert_eq!(dec!(830), ledger.entries[2].total_acb);
+        assert_eq!(dec!(8.30), ledger.entries[2].acb_per_share);
+
+        // the ACB beyond zero is a capital gain
+        let excess = &ledger.entries[3];
+        assert_eq!(dec!(-830), excess.acb);
+        assert_eq!(dec!(70), excess.gain);
+        assert_eq!(dec!(100), ledger.quantity());
+        assert_eq!(dec!(0), ledger.acb());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_superficial_loss() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let (a0, a1) = (Uuid::new_v4(), Uuid::new_v4());
+        let transactions = vec![
+            Transaction::new(
+                a0,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), xyz.clone()),
+                    cash: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                a0,
+                date!(2020, 3, 1),
+                TxnAction::Sell {
+                    asset: (dec!(100), xyz.clone()),
+                    cash: cad(dec!(800)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                a0,
+                date!(2020, 3, 31),
+                TxnAction::Buy {
+                    asset: (dec!(100), xyz.clone()),
+                    cash: cad(dec!(850)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+        ];
+
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &FixedExchange,
+        )?;
+        let ledger = ledgers.get(&xyz).expect("no ledger");
+        let sell = &ledger.entries[1];
+        assert_eq!(dec!(200), sell.superficial_loss);
+        assert_eq!(dec!(0), sell.gain);
+        assert_eq!(AcbAction::SuperficialLoss, ledger.entries[2].action);
+        assert_eq!(dec!(200), ledger.entries[2].acb);
+        assert_eq!(dec!(100), ledger.quantity());
+        assert_eq!(dec!(1050), ledger.acb());
+
+        // substituted property bought in a registered account
+        let mut transactions = transactions;
+        transactions[2].account = a1;
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::from([a1]),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &FixedExchange,
+        )?;
+        let ledger = ledgers.get(&xyz).expect("no ledger");
+        assert_eq!(dec!(200), ledger.entries[1].superficial_loss);
+        assert_eq!(dec!(0), ledger.entries[2].acb);
+        assert_eq!(dec!(0), ledger.quantity());
+        assert_eq!(dec!(0), ledger.acb());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_by_owner() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let xyz = AssetId::stock("TSE", "XYZ");
+
+        let u0 = {
+            let tran = conn.transaction()?;
+            database::migration::run_migration(&tran)?;
+            let mut u0 = User::new(
+                String::from("test_user"),
+                Sha256::digest("password").to_vec(),
+            );
+            u0.id = u0.insert(&tran)?;
+            for (name, kind, cost) in [
+                ("account_0", AccountKind::NRA, dec!(100)),
+                ("account_1", AccountKind::NRA, dec!(200)),
+                ("account_2", AccountKind::TFSA, dec!(1000)),
+            ] {
+                let mut a = Account::new(name, "alias", u0.id, kind);
+                a.id = a.insert(&tran)?;
+                Transaction::new(
+                    a.id,
+                    date!(2020, 1, 1),
+                    TxnAction::Buy {
+                        asset: (dec!(10), xyz.clone()),
+                        cash: cad(cost),
+                        fee: cad(dec!(0)),
+                    },
+                )
+                .insert(&tran)?;
+            }
+            tran.commit()?;
+            u0
+        };
+        {
+            let tran = conn.transaction()?;
+            let ledgers = AcbLedger::by_owner(
+                u0.id,
+                &AssetId::currency("CAD"),
+                &FixedExchange,
+                &tran,
+            )?;
+            let ledger = ledgers.get(&xyz).expect("no ledger");
+            assert_eq!(dec!(20), ledger.quantity());
+            assert_eq!(dec!(300), ledger.acb());
+            assert_eq!(dec!(15), ledger.acb_per_share());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use super::{Holding, Splits};
+use crate::database::account::AccountDrip;
+use crate::database::asset::{AssetDividend, AssetPrice};
+use crate::database::transaction::TxnAction;
+use crate::database::{Account, Transaction};
+use crate::error::ServerError;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+// decimal places of the units bought by a reinvestment
+const PRECISION: u32 = 4;
+
+// reinvested dividends of the assets flagged as DRIP in `account` between
+// `start` and `end` inclusively, computed from the stored dividends per share
+// and the units held before the ex-dividend date. the units are bought at the
+// latest price on that date. dividends recorded already on the same day, or
+// without a price, are left out.
+pub fn reinvested_dividends(
+    account: &Account,
+    start: NaiveDate,
+    end: NaiveDate,
+    transaction: &rusqlite::Transaction,
+) -> Result<Vec<Transaction>, ServerError> {
+    let mut transactions = Transaction::by_account(account.id, transaction)?;
+    let splits = Splits::by_transactions(
+        &transactions,
+        Some(account.owner),
+        transaction,
+    )?;
+
+    let mut dividends = Vec::new();
+    for asset in AccountDrip::by_account(account.id, transaction)? {
+        dividends.extend(
+            AssetDividend::history(&asset, Some(account.owner), transaction)?
+                .into_iter()
+                .filter(|x| x.date >= start && x.date <= end)
+                .map(|x| (asset.clone(), x)),
+        );
+    }
+    dividends.sort_by_key(|x| x.1.date);
+
+    let mut generated = Vec::new();
+    for (asset, dividend) in dividends {
+        let recorded = transactions.iter().any(|x| {
+            x.date == dividend.date
+                && match &x.action {
+                    TxnAction::Dividend { source, .. } => *source == asset,
+                    TxnAction::ReinvestedDividend { asset: bought, .. } => {
+                        bought.1 == asset
+                    }
+                    _ => false,
+                }
+        });
+        if recorded {
+            continue;
+        }
+
+        // units bought by earlier reinvestments are entitled too
+        let held = match dividend.date.pred_opt() {
+            Some(date) => {
+                Holding::replay(&transactions, &splits, date).quantity(&asset)
+            }
+            None => Decimal::ZERO,
+        };
+        if held <= Decimal::ZERO {
+            continue;
+        }
+        let price = match AssetPrice::latest(
+            &asset,
+            Some(account.owner),
+            Some(&dividend.currency),
+            dividend.date,
+            transaction,
+        )? {
+            Some(x) if x.price > Decimal::ZERO => x.price,
+            _ => continue,
+        };
+
+        let value = dividend.dividend * held;
+        let txn = Transaction::new(
+            account.id,
+            dividend.date,
+            TxnAction::ReinvestedDividend {
+                asset: ((value / price).round_dp(PRECISION), asset),
+                value: (value, dividend.currency.clone()),
+                fee: (Decimal::ZERO, dividend.currency),
+            },
+        );
+        transactions.push(txn.clone());
+        generated.push(txn);
+    }
+
+    Ok(generated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::account::AccountKind;
+    use crate::database::asset::{Asset, AssetId};
+    use crate::database::{self, User};
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    #[test]
+    fn test_reinvested_dividends() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let tran = conn.transaction()?;
+        database::migration::run_migration(&tran)?;
+        let mut u0 = User::new(
+            String::from("test_user"),
+            Sha256::digest("password").to_vec(),
+        );
+        u0.id = u0.insert(&tran)?;
+        let mut a0 =
+            Account::new("test_account", "alias", u0.id, AccountKind::NRA);
+        a0.id = a0.insert(&tran)?;
+
+        let cad = AssetId::currency("CAD");
+        let xeqt = AssetId::stock("TSE", "XEQT");
+        let mut asset = Asset::new(xeqt.clone(), "XEQT", None);
+        asset.id = asset.insert(&tran)?;
+        asset.insert_price(
+            &[
+                (date!(2021, 3, 1), dec!(25), cad.clone()),
+                (date!(2021, 6, 1), dec!(20), cad.clone()),
+            ],
+            &tran,
+        )?;
+        asset.insert_dividend(
+            &[
+                (date!(2021, 1, 1), dec!(1), cad.clone()),
+                (date!(2021, 3, 1), dec!(0.5), cad.clone()),
+                (date!(2021, 6, 1), dec!(0.51), cad.clone()),
+                (date!(2021, 9, 1), dec!(0.5), cad.clone()),
+            ],
+            &tran,
+        )?;
+        Transaction::new(
+            a0.id,
+            date!(2021, 2, 1),
+            TxnAction::Buy {
+                asset: (dec!(100), xeqt.clone()),
+                cash: (dec!(2500), cad.clone()),
+                fee: (dec!(0), cad.clone()),
+            },
+        )
+        .insert(&tran)?;
+
+        // nothing is reinvested unless flagged
+        let (start, end) = (date!(2021, 1, 1), date!(2021, 6, 30));
+        assert!(reinvested_dividends(&a0, start, end, &tran)?.is_empty());
+
+        AccountDrip::new(a0.id, xeqt.clone()).insert(&tran)?;
+        let generated = reinvested_dividends(&a0, start, end, &tran)?;
+        assert_eq!(
+            vec![
+                TxnAction::ReinvestedDividend {
+                    asset: (dec!(2), xeqt.clone()),
+                    value: (dec!(50), cad.clone()),
+                    fee: (dec!(0), cad.clone()),
+                },
+                // including the units of the first reinvestment
+                TxnAction::ReinvestedDividend {
+                    asset: (dec!(2.601), xeqt.clone()),
+                    value: (dec!(52.02), cad.clone()),
+                    fee: (dec!(0), cad.clone()),
+                },
+            ],
+            generated
+                .iter()
+                .map(|x| x.action.clone())
+                .collect::<Vec<_>>()
+        );
+
+        // the ones recorded already are left out
+        generated[0].insert(&tran)?;
+        assert_eq!(
+            vec![date!(2021, 6, 1)],
+            reinvested_dividends(&a0, start, end, &tran)?
+                .iter()
+                .map(|x| x.date)
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,398 @@
+use crate::database::asset::{Asset, AssetId, AssetPrice};
+use crate::error::ServerError;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+pub trait Exchange {
+    // how many `to` one unit of `from` is worth on `date`
+    fn rate(
+        &self,
+        from: &AssetId,
+        to: &AssetId,
+        date: NaiveDate,
+    ) -> Result<Decimal, ServerError>;
+
+    fn convert(
+        &self,
+        value: &(Decimal, AssetId),
+        to: &AssetId,
+        date: NaiveDate,
+    ) -> Result<Decimal, ServerError> {
+        if value.0.is_zero() || value.1 == *to {
+            Ok(value.0)
+        } else {
+            Ok(value.0 * self.rate(&value.1, to, date)?)
+        }
+    }
+}
+
+// the currency most exchange rates are quoted against
+pub const PIVOT: &str = "USD";
+
+// stored prices, an exchange rate is derived from them by `Exchange`.
+pub trait PriceSource {
+    // latest price of `asset` on or before `date`, quoted in `currency` if
+    // given.
+    fn latest(
+        &self,
+        asset: &AssetId,
+        currency: Option<&AssetId>,
+        date: NaiveDate,
+    ) -> Result<Option<AssetPrice>, ServerError>;
+
+    fn direct(
+        &self,
+        from: &AssetId,
+        to: &AssetId,
+        date: NaiveDate,
+    ) -> Result<Option<Decimal>, ServerError> {
+        if from == to {
+            return Ok(Some(Decimal::ONE));
+        }
+
+        if let Some(price) = self.latest(from, Some(to), date)? {
+            return Ok(Some(price.price));
+        }
+        let price = self.latest(to, Some(from), date)?;
+        Ok(price
+            .filter(|x| !x.price.is_zero())
+            .map(|x| Decimal::ONE / x.price))
+    }
+
+    // direct rate, or the one derived through the pivot currency when only
+    // rates against the pivot are stored, e.g. EUR to CAD via USD.
+    fn cross(
+        &self,
+        from: &AssetId,
+        to: &AssetId,
+        date: NaiveDate,
+    ) -> Result<Option<Decimal>, ServerError> {
+        if let Some(rate) = self.direct(from, to, date)? {
+            return Ok(Some(rate));
+        }
+
+        let pivot = AssetId::currency(PIVOT);
+        if *from == pivot || *to == pivot {
+            return Ok(None);
+        }
+        match self.direct(from, &pivot, date)? {
+            Some(x) => Ok(self.direct(&pivot, to, date)?.map(|y| x * y)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T: PriceSource> Exchange for T {
+    fn rate(
+        &self,
+        from: &AssetId,
+        to: &AssetId,
+        date: NaiveDate,
+    ) -> Result<Decimal, ServerError> {
+        if let Some(rate) = self.cross(from, to, date)? {
+            return Ok(rate);
+        }
+
+        // e.g. a stock quoted in USD while CAD is requested
+        if let Some(price) = self.latest(from, None, date)? {
+            if let Some(rate) = self.cross(&price.currency, to, date)? {
+                return Ok(price.price * rate);
+            }
+        }
+
+        Err(ServerError::Internal(format!(
+            "no exchange rate from {} to {} on {}",
+            String::from(from.clone()),
+            String::from(to.clone()),
+            date
+        )))
+    }
+}
+
+// exchange rates and prices read from the `asset_price` table, using the
+// latest price on or before the requested date. private assets of `owner`
+// are visible as well.
+pub struct SqlExchange<'a> {
+    owner: Option<Uuid>,
+    transaction: &'a rusqlite::Transaction<'a>,
+}
+
+impl<'a> SqlExchange<'a> {
+    pub fn new(
+        owner: Option<Uuid>,
+        transaction: &'a rusqlite::Transaction<'a>,
+    ) -> Self {
+        Self { owner, transaction }
+    }
+
+    // stores how many `to` one unit of `from` is worth on each date. the
+    // `from` currency is added as an asset of `owner` when missing.
+    pub fn insert(
+        &self,
+        from: &AssetId,
+        to: &AssetId,
+        rates: &[(NaiveDate, Decimal)],
+    ) -> Result<(), ServerError> {
+        let id = match Asset::by_asset(
+            from.clone(),
+            self.owner,
+            self.transaction,
+        )? {
+            Some(asset) => asset.id,
+            None => {
+                Asset::new(from.clone(), String::from(from.clone()), self.owner)
+                    .insert(self.transaction)?
+            }
+        };
+        let data: Vec<_> = rates
+            .iter()
+            .map(|(date, rate)| (*date, *rate, to.clone()))
+            .collect();
+        AssetPrice::insert(id, &data, self.transaction)
+    }
+}
+
+impl PriceSource for SqlExchange<'_> {
+    fn latest(
+        &self,
+        asset: &AssetId,
+        currency: Option<&AssetId>,
+        date: NaiveDate,
+    ) -> Result<Option<AssetPrice>, ServerError> {
+        AssetPrice::latest(asset, self.owner, currency, date, self.transaction)
+    }
+}
+
+// the full price history of a set of assets held in memory, for reports
+// asking for many dates at once.
+pub struct CachedExchange {
+    // by asset then by quote currency, sorted by date
+    prices: HashMap<AssetId, BTreeMap<AssetId, Vec<AssetPrice>>>,
+}
+
+impl CachedExchange {
+    pub fn new(prices: HashMap<AssetId, Vec<AssetPrice>>) -> Self {
+        let prices = prices
+            .into_iter()
+            .map(|(asset, history)| {
+                let mut quotes = BTreeMap::<_, Vec<_>>::new();
+                for price in history {
+                    quotes
+                        .entry(price.currency.clone())
+                        .or_default()
+                        .push(price);
+                }
+                quotes.values_mut().for_each(|x| x.sort_by_key(|x| x.date));
+                (asset, quotes)
+            })
+            .collect();
+        Self { prices }
+    }
+
+    // latest price of `prices` on or before `date`
+    fn before(prices: &[AssetPrice], date: NaiveDate) -> Option<&AssetPrice> {
+        let end = prices.partition_point(|x| x.date <= date);
+        end.checked_sub(1).map(|i| &prices[i])
+    }
+
+    // loads `assets` along with the pivot and every currency they are quoted
+    // in.
+    pub fn load<'a>(
+        assets: impl IntoIterator<Item = &'a AssetId>,
+        owner: Option<Uuid>,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<Self, ServerError> {
+        let mut pending: Vec<_> = assets.into_iter().cloned().collect();
+        pending.push(AssetId::currency(PIVOT));
+        let mut prices = HashMap::new();
+        while let Some(asset) = pending.pop() {
+            if prices.contains_key(&asset) {
+                continue;
+            }
+            let history = AssetPrice::history(&asset, owner, transaction)?;
+            pending.extend(history.iter().map(|x| x.currency.clone()));
+            prices.insert(asset, history);
+        }
+        Ok(Self::new(prices))
+    }
+}
+
+impl PriceSource for CachedExchange {
+    fn latest(
+        &self,
+        asset: &AssetId,
+        currency: Option<&AssetId>,
+        date: NaiveDate,
+    ) -> Result<Option<AssetPrice>, ServerError> {
+        let quotes = match self.prices.get(asset) {
+            None => return Ok(None),
+            Some(x) => x,
+        };
+        let price = match currency {
+            Some(currency) => {
+                quotes.get(currency).and_then(|x| Self::before(x, date))
+            }
+            // the first currency among the latest ones
+            None => quotes
+                .values()
+                .filter_map(|x| Self::before(x, date))
+                .rev()
+                .max_by_key(|x| x.date),
+        };
+        Ok(price.cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, User};
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    fn price(
+        date: NaiveDate,
+        price: Decimal,
+        currency: &AssetId,
+    ) -> AssetPrice {
+        AssetPrice {
+            asset: Uuid::nil(),
+            date,
+            price,
+            currency: currency.clone(),
+        }
+    }
+
+    #[test]
+    fn test_cached_exchange() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("NYSE", "XYZ");
+        let usd = AssetId::currency("USD");
+        let cad = AssetId::currency("CAD");
+        let eur = AssetId::currency("EUR");
+        let exchange = CachedExchange::new(HashMap::from([
+            (
+                xyz.clone(),
+                vec![
+                    price(date!(2021, 1, 5), dec!(12), &usd),
+                    price(date!(2021, 2, 1), dec!(9), &eur),
+                    price(date!(2021, 1, 1), dec!(10), &usd),
+                ],
+            ),
+            (cad.clone(), vec![price(date!(2021, 1, 1), dec!(0.8), &usd)]),
+        ]));
+
+        // carried forward from the last known price
+        assert_eq!(dec!(10), exchange.rate(&xyz, &usd, date!(2021, 1, 4))?);
+        assert_eq!(dec!(12), exchange.rate(&xyz, &usd, date!(2021, 3, 1))?);
+        assert!(exchange.rate(&xyz, &usd, date!(2020, 12, 31)).is_err());
+
+        // the latest price in any currency, or in the one requested
+        let latest = |currency| {
+            exchange
+                .latest(&xyz, currency, date!(2021, 3, 1))
+                .map(|x| x.map(|x| (x.price, x.currency)))
+        };
+        assert_eq!(Some((dec!(9), eur.clone())), latest(None)?);
+        assert_eq!(Some((dec!(12), usd.clone())), latest(Some(&usd))?);
+
+        // inverse and via the quote currency
+        assert_eq!(dec!(1.25), exchange.rate(&usd, &cad, date!(2021, 1, 1))?);
+        assert_eq!(dec!(15), exchange.rate(&xyz, &cad, date!(2021, 1, 5))?);
+        assert_eq!(
+            dec!(30),
+            exchange.convert(
+                &(dec!(2), xyz.clone()),
+                &cad,
+                date!(2021, 1, 5)
+            )?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pivot() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("XETR", "XYZ");
+        let (eur, usd, cad) = (
+            AssetId::currency("EUR"),
+            AssetId::currency("USD"),
+            AssetId::currency("CAD"),
+        );
+        let exchange = CachedExchange::new(HashMap::from([
+            (xyz.clone(), vec![price(date!(2021, 1, 1), dec!(10), &eur)]),
+            (eur.clone(), vec![price(date!(2021, 1, 1), dec!(1.2), &usd)]),
+            (
+                usd.clone(),
+                vec![price(date!(2021, 1, 1), dec!(1.25), &cad)],
+            ),
+        ]));
+
+        assert_eq!(dec!(1.5), exchange.rate(&eur, &cad, date!(2021, 1, 1))?);
+        assert_eq!(
+            dec!(0.666667),
+            exchange.rate(&cad, &eur, date!(2021, 1, 1))?.round_dp(6)
+        );
+        assert_eq!(dec!(15), exchange.rate(&xyz, &cad, date!(2021, 1, 1))?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sql_exchange() -> Result<(), ServerError> {
+        let mut conn = rusqlite::Connection::open_in_memory()?;
+        let (usd, cad) = (AssetId::currency("USD"), AssetId::currency("CAD"));
+        let owner = {
+            let tran = conn.transaction()?;
+            database::migration::run_migration(&tran)?;
+            let mut u0 = User::new(
+                String::from("test_user"),
+                Sha256::digest("password").to_vec(),
+            );
+            u0.id = u0.insert(&tran)?;
+            let exchange = SqlExchange::new(Some(u0.id), &tran);
+            exchange.insert(
+                &usd,
+                &cad,
+                &[
+                    (date!(2021, 1, 1), dec!(1.25)),
+                    (date!(2021, 1, 5), dec!(1.3)),
+                ],
+            )?;
+            // stored into the same asset
+            exchange.insert(&usd, &cad, &[(date!(2021, 1, 3), dec!(1.28))])?;
+            tran.commit()?;
+            u0.id
+        };
+        {
+            let tran = conn.transaction()?;
+            let exchange = SqlExchange::new(Some(owner), &tran);
+            assert_eq!(
+                dec!(1.28),
+                exchange.rate(&usd, &cad, date!(2021, 1, 4))?
+            );
+            assert_eq!(
+                dec!(1.3),
+                exchange.rate(&usd, &cad, date!(2022, 1, 1))?
+            );
+            assert_eq!(
+                dec!(0.8),
+                exchange.rate(&cad, &usd, date!(2021, 1, 2))?
+            );
+            assert!(exchange.rate(&usd, &cad, date!(2020, 1, 1)).is_err());
+
+            // rates of a user are not visible to the others
+            let exchange = SqlExchange::new(None, &tran);
+            assert!(exchange.rate(&usd, &cad, date!(2021, 1, 4)).is_err());
+        }
+
+        Ok(())
+    }
+}
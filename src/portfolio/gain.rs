@@ -0,0 +1,403 @@
+use super::{AcbAction, AcbLedger, Exchange};
+use crate::database::asset::AssetId;
+use crate::error::ServerError;
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Disposition {
+    pub transaction: Uuid,
+    pub account: Uuid,
+    pub date: NaiveDate,
+    pub asset: AssetId,
+    pub quantity: Decimal,
+    pub proceeds: Decimal,
+    pub acb: Decimal,
+    pub outlays: Decimal,
+    pub gain: Decimal,
+    pub superficial_loss: Decimal,
+    pub denied_loss: Decimal,
+    // the ACB counts shares deposited in kind without a cost
+    pub unknown_cost: bool,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct TaxYear {
+    pub year: i32,
+    pub dispositions: Vec<Disposition>,
+    pub proceeds: Decimal,
+    pub acb: Decimal,
+    pub outlays: Decimal,
+    pub gain: Decimal,
+}
+
+impl TaxYear {
+    fn new(year: i32) -> Self {
+        Self {
+            year,
+            dispositions: Vec::new(),
+            proceeds: Decimal::ZERO,
+            acb: Decimal::ZERO,
+            outlays: Decimal::ZERO,
+            gain: Decimal::ZERO,
+        }
+    }
+
+    fn push(&mut self, disposition: Disposition) {
+        self.proceeds += disposition.proceeds;
+        self.acb += disposition.acb;
+        self.outlays += disposition.outlays;
+        self.gain += disposition.gain;
+        self.dispositions.push(disposition);
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct UnrealizedGain {
+    pub asset: AssetId,
+    pub quantity: Decimal,
+    pub acb: Decimal,
+    pub unknown_cost: bool,
+    // none if the asset has no stored price
+    pub market_value: Option<Decimal>,
+    pub gain: Option<Decimal>,
+}
+
+// capital gains of non-registered accounts, realized gains are grouped by
+// tax year and unrealized gains are valued on `date`.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct GainReport {
+    pub currency: AssetId,
+    pub date: NaiveDate,
+    pub years: Vec<TaxYear>,
+    pub unrealized: Vec<UnrealizedGain>,
+}
+
+impl GainReport {
+    pub fn build(
+        ledgers: &BTreeMap<AssetId, AcbLedger>,
+        currency: &AssetId,
+        date: NaiveDate,
+        exchange: &impl Exchange,
+    ) -> Self {
+        let mut dispositions: Vec<_> = ledgers
+            .values()
+            .flat_map(|ledger| {
+                ledger
+                    .entries
+                    .iter()
+                    .filter(|x| match x.action {
+                        AcbAction::Sell | AcbAction::TransferOut => true,
+                        // deemed gain of a return of capital beyond the ACB
+                        AcbAction::ReturnOfCapital => !x.gain.is_zero(),
+                        _ => false,
+                    })
+                    .map(|x| Disposition {
+                        transaction: x.transaction,
+                        account: x.account,
+                        date: x.date,
+                        asset: ledger.asset.clone(),
+                        quantity: -x.quantity,
+                        proceeds: x.proceeds,
+                        // the deemed gain of a return of capital has no cost,
+                        // the ACB it reduces is not disposed of
+                        acb: match x.action {
+                            AcbAction::ReturnOfCapital => Decimal::ZERO,
+                            _ => -x.acb,
+                        },
+                        outlays: x.outlays,
+                        gain: x.gain,
+                        superficial_loss: x.superficial_loss,
+                        denied_loss: x.denied_loss,
+                        unknown_cost: x.unknown_cost,
+                    })
+            })
+            .collect();
+        dispositions.sort_by_key(|x| x.date);
+
+        let mut years = BTreeMap::<i32, TaxYear>::new();
+        dispositions.into_iter().for_each(|x| {
+            let year = x.date.year();
+            years
+                .entry(year)
+                .or_insert_with(|| TaxYear::new(year))
+                .push(x)
+        });
+
+        let unrealized = ledgers
+            .values()
+            .filter(|ledger| ledger.quantity() > Decimal::ZERO)
+            .map(|ledger| {
+                let market_value = exchange
+                    .convert(
+                        &(ledger.quantity(), ledger.asset.clone()),
+                        currency,
+                        date,
+                    )
+                    .ok();
+                UnrealizedGain {
+                    asset: ledger.asset.clone(),
+                    quantity: ledger.quantity(),
+                    acb: ledger.acb(),
+                    unknown_cost: ledger.unknown_cost(),
+                    market_value,
+                    gain: market_value.map(|x| x - ledger.acb()),
+                }
+            })
+            .collect();
+
+        Self {
+            currency: currency.clone(),
+            date,
+            years: years.into_values().collect(),
+            unrealized,
+        }
+    }
+
+    pub fn by_owner(
+        owner: Uuid,
+        currency: &AssetId,
+        date: NaiveDate,
+        exchange: &impl Exchange,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<Self, ServerError> {
+        let ledgers =
+            AcbLedger::by_owner(owner, currency, exchange, transaction)?;
+        Ok(Self::build(&ledgers, currency, date, exchange))
+    }
+
+    // one row per disposition, in the layout of schedule 3
+    pub fn to_csv(&self) -> Result<String, ServerError> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record([
+            "tax_year",
+            "date",
+            "account",
+            "asset",
+            "quantity",
+            "proceeds",
+            "acb",
+            "outlays",
+            "gain",
+            "superficial_loss",
+            "denied_loss",
+            "unknown_cost",
+        ])?;
+        for year in &self.years {
+            for x in &year.dispositions {
+                writer.write_record([
+                    year.year.to_string(),
+                    x.date.to_string(),
+                    x.account.to_string(),
+                    String::from(x.asset.clone()),
+                    x.quantity.to_string(),
+                    x.proceeds.to_string(),
+                    x.acb.to_string(),
+                    x.outlays.to_string(),
+                    x.gain.to_string(),
+                    x.superficial_loss.to_string(),
+                    x.denied_loss.to_string(),
+                    x.unknown_cost.to_string(),
+                ])?;
+            }
+        }
+
+        let data = writer
+            .into_inner()
+            .map_err(|e| ServerError::Internal(e.to_string()))?;
+        String::from_utf8(data)
+            .map_err(|e| ServerError::Internal(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::account::AccountKind;
+    use crate::database::transaction::TxnAction;
+    use crate::database::{self, Account, Transaction, User};
+    use crate::portfolio::Splits;
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+    use std::collections::HashSet;
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    struct FixedExchange;
+
+    impl Exchange for FixedExchange {
+        fn rate(
+            &self,
+            from: &AssetId,
+            _: &AssetId,
+            _: NaiveDate,
+        ) -> Result<Decimal, ServerError> {
+            match from {
+                AssetId::STOCK { ticker, .. } if ticker == "XYZ" => {
+                    Ok(dec!(20))
+                }
+                _ => Err(ServerError::Internal(String::from("no rate"))),
+            }
+        }
+    }
+
+    fn cad(value: Decimal) -> (Decimal, AssetId) {
+        (value, AssetId::currency("CAD"))
+    }
+
+    #[test]
+    fn test_return_of_capital() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let account = Uuid::new_v4();
+        let transactions = vec![
+            Transaction::new(
+                account,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), xyz.clone()),
+                    cash: cad(dec!(830)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2020, 6, 1),
+                TxnAction::ReturnOfCapital {
+                    source: xyz.clone(),
+                    value: cad(dec!(900)),
+                },
+            ),
+        ];
+        let ledgers = AcbLedger::build(
+            &transactions,
+            &HashSet::new(),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &FixedExchange,
+        )?;
+        let report = GainReport::build(
+            &ledgers,
+            &AssetId::currency("CAD"),
+            date!(2021, 1, 1),
+            &FixedExchange,
+        );
+
+        // only the part beyond the ACB is a gain, and it has no cost
+        let row = &report.years[0].dispositions[0];
+        assert_eq!(dec!(0), row.quantity);
+        assert_eq!(dec!(70), row.proceeds);
+        assert_eq!(dec!(0), row.acb);
+        assert_eq!(dec!(70), row.gain);
+        assert_eq!(row.gain, row.proceeds - row.acb - row.outlays);
+        assert_eq!(dec!(0), report.years[0].acb);
+        assert_eq!(dec!(0), report.unrealized[0].acb);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_by_owner() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let abc = AssetId::stock("TSE", "ABC");
+
+        let u0 = {
+            let tran = conn.transaction()?;
+            database::migration::run_migration(&tran)?;
+            let mut u0 = User::new(
+                String::from("test_user"),
+                Sha256::digest("password").to_vec(),
+            );
+            u0.id = u0.insert(&tran)?;
+            for kind in [AccountKind::NRA, AccountKind::TFSA] {
+                let mut a = Account::new("account", "alias", u0.id, kind);
+                a.id = a.insert(&tran)?;
+                for (date, action) in [
+                    (
+                        date!(2020, 1, 1),
+                        TxnAction::Buy {
+                            asset: (dec!(100), xyz.clone()),
+                            cash: cad(dec!(1000)),
+                            fee: cad(dec!(0)),
+                        },
+                    ),
+                    (
+                        date!(2020, 6, 1),
+                        TxnAction::Sell {
+                            asset: (dec!(50), xyz.clone()),
+                            cash: cad(dec!(750)),
+                            fee: cad(dec!(10)),
+                        },
+                    ),
+                    (
+                        date!(2021, 1, 1),
+                        TxnAction::Buy {
+                            asset: (dec!(10), abc.clone()),
+                            cash: cad(dec!(100)),
+                            fee: cad(dec!(0)),
+                        },
+                    ),
+                    (
+                        date!(2021, 3, 1),
+                        TxnAction::Sell {
+                            asset: (dec!(5), abc.clone()),
+                            cash: cad(dec!(40)),
+                            fee: cad(dec!(0)),
+                        },
+                    ),
+                ] {
+                    Transaction::new(a.id, date, action).insert(&tran)?;
+                }
+            }
+            tran.commit()?;
+            u0
+        };
+        {
+            let tran = conn.transaction()?;
+            let report = GainReport::by_owner(
+                u0.id,
+                &AssetId::currency("CAD"),
+                date!(2022, 1, 1),
+                &FixedExchange,
+                &tran,
+            )?;
+
+            // sells in the TFSA account are not reported
+            assert_eq!(2, report.years.len());
+            assert_eq!(2020, report.years[0].year);
+            assert_eq!(1, report.years[0].dispositions.len());
+            assert_eq!(dec!(750), report.years[0].proceeds);
+            assert_eq!(dec!(500), report.years[0].acb);
+            assert_eq!(dec!(10), report.years[0].outlays);
+            assert_eq!(dec!(240), report.years[0].gain);
+            assert_eq!(2021, report.years[1].year);
+            assert_eq!(dec!(-10), report.years[1].gain);
+
+            assert_eq!(2, report.unrealized.len());
+            let abc_gain = &report.unrealized[0];
+            assert_eq!(abc, abc_gain.asset);
+            assert_eq!(None, abc_gain.market_value);
+            let xyz_gain = &report.unrealized[1];
+            assert_eq!(dec!(50), xyz_gain.quantity);
+            assert_eq!(Some(dec!(1000)), xyz_gain.market_value);
+            assert_eq!(Some(dec!(500)), xyz_gain.gain);
+
+            let csv = report.to_csv()?;
+            let lines: Vec<_> = csv.lines().collect();
+            assert_eq!(3, lines.len());
+            assert!(lines[0].starts_with("tax_year,date,"));
+            assert!(lines[1].starts_with("2020,2020-06-01,"));
+            assert!(lines[1].ends_with(",XTSE:XYZ,50,750,500,10,240,0,0,false"));
+        }
+
+        Ok(())
+    }
+}
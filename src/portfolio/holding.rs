@@ -0,0 +1,566 @@
+use super::split::Event;
+use super::{Exchange, Splits};
+use crate::database::asset::AssetId;
+use crate::database::transaction::TxnAction;
+use crate::database::{Account, Transaction};
+use crate::error::ServerError;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+// positions of a single account, currencies are tracked as cash and
+// everything else (stock, crypto, unknown) as assets. recorded splits,
+// mergers and journals apply to the whole position, so transactions of
+// several accounts must go through `Holdings` instead.
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct Holding {
+    pub assets: BTreeMap<AssetId, Decimal>,
+    pub cash: BTreeMap<AssetId, Decimal>,
+}
+
+impl Holding {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn quantity(&self, asset: &AssetId) -> Decimal {
+        self.bucket(asset).get(asset).cloned().unwrap_or_default()
+    }
+
+    fn bucket(&self, asset: &AssetId) -> &BTreeMap<AssetId, Decimal> {
+        match asset {
+            AssetId::CURRENCY(_) => &self.cash,
+            _ => &self.assets,
+        }
+    }
+
+    fn bucket_mut(
+        &mut self,
+        asset: &AssetId,
+    ) -> &mut BTreeMap<AssetId, Decimal> {
+        match asset {
+            AssetId::CURRENCY(_) => &mut self.cash,
+            _ => &mut self.assets,
+        }
+    }
+
+    fn add(&mut self, quantity: Decimal, asset: &AssetId) {
+        if quantity.is_zero() {
+            return;
+        }
+
+        let bucket = self.bucket_mut(asset);
+        let value = bucket.entry(asset.clone()).or_default();
+        *value += quantity;
+        if value.is_zero() {
+            bucket.remove(asset);
+        }
+    }
+
+    fn sub(&mut self, quantity: Decimal, asset: &AssetId) {
+        self.add(-quantity, asset)
+    }
+
+    pub fn apply(&mut self, action: &TxnAction) {
+        match action {
+            TxnAction::Deposit { value, fee } => {
+                self.add(value.0, &value.1);
+                self.sub(fee.0, &fee.1);
+            }
+            TxnAction::Withdrawal { value, fee } => {
+                self.sub(value.0, &value.1);
+                self.sub(fee.0, &fee.1);
+            }
+            TxnAction::Income { value, .. } => {
+                self.add(value.0, &value.1);
+            }
+            TxnAction::Fee { value, .. } => {
+                self.sub(value.0, &value.1);
+            }
+            TxnAction::Buy { asset, cash, fee } => {
+                self.add(asset.0, &asset.1);
+                self.sub(cash.0, &cash.1);
+                self.sub(fee.0, &fee.1);
+            }
+            TxnAction::Sell { asset, cash, fee } => {
+                self.sub(asset.0, &asset.1);
+                self.add(cash.0, &cash.1);
+                self.sub(fee.0, &fee.1);
+            }
+            TxnAction::Dividend { value, fee, .. } => {
+                self.add(value.0, &value.1);
+                self.sub(fee.0, &fee.1);
+            }
+            // the cash never reaches the account
+            TxnAction::ReinvestedDividend { asset, .. } => {
+                self.add(asset.0, &asset.1);
+            }
+            TxnAction::Journal {
+                source,
+                target,
+                fee,
+            } => {
+                // the whole position is moved to the target asset
+                let quantity = self.quantity(source);
+                self.sub(quantity, source);
+                self.add(quantity, target);
+                self.sub(fee.0, &fee.1);
+            }
+            TxnAction::TransferOut { asset, fee, .. } => {
+                self.sub(asset.0, &asset.1);
+                self.sub(fee.0, &fee.1);
+            }
+            TxnAction::TransferIn { asset, .. } => {
+                self.add(asset.0, &asset.1);
+            }
+            TxnAction::Split { asset, ratio, cash } => {
+                self.split(asset, *ratio);
+                if let Some(cash) = cash {
+                    self.sub(self.quantity(asset).fract(), asset);
+                    self.add(cash.0, &cash.1);
+                }
+            }
+            TxnAction::Merger {
+                source,
+                target,
+                ratio,
+                cash,
+                ..
+            } => {
+                let quantity = self.quantity(source);
+                self.sub(quantity, source);
+                self.add(quantity * ratio, target);
+                self.add(cash.0, &cash.1);
+            }
+            TxnAction::SpinOff { target, .. } => {
+                self.add(target.0, &target.1);
+            }
+            // only the cost base changes
+            TxnAction::ReturnOfCapital { .. }
+            | TxnAction::ReinvestedCapitalGain { .. } => (),
+        }
+    }
+
+    // multiplies the quantity of `asset` by `ratio`
+    pub fn split(&mut self, asset: &AssetId, ratio: Decimal) {
+        if let Some(quantity) = self.assets.get_mut(asset) {
+            *quantity *= ratio;
+        }
+    }
+
+    pub(super) fn apply_event(&mut self, event: &Event) {
+        match event {
+            Event::Split { asset, ratio, .. } => self.split(asset, *ratio),
+            Event::Transaction(txn) => self.apply(&txn.action),
+        }
+    }
+
+    // replay all transactions and splits happened on or before `date` in
+    // date order. the transactions must be of a single account.
+    pub fn replay<'a>(
+        transactions: impl IntoIterator<Item = &'a Transaction>,
+        splits: &'a Splits,
+        date: NaiveDate,
+    ) -> Self {
+        let transactions: Vec<_> = transactions.into_iter().collect();
+        debug_assert!(
+            transactions
+                .windows(2)
+                .all(|x| x[0].account == x[1].account),
+            "transactions of several accounts in one holding"
+        );
+
+        let mut holding = Self::new();
+        splits
+            .timeline(transactions)
+            .iter()
+            .take_while(|x| x.date() <= date)
+            .for_each(|x| holding.apply_event(x));
+        holding
+    }
+
+    // market value of all positions in `currency` on `date`
+    pub fn value(
+        &self,
+        currency: &AssetId,
+        date: NaiveDate,
+        exchange: &impl Exchange,
+    ) -> Result<Decimal, ServerError> {
+        self.assets
+            .iter()
+            .chain(self.cash.iter())
+            .map(|(asset, quantity)| {
+                exchange.convert(&(*quantity, asset.clone()), currency, date)
+            })
+            .sum()
+    }
+
+    pub fn by_account(
+        account: Uuid,
+        date: NaiveDate,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<Self, ServerError> {
+        let owner = Account::by_id(account, transaction)?.map(|x| x.owner);
+        let transactions = Transaction::by_account(account, transaction)?;
+        let splits =
+            Splits::by_transactions(&transactions, owner, transaction)?;
+        Ok(Self::replay(&transactions, &splits, date))
+    }
+}
+
+// positions of several accounts, each account is replayed on its own
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct Holdings(BTreeMap<Uuid, Holding>);
+
+impl Holdings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn apply_event(&mut self, event: &Event) {
+        match event {
+            Event::Split { asset, ratio, .. } => {
+                self.0.values_mut().for_each(|x| x.split(asset, *ratio))
+            }
+            Event::Transaction(txn) => {
+                self.0.entry(txn.account).or_default().apply(&txn.action)
+            }
+        }
+    }
+
+    // like `Holding::replay`, for transactions of any account
+    pub fn replay<'a>(
+        transactions: impl IntoIterator<Item = &'a Transaction>,
+        splits: &'a Splits,
+        date: NaiveDate,
+    ) -> Self {
+        let mut holdings = Self::new();
+        splits
+            .timeline(transactions)
+            .iter()
+            .take_while(|x| x.date() <= date)
+            .for_each(|x| holdings.apply_event(x));
+        holdings
+    }
+
+    pub fn account(&self, account: Uuid) -> Option<&Holding> {
+        self.0.get(&account)
+    }
+
+    // positions of all accounts added together
+    pub fn total(&self) -> Holding {
+        let mut total = Holding::new();
+        for holding in self.0.values() {
+            holding
+                .assets
+                .iter()
+                .chain(holding.cash.iter())
+                .for_each(|(asset, quantity)| total.add(*quantity, asset));
+        }
+        total
+    }
+
+    // market value of all positions of all accounts
+    pub fn value(
+        &self,
+        currency: &AssetId,
+        date: NaiveDate,
+        exchange: &impl Exchange,
+    ) -> Result<Decimal, ServerError> {
+        self.0
+            .values()
+            .map(|x| x.value(currency, date, exchange))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::account::AccountKind;
+    use crate::database::asset::Asset;
+    use crate::database::{self, User};
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+    use std::collections::HashMap;
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    fn cad(value: Decimal) -> (Decimal, AssetId) {
+        (value, AssetId::currency("CAD"))
+    }
+
+    fn usd(value: Decimal) -> (Decimal, AssetId) {
+        (value, AssetId::currency("USD"))
+    }
+
+    #[test]
+    fn test_apply() -> Result<(), ServerError> {
+        let dlr = AssetId::stock("TSE", "DLR");
+        let dlr_u = AssetId::stock("TSE", "DLR.U");
+        let mut holding = Holding::new();
+
+        holding.apply(&TxnAction::Deposit {
+            value: cad(dec!(1000)),
+            fee: cad(dec!(0)),
+        });
+        assert_eq!(dec!(1000), holding.quantity(&AssetId::currency("CAD")));
+
+        holding.apply(&TxnAction::Buy {
+            asset: (dec!(50), dlr.clone()),
+            cash: cad(dec!(680)),
+            fee: cad(dec!(9.99)),
+        });
+        assert_eq!(dec!(50), holding.quantity(&dlr));
+        assert_eq!(dec!(310.01), holding.quantity(&AssetId::currency("CAD")));
+
+        holding.apply(&TxnAction::Journal {
+            source: dlr.clone(),
+            target: dlr_u.clone(),
+            fee: cad(dec!(0)),
+        });
+        assert_eq!(dec!(0), holding.quantity(&dlr));
+        assert_eq!(dec!(50), holding.quantity(&dlr_u));
+        assert!(!holding.assets.contains_key(&dlr));
+
+        holding.apply(&TxnAction::Sell {
+            asset: (dec!(50), dlr_u.clone()),
+            cash: usd(dec!(500)),
+            fee: usd(dec!(4.95)),
+        });
+        assert!(holding.assets.is_empty());
+        assert_eq!(dec!(495.05), holding.quantity(&AssetId::currency("USD")));
+
+        holding.apply(&TxnAction::Dividend {
+            source: dlr_u.clone(),
+            value: usd(dec!(10)),
+            fee: usd(dec!(1.5)),
+        });
+        holding.apply(&TxnAction::Income {
+            value: cad(dec!(2)),
+            reason: String::from("Interest"),
+        });
+        holding.apply(&TxnAction::Fee {
+            value: cad(dec!(12.01)),
+            reason: String::from("Management Fee"),
+        });
+        holding.apply(&TxnAction::Withdrawal {
+            value: cad(dec!(300)),
+            fee: cad(dec!(0)),
+        });
+        assert_eq!(dec!(503.55), holding.quantity(&AssetId::currency("USD")));
+        assert!(!holding.cash.contains_key(&AssetId::currency("CAD")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_corporate_action() {
+        let abc = AssetId::stock("TSE", "ABC");
+        let def = AssetId::stock("TSE", "DEF");
+        let ghi = AssetId::stock("TSE", "GHI");
+        let mut holding = Holding::new();
+
+        holding.apply(&TxnAction::Buy {
+            asset: (dec!(100), abc.clone()),
+            cash: cad(dec!(1000)),
+            fee: cad(dec!(0)),
+        });
+        holding.apply(&TxnAction::Split {
+            asset: abc.clone(),
+            ratio: dec!(2),
+            cash: None,
+        });
+        assert_eq!(dec!(200), holding.quantity(&abc));
+
+        holding.apply(&TxnAction::Merger {
+            source: abc.clone(),
+            target: def.clone(),
+            ratio: dec!(0.5),
+            cash: cad(dec!(300)),
+            allocation: dec!(80),
+        });
+        assert!(!holding.assets.contains_key(&abc));
+        assert_eq!(dec!(100), holding.quantity(&def));
+        assert_eq!(dec!(-700), holding.quantity(&AssetId::currency("CAD")));
+
+        holding.apply(&TxnAction::SpinOff {
+            source: def.clone(),
+            target: (dec!(20), ghi.clone()),
+            allocation: dec!(25),
+        });
+        assert_eq!(dec!(100), holding.quantity(&def));
+        assert_eq!(dec!(20), holding.quantity(&ghi));
+    }
+
+    #[test]
+    fn test_split() {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let account = Uuid::new_v4();
+        let splits = Splits::new(HashMap::from([(
+            xyz.clone(),
+            vec![(date!(2020, 6, 1), dec!(0.1))],
+        )]));
+        let transactions = [
+            Transaction::new(
+                account,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(15), xyz.clone()),
+                    cash: cad(dec!(150)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            // fractional share paid in cash on the day of the reverse split
+            Transaction::new(
+                account,
+                date!(2020, 6, 1),
+                TxnAction::Sell {
+                    asset: (dec!(0.5), xyz.clone()),
+                    cash: cad(dec!(40)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+        ];
+
+        let res = Holding::replay(&transactions, &splits, date!(2020, 5, 31));
+        assert_eq!(dec!(15), res.quantity(&xyz));
+        let res = Holding::replay(&transactions, &splits, date!(2020, 6, 1));
+        assert_eq!(dec!(1), res.quantity(&xyz));
+        assert_eq!(dec!(-110), res.quantity(&AssetId::currency("CAD")));
+    }
+
+    #[test]
+    fn test_holdings() {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let abc = AssetId::stock("TSE", "ABC");
+        let (a0, a1) = (Uuid::new_v4(), Uuid::new_v4());
+        let splits = Splits::new(HashMap::from([(
+            abc.clone(),
+            vec![(date!(2020, 6, 1), dec!(3))],
+        )]));
+        let buy = |account, asset: &AssetId| {
+            Transaction::new(
+                account,
+                date!(2020, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), asset.clone()),
+                    cash: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            )
+        };
+        let split = |account| {
+            Transaction::new(
+                account,
+                date!(2020, 3, 1),
+                TxnAction::Split {
+                    asset: xyz.clone(),
+                    ratio: dec!(2),
+                    cash: None,
+                },
+            )
+        };
+        let transactions = [
+            buy(a0, &xyz),
+            buy(a1, &xyz),
+            buy(a1, &abc),
+            split(a0),
+            split(a1),
+        ];
+
+        // a recorded split only changes the account recording it, while the
+        // split history applies to every account
+        let res = Holdings::replay(&transactions, &splits, date!(2020, 6, 1));
+        assert_eq!(Some(dec!(200)), res.account(a0).map(|x| x.quantity(&xyz)));
+        assert_eq!(Some(dec!(200)), res.account(a1).map(|x| x.quantity(&xyz)));
+        assert_eq!(dec!(400), res.total().quantity(&xyz));
+        assert_eq!(dec!(300), res.total().quantity(&abc));
+        assert_eq!(
+            dec!(-3000),
+            res.total().quantity(&AssetId::currency("CAD"))
+        );
+    }
+
+    #[test]
+    fn test_by_account() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let dlr = AssetId::stock("TSE", "DLR");
+
+        let a0 = {
+            let tran = conn.transaction()?;
+            database::migration::run_migration(&tran)?;
+            let mut u0 = User::new(
+                String::from("test_user"),
+                Sha256::digest("password").to_vec(),
+            );
+            u0.id = u0.insert(&tran)?;
+            let mut a0 =
+                Account::new("test_account", "alias", u0.id, AccountKind::NRA);
+            a0.id = a0.insert(&tran)?;
+
+            // inserted out of order on purpose
+            Transaction::new(
+                a0.id,
+                date!(2020, 1, 10),
+                TxnAction::Sell {
+                    asset: (dec!(10), dlr.clone()),
+                    cash: cad(dec!(150)),
+                    fee: cad(dec!(0)),
+                },
+            )
+            .insert(&tran)?;
+            Transaction::new(
+                a0.id,
+                date!(2020, 1, 1),
+                TxnAction::Deposit {
+                    value: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            )
+            .insert(&tran)?;
+            Transaction::new(
+                a0.id,
+                date!(2020, 1, 5),
+                TxnAction::Buy {
+                    asset: (dec!(30), dlr.clone()),
+                    cash: cad(dec!(400)),
+                    fee: cad(dec!(0)),
+                },
+            )
+            .insert(&tran)?;
+
+            // 2-for-1 split between the buy and the sell
+            let mut asset = Asset::new(dlr.clone(), "DLR", None);
+            asset.id = asset.insert(&tran)?;
+            asset.insert_split(&[(date!(2020, 1, 8), dec!(2))], &tran)?;
+            tran.commit()?;
+            a0
+        };
+        {
+            let tran = conn.transaction()?;
+            let res = Holding::by_account(a0.id, date!(2019, 12, 31), &tran)?;
+            assert_eq!(Holding::new(), res);
+
+            let res = Holding::by_account(a0.id, date!(2020, 1, 5), &tran)?;
+            assert_eq!(dec!(30), res.quantity(&dlr));
+            assert_eq!(dec!(600), res.quantity(&AssetId::currency("CAD")));
+
+            let res = Holding::by_account(a0.id, date!(2020, 1, 8), &tran)?;
+            assert_eq!(dec!(60), res.quantity(&dlr));
+
+            let res = Holding::by_account(a0.id, date!(2020, 1, 31), &tran)?;
+            assert_eq!(dec!(50), res.quantity(&dlr));
+            assert_eq!(dec!(750), res.quantity(&AssetId::currency("CAD")));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,18 @@
+mod acb;
+mod drip;
+mod exchange;
+mod gain;
+mod holding;
+mod returns;
+mod series;
+mod split;
+mod superficial;
+
+pub use acb::{AcbAction, AcbEntry, AcbLedger};
+pub use drip::reinvested_dividends;
+pub use exchange::{CachedExchange, Exchange, PriceSource, SqlExchange, PIVOT};
+pub use gain::{Disposition, GainReport, TaxYear, UnrealizedGain};
+pub use holding::{Holding, Holdings};
+pub use returns::{xirr, Performance, Period};
+pub use series::{Interval, Point, Series};
+pub use split::Splits;
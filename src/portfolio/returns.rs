@@ -0,0 +1,462 @@
+use super::split::Event;
+use super::{Exchange, Holdings, Splits};
+use crate::database::asset::AssetId;
+use crate::database::transaction::TxnAction;
+use crate::database::Transaction;
+use crate::error::ServerError;
+use chrono::{Datelike, Days, Months, NaiveDate};
+use rust_decimal::{Decimal, MathematicalOps};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    #[serde(rename = "1M")]
+    OneMonth,
+    #[serde(rename = "YTD")]
+    YearToDate,
+    #[serde(rename = "1Y")]
+    OneYear,
+    #[serde(rename = "ALL")]
+    SinceInception,
+}
+
+impl Period {
+    // the name of the period in requests, also the one stored in settings
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Period::OneMonth => "1M",
+            Period::YearToDate => "YTD",
+            Period::OneYear => "1Y",
+            Period::SinceInception => "ALL",
+        }
+    }
+
+    pub fn start(&self, end: NaiveDate, inception: NaiveDate) -> NaiveDate {
+        let start = match self {
+            Period::OneMonth => end - Months::new(1),
+            Period::YearToDate => end.with_ordinal(1).unwrap(),
+            Period::OneYear => end - Months::new(12),
+            Period::SinceInception => inception,
+        };
+        start.max(inception).min(end)
+    }
+}
+
+impl FromStr for Period {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Period::OneMonth,
+            Period::YearToDate,
+            Period::OneYear,
+            Period::SinceInception,
+        ]
+        .into_iter()
+        .find(|x| x.as_str() == s)
+        .ok_or(())
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Performance {
+    pub currency: AssetId,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    // value before the first day and after the last day of the period
+    pub start_value: Decimal,
+    pub end_value: Decimal,
+    // deposits and transfers in minus withdrawals and transfers out during
+    // the period
+    pub contribution: Decimal,
+    // time-weighted return of the whole period
+    pub twr: Option<Decimal>,
+    // money-weighted return, annualized
+    pub mwr: Option<Decimal>,
+}
+
+impl Performance {
+    // returns of the portfolio formed by `transactions` between `start` and
+    // `end` inclusively. deposits, withdrawals and transfers are the only
+    // external cash flows, and they are assumed to happen at the end of the
+    // day.
+    pub fn build(
+        transactions: &[Transaction],
+        start: NaiveDate,
+        end: NaiveDate,
+        currency: &AssetId,
+        splits: &Splits,
+        exchange: &impl Exchange,
+    ) -> Result<Self, ServerError> {
+        let events = splits.timeline(transactions);
+        let mut iter =
+            events.into_iter().filter(|x| x.date() <= end).peekable();
+
+        let mut holdings = Holdings::new();
+        while let Some(event) = iter.next_if(|x| x.date() < start) {
+            holdings.apply_event(&event);
+        }
+
+        let before = start - Days::new(1);
+        let start_value = holdings.value(currency, before, exchange)?;
+        let mut flows = vec![(before, -start_value)];
+        let mut contribution = Decimal::ZERO;
+        let mut growth: Option<Decimal> = None;
+        let mut base = start_value;
+
+        while let Some(date) = iter.peek().map(|x| x.date()) {
+            let mut flow = Decimal::ZERO;
+            while let Some(event) = iter.next_if(|x| x.date() == date) {
+                if let Event::Transaction(txn) = &event {
+                    flow += Self::flow(&txn.action, currency, date, exchange)?;
+                }
+                holdings.apply_event(&event);
+            }
+            if flow.is_zero() {
+                continue;
+            }
+
+            let value = holdings.value(currency, date, exchange)?;
+            if !base.is_zero() {
+                let factor = (value - flow) / base;
+                growth = Some(growth.unwrap_or(Decimal::ONE) * factor);
+            }
+            flows.push((date, -flow));
+            contribution += flow;
+            base = value;
+        }
+
+        let end_value = holdings.value(currency, end, exchange)?;
+        if !base.is_zero() {
+            let factor = end_value / base;
+            growth = Some(growth.unwrap_or(Decimal::ONE) * factor);
+        }
+        flows.push((end, end_value));
+
+        Ok(Self {
+            currency: currency.clone(),
+            start,
+            end,
+            start_value,
+            end_value,
+            contribution,
+            twr: growth.map(|x| x - Decimal::ONE),
+            mwr: xirr(&flows),
+        })
+    }
+
+    // external cash flow into the portfolio
+    pub(super) fn flow(
+        action: &TxnAction,
+        currency: &AssetId,
+        date: NaiveDate,
+        exchange: &impl Exchange,
+    ) -> Result<Decimal, ServerError> {
+        match action {
+            TxnAction::Deposit { value, .. } => {
+                exchange.convert(value, currency, date)
+            }
+            TxnAction::Withdrawal { value, .. } => {
+                Ok(-exchange.convert(value, currency, date)?)
+            }
+            // both legs cancel out when the two accounts are included
+            TxnAction::TransferIn { value, .. } => {
+                exchange.convert(value, currency, date)
+            }
+            TxnAction::TransferOut { value, .. } => {
+                Ok(-exchange.convert(value, currency, date)?)
+            }
+            _ => Ok(Decimal::ZERO),
+        }
+    }
+}
+
+// annualized internal rate of return of irregular cash flows, where money
+// paid into the portfolio is negative. none if there is no solution or it
+// cannot be computed in decimal range.
+pub fn xirr(flows: &[(NaiveDate, Decimal)]) -> Option<Decimal> {
+    let first = flows.iter().map(|x| x.0).min()?;
+    let flows: Vec<(Decimal, Decimal)> = flows
+        .iter()
+        .filter(|x| !x.1.is_zero())
+        .map(|(date, amount)| {
+            let days = Decimal::from((*date - first).num_days());
+            (days / Decimal::from(365), *amount)
+        })
+        .collect();
+    if !flows.iter().any(|x| x.1.is_sign_positive())
+        || !flows.iter().any(|x| x.1.is_sign_negative())
+    {
+        return None;
+    }
+
+    // none on overflow
+    let npv = |rate: Decimal| -> Option<Decimal> {
+        flows.iter().try_fold(Decimal::ZERO, |sum, (t, x)| {
+            let factor = (Decimal::ONE + rate).checked_powd(*t)?;
+            sum.checked_add(x.checked_div(factor)?)
+        })
+    };
+    let derivative = |rate: Decimal| -> Option<Decimal> {
+        flows.iter().try_fold(Decimal::ZERO, |sum, (t, x)| {
+            let factor =
+                (Decimal::ONE + rate).checked_powd(t + Decimal::ONE)?;
+            sum.checked_sub(t.checked_mul(*x)?.checked_div(factor)?)
+        })
+    };
+
+    // newton's method converges quickly for most portfolios
+    let tolerance = Decimal::new(1, 12);
+    let mut rate = Decimal::new(1, 1);
+    for _ in 0..100 {
+        let next = match (npv(rate), derivative(rate)) {
+            (Some(value), Some(slope)) if !slope.is_zero() => {
+                match value.checked_div(slope) {
+                    Some(step) => rate - step,
+                    None => break,
+                }
+            }
+            _ => break,
+        };
+        if next <= -Decimal::ONE {
+            break;
+        }
+        if (next - rate).abs() < tolerance {
+            return Some(next.round_dp(8));
+        }
+        rate = next;
+    }
+
+    // fall back to bisection
+    let (mut low, mut high) = (Decimal::new(-99, 2), Decimal::ONE);
+    let sign = npv(low)?.is_sign_negative();
+    while npv(high)?.is_sign_negative() == sign {
+        high *= Decimal::TWO;
+        if high > Decimal::from(1_000_000_000) {
+            return None;
+        }
+    }
+    while high - low > tolerance {
+        let mid = (low + high) / Decimal::TWO;
+        if npv(mid)?.is_sign_negative() == sign {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some(((low + high) / Decimal::TWO).round_dp(8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use std::collections::BTreeMap;
+    use uuid::Uuid;
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    // price of XYZ in CAD, changing over time
+    struct HistoryExchange(BTreeMap<NaiveDate, Decimal>);
+
+    impl Exchange for HistoryExchange {
+        fn rate(
+            &self,
+            _: &AssetId,
+            _: &AssetId,
+            date: NaiveDate,
+        ) -> Result<Decimal, ServerError> {
+            self.0
+                .range(..=date)
+                .next_back()
+                .map(|x| *x.1)
+                .ok_or(ServerError::Internal(String::from("no price")))
+        }
+    }
+
+    fn cad(value: Decimal) -> (Decimal, AssetId) {
+        (value, AssetId::currency("CAD"))
+    }
+
+    #[test]
+    fn test_period() {
+        let inception = date!(2020, 5, 1);
+        let end = date!(2024, 3, 31);
+        assert_eq!(date!(2024, 2, 29), Period::OneMonth.start(end, inception));
+        assert_eq!(date!(2024, 1, 1), Period::YearToDate.start(end, inception));
+        assert_eq!(date!(2023, 3, 31), Period::OneYear.start(end, inception));
+        assert_eq!(inception, Period::SinceInception.start(end, inception));
+        assert_eq!(
+            inception,
+            Period::OneYear.start(date!(2020, 6, 1), inception)
+        );
+
+        // names match the ones of requests
+        let period: Period = serde_json::from_str("\"YTD\"").unwrap();
+        assert_eq!(Ok(period), Period::from_str(period.as_str()));
+        assert_eq!(Err(()), Period::from_str("2Y"));
+    }
+
+    #[test]
+    fn test_xirr() {
+        let rate = xirr(&[
+            (date!(2021, 1, 1), dec!(-1000)),
+            (date!(2022, 1, 1), dec!(1100)),
+        ])
+        .unwrap();
+        assert_eq!(dec!(0.1), rate.round_dp(6));
+
+        let rate = xirr(&[
+            (date!(2021, 1, 1), dec!(-1000)),
+            (date!(2021, 7, 2), dec!(-1000)),
+            (date!(2022, 1, 1), dec!(1900)),
+        ])
+        .unwrap();
+        assert!(rate < dec!(-0.06) && rate > dec!(-0.07));
+
+        let rate = xirr(&[
+            (date!(2021, 1, 1), dec!(-1000)),
+            (date!(2022, 1, 1), dec!(100)),
+        ])
+        .unwrap();
+        assert_eq!(dec!(-0.9), rate.round_dp(6));
+
+        assert_eq!(None, xirr(&[(date!(2021, 1, 1), dec!(-1000))]));
+    }
+
+    #[test]
+    fn test_build() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let account = Uuid::new_v4();
+        let exchange = HistoryExchange(BTreeMap::from([
+            (date!(2021, 1, 1), dec!(10)),
+            (date!(2021, 7, 1), dec!(12)),
+            (date!(2022, 1, 1), dec!(11)),
+        ]));
+        let transactions = vec![
+            Transaction::new(
+                account,
+                date!(2021, 1, 1),
+                TxnAction::Deposit {
+                    value: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2021, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), xyz.clone()),
+                    cash: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2021, 7, 1),
+                TxnAction::Deposit {
+                    value: cad(dec!(1200)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2021, 7, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), xyz.clone()),
+                    cash: cad(dec!(1200)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+        ];
+
+        let res = Performance::build(
+            &transactions,
+            date!(2021, 1, 1),
+            date!(2022, 1, 1),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &exchange,
+        )?;
+        assert_eq!(dec!(0), res.start_value);
+        assert_eq!(dec!(2200), res.end_value);
+        assert_eq!(dec!(2200), res.contribution);
+        assert_eq!(Some(dec!(0.1)), res.twr.map(|x| x.round_dp(6)));
+        assert_eq!(Some(dec!(0)), res.mwr.map(|x| x.round_dp(6)));
+
+        // the second half only
+        let res = Performance::build(
+            &transactions,
+            date!(2021, 7, 1),
+            date!(2022, 1, 1),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &exchange,
+        )?;
+        assert_eq!(dec!(1000), res.start_value);
+        assert_eq!(dec!(1200), res.contribution);
+        assert_eq!(Some(dec!(0.1)), res.twr.map(|x| x.round_dp(6)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_accounts() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let exchange = HistoryExchange(BTreeMap::from([
+            (date!(2021, 1, 1), dec!(10)),
+            (date!(2021, 3, 1), dec!(6)),
+        ]));
+        let mut transactions = Vec::new();
+        for account in [Uuid::new_v4(), Uuid::new_v4()] {
+            transactions.extend([
+                Transaction::new(
+                    account,
+                    date!(2021, 1, 1),
+                    TxnAction::Deposit {
+                        value: cad(dec!(1000)),
+                        fee: cad(dec!(0)),
+                    },
+                ),
+                Transaction::new(
+                    account,
+                    date!(2021, 1, 1),
+                    TxnAction::Buy {
+                        asset: (dec!(100), xyz.clone()),
+                        cash: cad(dec!(1000)),
+                        fee: cad(dec!(0)),
+                    },
+                ),
+                Transaction::new(
+                    account,
+                    date!(2021, 3, 1),
+                    TxnAction::Split {
+                        asset: xyz.clone(),
+                        ratio: dec!(2),
+                        cash: None,
+                    },
+                ),
+            ]);
+        }
+
+        // each account splits its own shares only, 400 shares at 6
+        let res = Performance::build(
+            &transactions,
+            date!(2021, 1, 1),
+            date!(2022, 1, 1),
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &exchange,
+        )?;
+        assert_eq!(dec!(2400), res.end_value);
+        assert_eq!(Some(dec!(0.2)), res.twr.map(|x| x.round_dp(6)));
+        assert_eq!(Some(dec!(0.2)), res.mwr.map(|x| x.round_dp(6)));
+
+        Ok(())
+    }
+}
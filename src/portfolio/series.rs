@@ -0,0 +1,286 @@
+use super::split::Event;
+use super::{Exchange, Holdings, Performance, Splits};
+use crate::database::asset::AssetId;
+use crate::database::Transaction;
+use crate::error::ServerError;
+use chrono::{Days, Months, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default,
+)]
+pub enum Interval {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Interval {
+    // the `n`th sampled date after `start`
+    fn nth(&self, start: NaiveDate, n: u32) -> Option<NaiveDate> {
+        match self {
+            Interval::Daily => start.checked_add_days(Days::new(n.into())),
+            Interval::Weekly => {
+                start.checked_add_days(Days::new(u64::from(n) * 7))
+            }
+            Interval::Monthly => start.checked_add_months(Months::new(n)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Point {
+    pub date: NaiveDate,
+    // market value of all positions, cash included
+    pub value: Decimal,
+    pub cash: Decimal,
+    // deposits minus withdrawals up to the date
+    pub contribution: Decimal,
+    pub gain: Decimal,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Series {
+    pub currency: AssetId,
+    pub interval: Interval,
+    pub points: Vec<Point>,
+}
+
+impl Series {
+    // value of the portfolio formed by `transactions` at the end of every
+    // sampled day between `start` and `end`, the last day is always included.
+    // prices are carried forward from the last known one.
+    pub fn build(
+        transactions: &[Transaction],
+        start: NaiveDate,
+        end: NaiveDate,
+        interval: Interval,
+        currency: &AssetId,
+        splits: &Splits,
+        exchange: &impl Exchange,
+    ) -> Result<Self, ServerError> {
+        let mut dates: Vec<_> = (0..)
+            .map_while(|n| interval.nth(start, n))
+            .take_while(|date| *date <= end)
+            .collect();
+        if start <= end && dates.last() != Some(&end) {
+            dates.push(end);
+        }
+
+        let mut holdings = Holdings::new();
+        let mut contribution = Decimal::ZERO;
+        let events = splits.timeline(transactions);
+        let mut iter = events.into_iter().peekable();
+        let mut points = Vec::with_capacity(dates.len());
+        for date in dates {
+            while let Some(event) = iter.next_if(|x| x.date() <= date) {
+                if let Event::Transaction(txn) = &event {
+                    contribution += Performance::flow(
+                        &txn.action,
+                        currency,
+                        txn.date,
+                        exchange,
+                    )?;
+                }
+                holdings.apply_event(&event);
+            }
+
+            let holding = holdings.total();
+            let assets =
+                Self::value(&holding.assets, currency, date, exchange)?;
+            let cash = Self::value(&holding.cash, currency, date, exchange)?;
+            points.push(Point {
+                date,
+                value: assets + cash,
+                cash,
+                contribution,
+                gain: assets + cash - contribution,
+            });
+        }
+
+        Ok(Self {
+            currency: currency.clone(),
+            interval,
+            points,
+        })
+    }
+
+    fn value(
+        positions: &BTreeMap<AssetId, Decimal>,
+        currency: &AssetId,
+        date: NaiveDate,
+        exchange: &impl Exchange,
+    ) -> Result<Decimal, ServerError> {
+        positions
+            .iter()
+            .map(|(asset, quantity)| {
+                exchange.convert(&(*quantity, asset.clone()), currency, date)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::transaction::TxnAction;
+    use rust_decimal_macros::dec;
+    use uuid::Uuid;
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    // price of XYZ in CAD, changing over time
+    struct HistoryExchange(BTreeMap<NaiveDate, Decimal>);
+
+    impl Exchange for HistoryExchange {
+        fn rate(
+            &self,
+            _: &AssetId,
+            _: &AssetId,
+            date: NaiveDate,
+        ) -> Result<Decimal, ServerError> {
+            self.0
+                .range(..=date)
+                .next_back()
+                .map(|x| *x.1)
+                .ok_or(ServerError::Internal(String::from("no price")))
+        }
+    }
+
+    fn cad(value: Decimal) -> (Decimal, AssetId) {
+        (value, AssetId::currency("CAD"))
+    }
+
+    #[test]
+    fn test_interval() {
+        let start = date!(2024, 1, 31);
+        assert_eq!(Some(date!(2024, 2, 1)), Interval::Daily.nth(start, 1));
+        assert_eq!(Some(date!(2024, 2, 14)), Interval::Weekly.nth(start, 2));
+        assert_eq!(Some(date!(2024, 2, 29)), Interval::Monthly.nth(start, 1));
+        assert_eq!(Some(date!(2024, 3, 31)), Interval::Monthly.nth(start, 2));
+    }
+
+    #[test]
+    fn test_build() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let account = Uuid::new_v4();
+        let exchange = HistoryExchange(BTreeMap::from([
+            (date!(2021, 1, 1), dec!(10)),
+            (date!(2021, 1, 10), dec!(12)),
+        ]));
+        let transactions = vec![
+            Transaction::new(
+                account,
+                date!(2021, 1, 1),
+                TxnAction::Deposit {
+                    value: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2021, 1, 2),
+                TxnAction::Buy {
+                    asset: (dec!(50), xyz.clone()),
+                    cash: cad(dec!(500)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+            Transaction::new(
+                account,
+                date!(2021, 1, 15),
+                TxnAction::Withdrawal {
+                    value: cad(dec!(200)),
+                    fee: cad(dec!(0)),
+                },
+            ),
+        ];
+
+        let res = Series::build(
+            &transactions,
+            date!(2021, 1, 1),
+            date!(2021, 1, 20),
+            Interval::Weekly,
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &exchange,
+        )?;
+        let dates: Vec<_> = res.points.iter().map(|x| x.date).collect();
+        assert_eq!(
+            vec![
+                date!(2021, 1, 1),
+                date!(2021, 1, 8),
+                date!(2021, 1, 15),
+                date!(2021, 1, 20)
+            ],
+            dates
+        );
+
+        assert_eq!(dec!(1000), res.points[0].value);
+        assert_eq!(dec!(1000), res.points[0].cash);
+        assert_eq!(dec!(0), res.points[0].gain);
+
+        assert_eq!(dec!(1000), res.points[1].value);
+        assert_eq!(dec!(500), res.points[1].cash);
+
+        // carried forward from the price on the 10th
+        assert_eq!(dec!(900), res.points[2].value);
+        assert_eq!(dec!(800), res.points[2].contribution);
+        assert_eq!(dec!(100), res.points[2].gain);
+        assert_eq!(res.points[2].value, res.points[3].value);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_accounts() -> Result<(), ServerError> {
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let exchange =
+            HistoryExchange(BTreeMap::from([(date!(2021, 1, 1), dec!(10))]));
+        let mut transactions = Vec::new();
+        for account in [Uuid::new_v4(), Uuid::new_v4()] {
+            transactions.push(Transaction::new(
+                account,
+                date!(2021, 1, 1),
+                TxnAction::Buy {
+                    asset: (dec!(100), xyz.clone()),
+                    cash: cad(dec!(1000)),
+                    fee: cad(dec!(0)),
+                },
+            ));
+            transactions.push(Transaction::new(
+                account,
+                date!(2021, 1, 5),
+                TxnAction::Split {
+                    asset: xyz.clone(),
+                    ratio: dec!(2),
+                    cash: None,
+                },
+            ));
+        }
+
+        // each account splits its own shares only
+        let res = Series::build(
+            &transactions,
+            date!(2021, 1, 1),
+            date!(2021, 1, 5),
+            Interval::Monthly,
+            &AssetId::currency("CAD"),
+            &Splits::default(),
+            &exchange,
+        )?;
+        assert_eq!(2, res.points.len());
+        assert_eq!(dec!(0), res.points[0].value);
+        assert_eq!(dec!(2000), res.points[1].value);
+        assert_eq!(dec!(-2000), res.points[1].cash);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use crate::database::asset::{AssetId, AssetSplit};
+use crate::database::transaction::TxnAction;
+use crate::database::Transaction;
+use crate::error::ServerError;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// something changing the positions of a portfolio
+pub(super) enum Event<'a> {
+    Split {
+        date: NaiveDate,
+        asset: &'a AssetId,
+        ratio: Decimal,
+    },
+    // boxed to keep events small, most of them are borrowed
+    Transaction(Box<Cow<'a, Transaction>>),
+}
+
+impl Event<'_> {
+    pub(super) fn date(&self) -> NaiveDate {
+        match self {
+            Event::Split { date, .. } => *date,
+            Event::Transaction(txn) => txn.date,
+        }
+    }
+}
+
+// stock splits of assets, `ratio` new shares for every old share. share
+// counts are multiplied by the ratio while the cost stays the same.
+// fractional shares left by a reverse split stay in the position unless a
+// `Split` transaction records the cash paid in lieu of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Splits(HashMap<AssetId, Vec<(NaiveDate, Decimal)>>);
+
+impl Splits {
+    pub fn new(
+        mut splits: HashMap<AssetId, Vec<(NaiveDate, Decimal)>>,
+    ) -> Self {
+        splits.values_mut().for_each(|x| x.sort_by_key(|x| x.0));
+        Self(splits)
+    }
+
+    pub fn load<'a>(
+        assets: impl IntoIterator<Item = &'a AssetId>,
+        owner: Option<Uuid>,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<Self, ServerError> {
+        let mut splits = HashMap::new();
+        for asset in assets {
+            if matches!(asset, AssetId::CURRENCY(_)) {
+                continue;
+            }
+            let history: Vec<_> =
+                AssetSplit::history(asset, owner, transaction)?
+                    .into_iter()
+                    .map(|x| (x.date, x.ratio))
+                    .collect();
+            if !history.is_empty() {
+                splits.insert(asset.clone(), history);
+            }
+        }
+        Ok(Self(splits))
+    }
+
+    // splits of every asset appearing in `transactions`
+    pub fn by_transactions(
+        transactions: &[Transaction],
+        owner: Option<Uuid>,
+        transaction: &rusqlite::Transaction,
+    ) -> Result<Self, ServerError> {
+        let mut assets: Vec<_> = transactions
+            .iter()
+            .flat_map(|txn| txn.action.assets())
+            .collect();
+        assets.sort();
+        assets.dedup();
+        Self::load(&assets, owner, transaction)
+    }
+
+    fn contains(&self, asset: &AssetId, date: NaiveDate) -> bool {
+        self.0
+            .get(asset)
+            .is_some_and(|x| x.iter().any(|x| x.0 == date))
+    }
+
+    // none for a `Split` transaction applied by the history already but for
+    // its cash in lieu
+    fn event<'a>(&self, txn: &'a Transaction) -> Option<Event<'a>> {
+        let txn = match &txn.action {
+            TxnAction::Split { asset, cash, .. }
+                if self.contains(asset, txn.date) =>
+            {
+                let mut txn = txn.clone();
+                txn.action = TxnAction::Split {
+                    asset: asset.clone(),
+                    ratio: Decimal::ONE,
+                    cash: Some(cash.clone()?),
+                };
+                Cow::Owned(txn)
+            }
+            _ => Cow::Borrowed(txn),
+        };
+        Some(Event::Transaction(Box::new(txn)))
+    }
+
+    // merges splits into `transactions` sorted by date. a split takes effect
+    // before the transactions of the same day. when the split of a `Split`
+    // transaction is known already, the shares of its account are split by
+    // the history like those of every other account, only its cash in lieu
+    // is kept.
+    pub(super) fn timeline<'a>(
+        &'a self,
+        transactions: impl IntoIterator<Item = &'a Transaction>,
+    ) -> Vec<Event<'a>> {
+        let mut events: Vec<_> = self
+            .0
+            .iter()
+            .flat_map(|(asset, splits)| {
+                splits.iter().map(move |(date, ratio)| Event::Split {
+                    date: *date,
+                    asset,
+                    ratio: *ratio,
+                })
+            })
+            .chain(transactions.into_iter().filter_map(|x| self.event(x)))
+            .collect();
+        events.sort_by_key(|x| (x.date(), matches!(x, Event::Transaction(_))));
+        events
+    }
+}
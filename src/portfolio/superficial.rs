@@ -0,0 +1,315 @@
+use super::split::Event as Change;
+use super::Holding;
+use crate::database::asset::AssetId;
+use crate::database::transaction::TxnAction;
+use chrono::{Days, NaiveDate};
+use rust_decimal::Decimal;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+// the period starts 30 days before and ends 30 days after the disposition
+const WINDOW: Days = Days::new(30);
+
+struct Event {
+    date: NaiveDate,
+    quantity: Decimal,
+    registered: bool,
+    acquisition: bool,
+}
+
+// changes of the quantity held of every asset across all accounts of a
+// user, registered ones included, used to detect superficial losses.
+pub(super) struct SuperficialLoss {
+    events: HashMap<AssetId, Vec<Event>>,
+}
+
+impl SuperficialLoss {
+    // `changes` must be sorted by date
+    pub(super) fn new(changes: &[Change], registered: &HashSet<Uuid>) -> Self {
+        let mut events = HashMap::<AssetId, Vec<Event>>::new();
+        let mut holdings = HashMap::<Uuid, Holding>::new();
+
+        for change in changes {
+            let txn = match change {
+                Change::Split { date, asset, ratio } => {
+                    // share counts change while nothing is acquired
+                    for (account, holding) in holdings.iter_mut() {
+                        let quantity = holding.quantity(asset);
+                        holding.split(asset, *ratio);
+                        let delta = holding.quantity(asset) - quantity;
+                        if !delta.is_zero() {
+                            events.entry((*asset).clone()).or_default().push(
+                                Event {
+                                    date: *date,
+                                    quantity: delta,
+                                    registered: registered.contains(account),
+                                    acquisition: false,
+                                },
+                            );
+                        }
+                    }
+                    continue;
+                }
+                Change::Transaction(txn) => txn,
+            };
+            let holding = holdings.entry(txn.account).or_default();
+            let mut changes = Vec::new();
+            match &txn.action {
+                TxnAction::Buy { asset, .. }
+                | TxnAction::ReinvestedDividend { asset, .. } => {
+                    changes.push((asset.1.clone(), asset.0, true))
+                }
+                TxnAction::Sell { asset, .. } => {
+                    changes.push((asset.1.clone(), -asset.0, false))
+                }
+                TxnAction::Deposit { value, .. } => {
+                    changes.push((value.1.clone(), value.0, false))
+                }
+                TxnAction::Withdrawal { value, .. } => {
+                    changes.push((value.1.clone(), -value.0, false))
+                }
+                TxnAction::TransferOut { asset, .. } => {
+                    changes.push((asset.1.clone(), -asset.0, false))
+                }
+                TxnAction::TransferIn { asset, .. } => {
+                    changes.push((asset.1.clone(), asset.0, false))
+                }
+                TxnAction::Journal { source, target, .. } => {
+                    let quantity = holding.quantity(source);
+                    changes.push((source.clone(), -quantity, false));
+                    changes.push((target.clone(), quantity, false));
+                }
+                // nothing is bought by corporate actions
+                TxnAction::Split { asset, ratio, cash } => {
+                    let quantity = holding.quantity(asset);
+                    let mut split = quantity * ratio;
+                    if cash.is_some() {
+                        split -= split.fract();
+                    }
+                    changes.push((asset.clone(), split - quantity, false));
+                }
+                TxnAction::Merger {
+                    source,
+                    target,
+                    ratio,
+                    ..
+                } => {
+                    let quantity = holding.quantity(source);
+                    changes.push((source.clone(), -quantity, false));
+                    changes.push((target.clone(), quantity * ratio, false));
+                }
+                TxnAction::SpinOff { target, .. } => {
+                    changes.push((target.1.clone(), target.0, false))
+                }
+                _ => (),
+            }
+            holding.apply(&txn.action);
+
+            changes
+                .into_iter()
+                .filter(|(asset, quantity, _)| {
+                    !matches!(asset, AssetId::CURRENCY(_))
+                        && !quantity.is_zero()
+                })
+                .for_each(|(asset, quantity, acquisition)| {
+                    events.entry(asset).or_default().push(Event {
+                        date: txn.date,
+                        quantity,
+                        registered: registered.contains(&txn.account),
+                        acquisition,
+                    })
+                });
+        }
+
+        Self { events }
+    }
+
+    // for a loss realized by disposing `quantity` of `asset` on `date`,
+    // returns the fraction of the loss that is denied, and the fraction of
+    // the denied loss that goes to substituted property held in
+    // non-registered accounts.
+    pub(super) fn check(
+        &self,
+        asset: &AssetId,
+        date: NaiveDate,
+        quantity: Decimal,
+    ) -> Option<(Decimal, Decimal)> {
+        let events = self.events.get(asset)?;
+        let start = date - WINDOW;
+        let end = date + WINDOW;
+
+        let acquired: Decimal = events
+            .iter()
+            .filter(|x| x.acquisition && x.date >= start && x.date <= end)
+            .map(|x| x.quantity)
+            .sum();
+        let (held, held_registered) = events
+            .iter()
+            .filter(|x| x.date <= end)
+            .fold((Decimal::ZERO, Decimal::ZERO), |(all, reg), x| {
+                if x.registered {
+                    (all + x.quantity, reg + x.quantity)
+                } else {
+                    (all + x.quantity, reg)
+                }
+            });
+
+        let substituted = quantity.min(acquired).min(held);
+        if quantity <= Decimal::ZERO || substituted <= Decimal::ZERO {
+            return None;
+        }
+
+        let denied = substituted / quantity;
+        let unregistered = ((held - held_registered) / held)
+            .max(Decimal::ZERO)
+            .min(Decimal::ONE);
+        Some((denied, unregistered))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Transaction;
+    use crate::portfolio::Splits;
+    use rust_decimal_macros::dec;
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    fn buy(account: Uuid, date: NaiveDate, quantity: Decimal) -> Transaction {
+        Transaction::new(
+            account,
+            date,
+            TxnAction::Buy {
+                asset: (quantity, AssetId::stock("TSE", "XYZ")),
+                cash: (quantity * dec!(10), AssetId::currency("CAD")),
+                fee: (dec!(0), AssetId::currency("CAD")),
+            },
+        )
+    }
+
+    fn sell(account: Uuid, date: NaiveDate, quantity: Decimal) -> Transaction {
+        Transaction::new(
+            account,
+            date,
+            TxnAction::Sell {
+                asset: (quantity, AssetId::stock("TSE", "XYZ")),
+                cash: (quantity * dec!(8), AssetId::currency("CAD")),
+                fee: (dec!(0), AssetId::currency("CAD")),
+            },
+        )
+    }
+
+    fn check(
+        transactions: &[Transaction],
+        registered: &HashSet<Uuid>,
+        date: NaiveDate,
+        quantity: Decimal,
+    ) -> Option<(Decimal, Decimal)> {
+        let splits = Splits::default();
+        let changes = splits.timeline(transactions);
+        SuperficialLoss::new(&changes, registered).check(
+            &AssetId::stock("TSE", "XYZ"),
+            date,
+            quantity,
+        )
+    }
+
+    #[test]
+    fn test_window_after() {
+        let a0 = Uuid::new_v4();
+        let none = HashSet::new();
+
+        // the 30th day after the disposition is still in the period
+        let txns = [
+            buy(a0, date!(2020, 1, 1), dec!(100)),
+            sell(a0, date!(2020, 3, 1), dec!(100)),
+            buy(a0, date!(2020, 3, 31), dec!(100)),
+        ];
+        assert_eq!(
+            Some((dec!(1), dec!(1))),
+            check(&txns, &none, date!(2020, 3, 1), dec!(100))
+        );
+
+        // the 31st day is not
+        let txns = [
+            buy(a0, date!(2020, 1, 1), dec!(100)),
+            sell(a0, date!(2020, 3, 1), dec!(100)),
+            buy(a0, date!(2020, 4, 1), dec!(100)),
+        ];
+        assert_eq!(None, check(&txns, &none, date!(2020, 3, 1), dec!(100)));
+    }
+
+    #[test]
+    fn test_window_before() {
+        let a0 = Uuid::new_v4();
+        let none = HashSet::new();
+
+        // the 30th day before the disposition is still in the period
+        let txns = [
+            buy(a0, date!(2020, 1, 1), dec!(100)),
+            buy(a0, date!(2020, 1, 31), dec!(100)),
+            sell(a0, date!(2020, 3, 1), dec!(100)),
+        ];
+        assert_eq!(
+            Some((dec!(1), dec!(1))),
+            check(&txns, &none, date!(2020, 3, 1), dec!(100))
+        );
+
+        // the 31st day is not
+        let txns = [
+            buy(a0, date!(2020, 1, 1), dec!(100)),
+            buy(a0, date!(2020, 1, 30), dec!(100)),
+            sell(a0, date!(2020, 3, 1), dec!(100)),
+        ];
+        assert_eq!(None, check(&txns, &none, date!(2020, 3, 1), dec!(100)));
+    }
+
+    #[test]
+    fn test_not_held_at_end() {
+        let a0 = Uuid::new_v4();
+        let none = HashSet::new();
+
+        // substituted property sold again on the last day of the period
+        let txns = [
+            buy(a0, date!(2020, 1, 1), dec!(100)),
+            sell(a0, date!(2020, 3, 1), dec!(100)),
+            buy(a0, date!(2020, 3, 10), dec!(100)),
+            sell(a0, date!(2020, 3, 31), dec!(100)),
+        ];
+        assert_eq!(None, check(&txns, &none, date!(2020, 3, 1), dec!(100)));
+
+        // sold the day after the period ends
+        let txns = [
+            buy(a0, date!(2020, 1, 1), dec!(100)),
+            sell(a0, date!(2020, 3, 1), dec!(100)),
+            buy(a0, date!(2020, 3, 10), dec!(100)),
+            sell(a0, date!(2020, 4, 1), dec!(100)),
+        ];
+        assert_eq!(
+            Some((dec!(1), dec!(1))),
+            check(&txns, &none, date!(2020, 3, 1), dec!(100))
+        );
+    }
+
+    #[test]
+    fn test_partial_and_registered() {
+        let (a0, a1) = (Uuid::new_v4(), Uuid::new_v4());
+        let registered = HashSet::from([a1]);
+
+        let txns = [
+            buy(a0, date!(2020, 1, 1), dec!(100)),
+            sell(a0, date!(2020, 3, 1), dec!(100)),
+            buy(a0, date!(2020, 3, 2), dec!(10)),
+            buy(a1, date!(2020, 3, 2), dec!(30)),
+        ];
+        assert_eq!(
+            Some((dec!(0.4), dec!(0.25))),
+            check(&txns, &registered, date!(2020, 3, 1), dec!(100))
+        );
+    }
+}
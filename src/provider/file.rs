@@ -0,0 +1,97 @@
+use super::{Dividend, Price, Provider, Split};
+use crate::database::asset::AssetId;
+use crate::error::ServerError;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct History {
+    #[serde(default)]
+    pub prices: Vec<Price>,
+    #[serde(default)]
+    pub dividends: Vec<Dividend>,
+    #[serde(default)]
+    pub splits: Vec<Split>,
+}
+
+// market data read from a json file mapping every asset to its history, for
+// tests and offline use.
+#[derive(Debug, Clone, Default)]
+pub struct FileProvider {
+    data: HashMap<AssetId, History>,
+}
+
+impl FileProvider {
+    pub fn new(data: HashMap<AssetId, History>) -> Self {
+        Self { data }
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ServerError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    fn history(&self, asset: &AssetId) -> Option<&History> {
+        self.data.get(asset)
+    }
+}
+
+impl FromStr for FileProvider {
+    type Err = ServerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(serde_json::from_str(s)?))
+    }
+}
+
+impl Provider for FileProvider {
+    fn prices(
+        &self,
+        asset: &AssetId,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Price>, ServerError> {
+        Ok(self
+            .history(asset)
+            .map(|x| &x.prices[..])
+            .unwrap_or_default()
+            .iter()
+            .filter(|x| x.date >= start && x.date <= end)
+            .cloned()
+            .collect())
+    }
+
+    fn dividends(
+        &self,
+        asset: &AssetId,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Dividend>, ServerError> {
+        Ok(self
+            .history(asset)
+            .map(|x| &x.dividends[..])
+            .unwrap_or_default()
+            .iter()
+            .filter(|x| x.date >= start && x.date <= end)
+            .cloned()
+            .collect())
+    }
+
+    fn splits(
+        &self,
+        asset: &AssetId,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Split>, ServerError> {
+        Ok(self
+            .history(asset)
+            .map(|x| &x.splits[..])
+            .unwrap_or_default()
+            .iter()
+            .filter(|x| x.date >= start && x.date <= end)
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,158 @@
+use super::{Dividend, Price, Provider, Split};
+use crate::database::asset::AssetId;
+use crate::error::ServerError;
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+// market data served over http as json arrays, e.g.
+// GET {base_url}/prices?asset=XTSE:XYZ&start=2021-01-01&end=2021-01-31
+pub struct HttpProvider {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl HttpProvider {
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().timeout(Self::TIMEOUT).build(),
+        }
+    }
+
+    fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        asset: &AssetId,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<T>, ServerError> {
+        self.agent
+            .get(&format!("{}/{}", self.base_url, path))
+            .query("asset", &String::from(asset.clone()))
+            .query("start", &start.to_string())
+            .query("end", &end.to_string())
+            .call()
+            .map_err(|e| ServerError::Internal(e.to_string()))?
+            .into_json()
+            .map_err(|e| e.into())
+    }
+}
+
+impl Provider for HttpProvider {
+    fn prices(
+        &self,
+        asset: &AssetId,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Price>, ServerError> {
+        self.get("prices", asset, start, end)
+    }
+
+    fn dividends(
+        &self,
+        asset: &AssetId,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Dividend>, ServerError> {
+        self.get("dividends", asset, start, end)
+    }
+
+    fn splits(
+        &self,
+        asset: &AssetId,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Split>, ServerError> {
+        self.get("splits", asset, start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    // answers a single request with `body`, returns the base url and the
+    // request line received.
+    fn serve(body: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            request
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_prices() -> Result<(), ServerError> {
+        let (url, handle) = serve(
+            r#"[{"date": "2021-01-04", "price": 10.5, "currency": "CURRENCY:CAD"}]"#,
+        );
+        let provider = HttpProvider::new(url);
+        let res = provider.prices(
+            &AssetId::stock("TSE", "XYZ"),
+            date!(2021, 1, 1),
+            date!(2021, 1, 31),
+        )?;
+        assert_eq!(
+            vec![Price {
+                date: date!(2021, 1, 4),
+                price: dec!(10.5),
+                currency: AssetId::currency("CAD"),
+            }],
+            res
+        );
+
+        let request = handle.join().unwrap();
+        assert!(request.starts_with(
+            "GET /prices?asset=XTSE%3AXYZ&start=2021-01-01&end=2021-01-31 "
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unreachable() {
+        // nothing listens on the port once the listener is dropped
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let provider = HttpProvider::new(url);
+        assert!(provider
+            .splits(
+                &AssetId::stock("TSE", "XYZ"),
+                date!(2021, 1, 1),
+                date!(2021, 1, 31)
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,222 @@
+mod file;
+mod http;
+mod scheduler;
+
+use crate::database::asset::{Asset, AssetId, AssetUpdateKind};
+use crate::error::ServerError;
+use chrono::{NaiveDate, Utc};
+pub use file::{FileProvider, History};
+pub use http::HttpProvider;
+use rust_decimal::Decimal;
+pub use scheduler::{status, Failure, Scheduler, Status};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Price {
+    pub date: NaiveDate,
+    pub price: Decimal,
+    pub currency: AssetId,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Dividend {
+    pub date: NaiveDate,
+    pub dividend: Decimal,
+    pub currency: AssetId,
+}
+
+// `ratio` new shares for every old share, as in `AssetSplit`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Split {
+    pub date: NaiveDate,
+    pub ratio: Decimal,
+}
+
+// source of market data, every method returns the records between `start`
+// and `end` inclusively.
+pub trait Provider {
+    fn prices(
+        &self,
+        asset: &AssetId,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Price>, ServerError>;
+
+    fn dividends(
+        &self,
+        asset: &AssetId,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Dividend>, ServerError>;
+
+    fn splits(
+        &self,
+        asset: &AssetId,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Split>, ServerError>;
+}
+
+// fetches `kind` data of `asset` between `start` and `end` from `provider`,
+// stores it and marks the asset as updated. returns the number of records
+// stored.
+pub fn refresh(
+    provider: &(impl Provider + ?Sized),
+    asset: &Asset,
+    kind: AssetUpdateKind,
+    start: NaiveDate,
+    end: NaiveDate,
+    transaction: &rusqlite::Transaction,
+) -> Result<usize, ServerError> {
+    let asset_id = &asset.asset_id;
+    let count = match kind {
+        AssetUpdateKind::Price => {
+            let data: Vec<_> = provider
+                .prices(asset_id, start, end)?
+                .into_iter()
+                .filter(|x| x.date >= start && x.date <= end)
+                .map(|x| (x.date, x.price, x.currency))
+                .collect();
+            asset.insert_price(&data, transaction)?;
+            data.len()
+        }
+        AssetUpdateKind::Dividend => {
+            let data: Vec<_> = provider
+                .dividends(asset_id, start, end)?
+                .into_iter()
+                .filter(|x| x.date >= start && x.date <= end)
+                .map(|x| (x.date, x.dividend, x.currency))
+                .collect();
+            asset.insert_dividend(&data, transaction)?;
+            data.len()
+        }
+        AssetUpdateKind::Split => {
+            let data: Vec<_> = provider
+                .splits(asset_id, start, end)?
+                .into_iter()
+                .filter(|x| x.date >= start && x.date <= end)
+                .map(|x| (x.date, x.ratio))
+                .collect();
+            asset.insert_split(&data, transaction)?;
+            data.len()
+        }
+    };
+
+    // the earlier data is kept unless there is a gap before it
+    let from = match asset.fetched_from(kind, transaction)? {
+        Some(x) if end < x => x,
+        Some(x) => x.min(start),
+        None => start,
+    };
+    asset.set_updated_at(kind, Utc::now(), transaction)?;
+    asset.set_fetched_from(kind, from, transaction)?;
+    Ok(count)
+}
+
+// prices, dividends and splits of `asset` between `start` and `end`
+pub fn sync(
+    provider: &(impl Provider + ?Sized),
+    asset: &Asset,
+    start: NaiveDate,
+    end: NaiveDate,
+    transaction: &rusqlite::Transaction,
+) -> Result<(), ServerError> {
+    for kind in [
+        AssetUpdateKind::Price,
+        AssetUpdateKind::Dividend,
+        AssetUpdateKind::Split,
+    ] {
+        refresh(provider, asset, kind, start, end, transaction)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database;
+    use chrono::DateTime;
+    use rusqlite::Connection;
+    use rust_decimal_macros::dec;
+
+    macro_rules! date {
+        ($y:expr, $m:expr, $d:expr) => {
+            NaiveDate::from_ymd_opt($y, $m, $d).unwrap()
+        };
+    }
+
+    #[test]
+    fn test_sync() -> Result<(), ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let cad = AssetId::currency("CAD");
+        let provider: FileProvider = r#"{
+            "XTSE:XYZ": {
+                "prices": [
+                    {"date": "2020-12-31", "price": 9, "currency": "CURRENCY:CAD"},
+                    {"date": "2021-01-04", "price": 10, "currency": "CURRENCY:CAD"},
+                    {"date": "2021-01-05", "price": 10.5, "currency": "CURRENCY:CAD"}
+                ],
+                "dividends": [
+                    {"date": "2021-01-05", "dividend": 0.25, "currency": "CURRENCY:CAD"}
+                ],
+                "splits": [{"date": "2021-01-05", "ratio": 2}]
+            }
+        }"#
+        .parse()?;
+
+        let tran = conn.transaction()?;
+        database::migration::run_migration(&tran)?;
+        let mut asset = Asset::new(xyz.clone(), "XYZ", None);
+        asset.id = asset.insert(&tran)?;
+        assert_eq!(
+            DateTime::<Utc>::MIN_UTC,
+            asset.updated_at(AssetUpdateKind::Price, &tran)?
+        );
+
+        sync(
+            &provider,
+            &asset,
+            date!(2021, 1, 1),
+            date!(2021, 1, 31),
+            &tran,
+        )?;
+
+        let prices = asset.prices(
+            date!(2020, 1, 1),
+            date!(2021, 12, 31),
+            None,
+            &tran,
+        )?;
+        assert_eq!(2, prices.len());
+        assert_eq!(dec!(10), prices[0].price);
+        assert_eq!(cad, prices[0].currency);
+        let splits = asset.splits(&tran)?;
+        assert_eq!(1, splits.len());
+        assert_eq!(dec!(2), splits[0].ratio);
+        for kind in [
+            AssetUpdateKind::Price,
+            AssetUpdateKind::Dividend,
+            AssetUpdateKind::Split,
+        ] {
+            assert!(asset.updated_at(kind, &tran)? > DateTime::<Utc>::MIN_UTC);
+        }
+
+        // an asset unknown to the provider has nothing to store
+        let mut abc = Asset::new(AssetId::stock("TSE", "ABC"), "ABC", None);
+        abc.id = abc.insert(&tran)?;
+        assert_eq!(
+            0,
+            refresh(
+                &provider,
+                &abc,
+                AssetUpdateKind::Price,
+                date!(2021, 1, 1),
+                date!(2021, 1, 31),
+                &tran
+            )?
+        );
+
+        Ok(())
+    }
+}
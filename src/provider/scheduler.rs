@@ -0,0 +1,393 @@
+use super::{refresh, FileProvider, HttpProvider, Provider};
+use crate::database::asset::{Asset, AssetId, AssetUpdateKind};
+use crate::database::get_connection;
+use crate::error::ServerError;
+use actix_web::rt::time;
+use actix_web::web;
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{error, info, warn};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+const INTERVAL: Duration = Duration::from_secs(15 * 60);
+const KINDS: [AssetUpdateKind; 3] = [
+    AssetUpdateKind::Price,
+    AssetUpdateKind::Dividend,
+    AssetUpdateKind::Split,
+];
+
+// how long fetched data stays fresh
+fn threshold(kind: AssetUpdateKind) -> TimeDelta {
+    match kind {
+        AssetUpdateKind::Price => TimeDelta::days(1),
+        AssetUpdateKind::Dividend | AssetUpdateKind::Split => {
+            TimeDelta::days(7)
+        }
+    }
+}
+
+// delay before the next attempt after `attempts` failures in a row,
+// doubling from 5 minutes up to a day.
+fn backoff(attempts: u32) -> TimeDelta {
+    let factor = 2_i32.pow(attempts.saturating_sub(1).min(10));
+    (TimeDelta::minutes(5) * factor).min(TimeDelta::days(1))
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Failure {
+    pub asset: AssetId,
+    pub kind: AssetUpdateKind,
+    pub attempts: u32,
+    pub retry_at: DateTime<Utc>,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct Status {
+    // false when no provider is configured
+    pub enabled: bool,
+    pub last_run: Option<DateTime<Utc>>,
+    // number of asset data refreshed by the last run
+    pub refreshed: usize,
+    pub failures: Vec<Failure>,
+}
+
+static STATUS: LazyLock<Mutex<Status>> = LazyLock::new(Default::default);
+
+pub fn status() -> Status {
+    STATUS.lock().map(|x| x.clone()).unwrap_or_default()
+}
+
+// keeps market data of every traded asset fresh
+pub struct Scheduler {
+    provider: Box<dyn Provider + Send>,
+    failures: HashMap<(Uuid, AssetUpdateKind), Failure>,
+}
+
+impl Scheduler {
+    pub fn new(provider: Box<dyn Provider + Send>) -> Self {
+        Self {
+            provider,
+            failures: HashMap::new(),
+        }
+    }
+
+    // the provider set by FLEXFOLIO_PROVIDER_URL, or FLEXFOLIO_PROVIDER_FILE
+    // for offline use. none if neither is set.
+    pub fn from_env() -> Result<Option<Self>, ServerError> {
+        if let Ok(url) = std::env::var("FLEXFOLIO_PROVIDER_URL") {
+            return Ok(Some(Self::new(Box::new(HttpProvider::new(url)))));
+        }
+        if let Ok(path) = std::env::var("FLEXFOLIO_PROVIDER_FILE") {
+            return Ok(Some(Self::new(Box::new(FileProvider::open(path)?))));
+        }
+        Ok(None)
+    }
+
+    // refreshes stale data, fetching only what is missing since the last
+    // update. returns the number of asset data refreshed.
+    pub fn run(
+        &mut self,
+        connection: &mut Connection,
+        now: DateTime<Utc>,
+    ) -> Result<usize, ServerError> {
+        let assets = {
+            let tran = connection.transaction()?;
+            // assets without data to fetch are left out
+            let assets: Vec<_> = Asset::traded(&tran)?
+                .into_iter()
+                .filter(|x| !matches!(x.0.asset_id, AssetId::UNKNOWN(_)))
+                .collect();
+            tran.commit()?;
+            assets
+        };
+
+        let mut refreshed = 0;
+        for (asset, first) in assets {
+            for kind in KINDS {
+                let key = (asset.id, kind);
+                if self.failures.get(&key).is_some_and(|x| x.retry_at > now) {
+                    continue;
+                }
+
+                let tran = connection.transaction()?;
+                let updated_at = asset.updated_at(kind, &tran)?;
+                // an older transaction may have been recorded since
+                let missing =
+                    asset.fetched_from(kind, &tran)?.is_none_or(|x| first < x);
+                if !missing && now - updated_at < threshold(kind) {
+                    continue;
+                }
+                // the day of the last update again, it may have been partial
+                let start = if missing {
+                    first
+                } else {
+                    updated_at.date_naive().max(first)
+                };
+
+                let end = now.date_naive();
+                match refresh(&*self.provider, &asset, kind, start, end, &tran)
+                {
+                    Ok(_) => {
+                        tran.commit()?;
+                        self.failures.remove(&key);
+                        refreshed += 1;
+                    }
+                    Err(e) => {
+                        let attempts = self
+                            .failures
+                            .get(&key)
+                            .map_or(1, |x| x.attempts + 1);
+                        warn!(
+                            "Fail to refresh {:?} of {} with error: {}",
+                            kind,
+                            String::from(asset.asset_id.clone()),
+                            e
+                        );
+                        self.failures.insert(
+                            key,
+                            Failure {
+                                asset: asset.asset_id.clone(),
+                                kind,
+                                attempts,
+                                retry_at: now + backoff(attempts),
+                                error: e.to_string(),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+
+        if let Ok(mut status) = STATUS.lock() {
+            *status = Status {
+                enabled: true,
+                last_run: Some(now),
+                refreshed,
+                failures: self.failures.values().cloned().collect(),
+            };
+        }
+        Ok(refreshed)
+    }
+
+    // runs forever on the blocking thread pool every `INTERVAL`
+    pub async fn start(mut self) {
+        if let Ok(mut status) = STATUS.lock() {
+            status.enabled = true;
+        }
+
+        let mut interval = time::interval(INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = web::block(move || {
+                let result = get_connection()
+                    .map_err(ServerError::from)
+                    .and_then(|mut conn| self.run(&mut conn, Utc::now()));
+                (self, result)
+            })
+            .await;
+
+            self = match result {
+                Ok((scheduler, Ok(refreshed))) => {
+                    info!("Refreshed {} asset data", refreshed);
+                    scheduler
+                }
+                Ok((scheduler, Err(e))) => {
+                    error!("Fail to refresh asset data with error: {}", e);
+                    scheduler
+                }
+                Err(e) => {
+                    error!("Market data scheduler stopped: {}", e);
+                    return;
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::account::AccountKind;
+    use crate::database::transaction::TxnAction;
+    use crate::database::{self, Account, Transaction, User};
+    use crate::provider::{Dividend, History, Price, Split};
+    use chrono::{Days, NaiveDate};
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+    use sha2::{Digest, Sha256};
+
+    struct FailingProvider;
+
+    impl Provider for FailingProvider {
+        fn prices(
+            &self,
+            _: &AssetId,
+            _: NaiveDate,
+            _: NaiveDate,
+        ) -> Result<Vec<Price>, ServerError> {
+            Err(ServerError::Internal(String::from("unavailable")))
+        }
+
+        fn dividends(
+            &self,
+            _: &AssetId,
+            _: NaiveDate,
+            _: NaiveDate,
+        ) -> Result<Vec<Dividend>, ServerError> {
+            Ok(Vec::new())
+        }
+
+        fn splits(
+            &self,
+            _: &AssetId,
+            _: NaiveDate,
+            _: NaiveDate,
+        ) -> Result<Vec<Split>, ServerError> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn cad(value: Decimal) -> (Decimal, AssetId) {
+        (value, AssetId::currency("CAD"))
+    }
+
+    fn setup(today: NaiveDate) -> Result<Connection, ServerError> {
+        let mut conn = Connection::open_in_memory()?;
+        let tran = conn.transaction()?;
+        database::migration::run_migration(&tran)?;
+        let mut u0 = User::new(
+            String::from("test_user"),
+            Sha256::digest("password").to_vec(),
+        );
+        u0.id = u0.insert(&tran)?;
+        let mut a0 =
+            Account::new("test_account", "alias", u0.id, AccountKind::NRA);
+        a0.id = a0.insert(&tran)?;
+        Asset::new(AssetId::stock("TSE", "XYZ"), "XYZ", None).insert(&tran)?;
+        Asset::new(AssetId::currency("CAD"), "CAD", None).insert(&tran)?;
+        Asset::new(AssetId::stock("TSE", "ABC"), "ABC", Some(u0.id))
+            .insert(&tran)?;
+        for asset in
+            [AssetId::stock("TSE", "XYZ"), AssetId::stock("TSE", "ABC")]
+        {
+            Transaction::new(
+                a0.id,
+                today - Days::new(10),
+                TxnAction::Buy {
+                    asset: (dec!(10), asset),
+                    cash: cad(dec!(100)),
+                    fee: cad(dec!(0)),
+                },
+            )
+            .insert(&tran)?;
+        }
+        tran.commit()?;
+        Ok(conn)
+    }
+
+    #[test]
+    fn test_run() -> Result<(), ServerError> {
+        let now = Utc::now();
+        let today = now.date_naive();
+        let xyz = AssetId::stock("TSE", "XYZ");
+        let mut conn = setup(today)?;
+        let provider = FileProvider::new(HashMap::from([(
+            xyz.clone(),
+            History {
+                prices: vec![
+                    Price {
+                        date: today - Days::new(20),
+                        price: dec!(9),
+                        currency: AssetId::currency("CAD"),
+                    },
+                    Price {
+                        date: today - Days::new(10),
+                        price: dec!(10),
+                        currency: AssetId::currency("CAD"),
+                    },
+                ],
+                ..Default::default()
+            },
+        )]));
+
+        // every kind of XYZ and CAD, private assets are left out
+        let mut scheduler = Scheduler::new(Box::new(provider));
+        assert_eq!(6, scheduler.run(&mut conn, now)?);
+        {
+            let tran = conn.transaction()?;
+            let abc = AssetId::stock("TSE", "ABC");
+            assert!(Asset::by_asset(abc, None, &tran)?.is_none());
+            let asset =
+                Asset::by_asset(xyz.clone(), None, &tran)?.expect("no asset");
+            let prices =
+                asset.prices(today - Days::new(30), today, None, &tran)?;
+            // nothing before the first transaction
+            assert_eq!(1, prices.len());
+            assert_eq!(dec!(10), prices[0].price);
+        }
+
+        // still fresh, then prices only
+        assert_eq!(0, scheduler.run(&mut conn, now + TimeDelta::hours(1))?);
+        assert_eq!(2, scheduler.run(&mut conn, now + TimeDelta::days(2))?);
+
+        // an older transaction is fetched for even when the data is fresh
+        {
+            let tran = conn.transaction()?;
+            let account: Uuid =
+                tran.query_row("SELECT id FROM account", [], |row| row.get(0))?;
+            Transaction::new(
+                account,
+                today - Days::new(25),
+                TxnAction::Buy {
+                    asset: (dec!(10), xyz.clone()),
+                    cash: cad(dec!(90)),
+                    fee: cad(dec!(0)),
+                },
+            )
+            .insert(&tran)?;
+            tran.commit()?;
+        }
+        assert_eq!(6, scheduler.run(&mut conn, now + TimeDelta::hours(2))?);
+        assert_eq!(0, scheduler.run(&mut conn, now + TimeDelta::hours(3))?);
+        {
+            let tran = conn.transaction()?;
+            let asset =
+                Asset::by_asset(xyz.clone(), None, &tran)?.expect("no asset");
+            let prices =
+                asset.prices(today - Days::new(30), today, None, &tran)?;
+            assert_eq!(2, prices.len());
+            assert_eq!(dec!(9), prices[0].price);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_backoff() -> Result<(), ServerError> {
+        let now = Utc::now();
+        let mut conn = setup(now.date_naive())?;
+
+        let mut scheduler = Scheduler::new(Box::new(FailingProvider));
+        assert_eq!(4, scheduler.run(&mut conn, now)?);
+        assert_eq!(2, scheduler.failures.len());
+        assert!(scheduler.failures.values().all(|x| x.attempts == 1));
+
+        // not retried before the delay is over
+        scheduler.run(&mut conn, now + TimeDelta::minutes(1))?;
+        assert!(scheduler.failures.values().all(|x| x.attempts == 1));
+        scheduler.run(&mut conn, now + TimeDelta::minutes(5))?;
+        assert!(scheduler.failures.values().all(|x| x.attempts == 2));
+        assert!(scheduler
+            .failures
+            .values()
+            .all(|x| x.retry_at == now + TimeDelta::minutes(15)));
+
+        assert_eq!(TimeDelta::days(1), backoff(20));
+        Ok(())
+    }
+}
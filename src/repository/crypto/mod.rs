@@ -0,0 +1,43 @@
+use super::{AssetInfo, AssetKind, IRepository};
+use crate::database::asset::AssetId;
+use crate::error::ServerError;
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+#[derive(Deserialize)]
+struct Datum {
+    name: String,
+    symbol: String,
+}
+
+static DATA: LazyLock<Vec<Datum>> = LazyLock::new(|| {
+    let data: Vec<Datum> =
+        serde_json::from_str(include_str!("crypto.json")).unwrap();
+    data
+});
+
+pub struct Repository;
+
+impl IRepository for Repository {
+    async fn search(
+        &self,
+        _: uuid::Uuid,
+        query: String,
+        kinds: Vec<AssetKind>,
+    ) -> Result<Vec<AssetInfo>, ServerError> {
+        if !kinds.contains(&AssetKind::Crypto) {
+            return Ok(Vec::new());
+        }
+
+        let result = DATA
+            .iter()
+            .map(|x| AssetInfo {
+                id: AssetId::CRYPTO(x.symbol.clone()),
+                name: x.name.clone(),
+                kind: AssetKind::Crypto,
+            })
+            .filter(|x| x.rank(&query).is_some())
+            .collect();
+        Ok(result)
+    }
+}
@@ -16,27 +16,32 @@ static DATA: LazyLock<Vec<Datum>> = LazyLock::new(|| {
     data
 });
 
+// whether `symbol` is a known ISO 4217 currency code
+pub fn exists(symbol: &str) -> bool {
+    DATA.iter().any(|x| x.symbol == symbol)
+}
+
 pub struct Repository;
 
 impl IRepository for Repository {
     async fn search(
         &self,
         _: uuid::Uuid,
-        prefix: String,
+        query: String,
         kinds: Vec<AssetKind>,
     ) -> Result<Vec<AssetInfo>, ServerError> {
-        if kinds != vec![AssetKind::Currency] {
-            return Err(ServerError::Internal(String::from("Unsupported")));
+        if !kinds.contains(&AssetKind::Currency) {
+            return Ok(Vec::new());
         }
 
         let result = DATA
             .iter()
-            .filter(|x| x.symbol.starts_with(&prefix))
             .map(|x| AssetInfo {
                 id: AssetId::CURRENCY(x.symbol.clone()),
                 name: x.name.clone(),
                 kind: AssetKind::Currency,
             })
+            .filter(|x| x.rank(&query).is_some())
             .collect();
         Ok(result)
     }
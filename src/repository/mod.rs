@@ -1,8 +1,12 @@
-mod currency;
+mod crypto;
+pub(crate) mod currency;
+mod private;
+pub(crate) mod security;
 
 use crate::database::asset::AssetId;
 use crate::error::ServerError;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -14,10 +18,51 @@ pub enum AssetKind {
     Unknown,
 }
 
+impl AssetKind {
+    pub const ALL: [AssetKind; 5] = [
+        AssetKind::Currency,
+        AssetKind::Stock,
+        AssetKind::ETF,
+        AssetKind::Crypto,
+        AssetKind::Unknown,
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct AssetInfo {
-    id: AssetId,
-    name: String,
-    kind: AssetKind,
+    pub id: AssetId,
+    pub name: String,
+    pub kind: AssetKind,
+}
+
+impl AssetInfo {
+    // how well the asset matches `query`, lower is better. none if it does
+    // not match at all.
+    fn rank(&self, query: &str) -> Option<u8> {
+        let symbol = match &self.id {
+            AssetId::STOCK { ticker, .. } => ticker,
+            AssetId::CURRENCY(x) | AssetId::CRYPTO(x) | AssetId::UNKNOWN(x) => {
+                x
+            }
+        }
+        .to_uppercase();
+        let name = self.name.to_uppercase();
+        let query = query.trim().to_uppercase();
+
+        if symbol == query {
+            Some(0)
+        } else if symbol.starts_with(&query) {
+            Some(1)
+        } else if name.starts_with(&query) {
+            Some(2)
+        } else if symbol.contains(&query) {
+            Some(3)
+        } else if name.contains(&query) {
+            Some(4)
+        } else {
+            None
+        }
+    }
 }
 
 pub trait IRepository {
@@ -31,22 +76,128 @@ pub trait IRepository {
     }
 }
 
+// every catalog merged, the private assets of the user come first when the
+// same asset is found more than once.
 pub struct Repository;
 
-impl IRepository for Repository {
+impl Repository {
+    const LIMIT: usize = 20;
+}
 
+impl IRepository for Repository {
     async fn search(
         &self,
         user_id: Uuid,
-        prefix: String,
+        query: String,
         kinds: Vec<AssetKind>,
     ) -> Result<Vec<AssetInfo>, ServerError> {
-        let repo = currency::Repository;
-        match repo.search(user_id, prefix, kinds).await {
-            Ok(result) => return Ok(result),
-            _ => (),
-        }
+        let mut result = Vec::new();
+        result.extend(
+            private::Repository
+                .search(user_id, query.clone(), kinds.clone())
+                .await?,
+        );
+        result.extend(
+            currency::Repository
+                .search(user_id, query.clone(), kinds.clone())
+                .await?,
+        );
+        result.extend(
+            security::Repository
+                .search(user_id, query.clone(), kinds.clone())
+                .await?,
+        );
+        result.extend(
+            crypto::Repository
+                .search(user_id, query.clone(), kinds.clone())
+                .await?,
+        );
+
+        Ok(merge(&query, result))
+    }
+}
+
+// drops duplicates and the ones not matching `query`, best matches first
+fn merge(query: &str, found: Vec<AssetInfo>) -> Vec<AssetInfo> {
+    let mut seen = HashSet::new();
+    let mut result: Vec<_> = found
+        .into_iter()
+        .filter(|x| seen.insert(x.id.clone()))
+        .filter_map(|x| Some((x.rank(query)?, x)))
+        .collect();
+    // stable, ties keep the order of the catalogs
+    result.sort_by_key(|x| x.0);
+    result
+        .into_iter()
+        .map(|x| x.1)
+        .take(Repository::LIMIT)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the bundled catalogs only, the private one needs a database
+    async fn search(
+        query: &str,
+        kinds: &[AssetKind],
+    ) -> Result<Vec<AssetId>, ServerError> {
+        let mut found = currency::Repository
+            .search(Uuid::nil(), query.to_string(), kinds.to_vec())
+            .await?;
+        found.extend(
+            security::Repository
+                .search(Uuid::nil(), query.to_string(), kinds.to_vec())
+                .await?,
+        );
+        found.extend(
+            crypto::Repository
+                .search(Uuid::nil(), query.to_string(), kinds.to_vec())
+                .await?,
+        );
+        Ok(merge(query, found).into_iter().map(|x| x.id).collect())
+    }
+
+    #[actix_web::test]
+    async fn test_search() -> Result<(), ServerError> {
+        let res = search("cad", &[AssetKind::Currency]).await?;
+        assert_eq!(AssetId::currency("CAD"), res[0]);
+
+        // exact symbols first, then symbol prefixes, then names
+        let res = search("RY", &AssetKind::ALL).await?;
+        assert_eq!(AssetId::stock("TSE", "RY"), res[0]);
+        assert_eq!(AssetId::stock("NYSE", "RY"), res[1]);
+        assert!(!res.contains(&AssetId::currency("CAD")));
+
+        let res = search("bitcoin", &[AssetKind::Crypto]).await?;
+        assert_eq!(vec![AssetId::crypto("BTC"), AssetId::crypto("BCH")], res);
+
+        let res = search("vanguard", &[AssetKind::ETF]).await?;
+        assert!(res.contains(&AssetId::stock("TSE", "VEQT")));
+        assert!(!res.contains(&AssetId::stock("NASDAQ", "AAPL")));
+
+        Ok(())
+    }
 
-        todo!()
+    #[test]
+    fn test_merge() {
+        let info = |id: AssetId, name: &str| AssetInfo {
+            id,
+            name: String::from(name),
+            kind: AssetKind::Unknown,
+        };
+        let res = merge(
+            "td",
+            vec![
+                info(AssetId::unknown("TDB627"), "TD Dividend Income Fund"),
+                info(AssetId::stock("TSE", "TD"), "Toronto-Dominion Bank"),
+                info(AssetId::unknown("XYZ"), "Not matching"),
+                info(AssetId::unknown("TDB627"), "Duplicate"),
+            ],
+        );
+        assert_eq!(2, res.len());
+        assert_eq!(AssetId::stock("TSE", "TD"), res[0].id);
+        assert_eq!("TD Dividend Income Fund", res[1].name);
     }
 }
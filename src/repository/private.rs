@@ -0,0 +1,39 @@
+use super::{AssetInfo, AssetKind, IRepository};
+use crate::database::asset::{Asset, AssetId};
+use crate::database::get_connection;
+use crate::error::ServerError;
+use uuid::Uuid;
+
+// assets stored in the database, global ones and the ones owned by the user
+pub struct Repository;
+
+impl IRepository for Repository {
+    async fn search(
+        &self,
+        user_id: Uuid,
+        query: String,
+        kinds: Vec<AssetKind>,
+    ) -> Result<Vec<AssetInfo>, ServerError> {
+        let mut conn = get_connection()?;
+        let tran = conn.transaction()?;
+
+        let mut assets = Asset::by_owner(user_id, &tran)?;
+        assets.extend(Asset::search(query.clone(), user_id, &tran)?);
+
+        let result = assets
+            .into_iter()
+            .map(|x| AssetInfo {
+                kind: match x.asset_id {
+                    AssetId::STOCK { .. } => AssetKind::Stock,
+                    AssetId::CURRENCY(_) => AssetKind::Currency,
+                    AssetId::CRYPTO(_) => AssetKind::Crypto,
+                    AssetId::UNKNOWN(_) => AssetKind::Unknown,
+                },
+                id: x.asset_id,
+                name: x.name,
+            })
+            .filter(|x| kinds.contains(&x.kind) && x.rank(&query).is_some())
+            .collect();
+        Ok(result)
+    }
+}
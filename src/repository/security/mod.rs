@@ -0,0 +1,59 @@
+use super::{AssetInfo, AssetKind, IRepository};
+use crate::database::asset::AssetId;
+use crate::error::ServerError;
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+// listed on TSX, NYSE or NASDAQ
+#[derive(Deserialize)]
+struct Datum {
+    exchange: String,
+    ticker: String,
+    name: String,
+    kind: AssetKind,
+    #[serde(default)]
+    cusip: Option<String>,
+}
+
+static DATA: LazyLock<Vec<Datum>> = LazyLock::new(|| {
+    let data: Vec<Datum> =
+        serde_json::from_str(include_str!("security.json")).unwrap();
+    data
+});
+
+// the listing of `ticker`, the first one if listed on several exchanges
+pub(crate) fn find(ticker: &str) -> Option<AssetId> {
+    DATA.iter()
+        .find(|x| x.ticker == ticker)
+        .map(|x| AssetId::stock(&x.exchange, &x.ticker))
+}
+
+// the listing identified by `cusip`
+pub(crate) fn find_cusip(cusip: &str) -> Option<AssetId> {
+    DATA.iter()
+        .find(|x| x.cusip.as_deref() == Some(cusip))
+        .map(|x| AssetId::stock(&x.exchange, &x.ticker))
+}
+
+pub struct Repository;
+
+impl IRepository for Repository {
+    async fn search(
+        &self,
+        _: uuid::Uuid,
+        query: String,
+        kinds: Vec<AssetKind>,
+    ) -> Result<Vec<AssetInfo>, ServerError> {
+        let result = DATA
+            .iter()
+            .filter(|x| kinds.contains(&x.kind))
+            .map(|x| AssetInfo {
+                id: AssetId::stock(&x.exchange, &x.ticker),
+                name: x.name.clone(),
+                kind: x.kind,
+            })
+            .filter(|x| x.rank(&query).is_some())
+            .collect();
+        Ok(result)
+    }
+}
@@ -3,6 +3,7 @@ pub mod exist;
 pub mod login;
 pub mod register;
 pub mod rotate;
+pub mod settings;
 pub mod update;
 
 use hmac::{Hmac, Mac};
@@ -0,0 +1,27 @@
+use crate::database::{get_connection, Setting};
+use crate::error::ServerError;
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct RequestData {
+    token: String,
+}
+
+#[post("/api/user/settings/fetch")]
+pub async fn handler(
+    request: web::Json<RequestData>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    // permission check
+    let id = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+
+    let setting = Setting::by_user(id, &tran)?;
+    Ok(HttpResponse::Ok().json(setting))
+}
@@ -0,0 +1,58 @@
+use crate::database::asset::AssetId;
+use crate::database::setting::Display;
+use crate::database::{get_connection, Setting};
+use crate::error::ServerError;
+use crate::portfolio::Period;
+use crate::repository::currency;
+use crate::user::authenticate;
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct RequestData {
+    token: String,
+    base_currency: Option<AssetId>,
+    date_range: Option<Period>,
+    display: Option<Display>,
+}
+
+#[post("/api/user/settings/update")]
+pub async fn handler(
+    request: web::Json<RequestData>,
+) -> Result<impl Responder, ServerError> {
+    let mut conn = get_connection()?;
+    let tran = conn.transaction()?;
+
+    // permission check
+    let id = match authenticate(&request.token)? {
+        None => return Ok(HttpResponse::Forbidden().finish()),
+        Some(i) => i,
+    };
+    let mut setting = Setting::by_user(id, &tran)?;
+
+    // update values
+    if let Some(base_currency) = request.base_currency.clone() {
+        match &base_currency {
+            AssetId::CURRENCY(symbol) if currency::exists(symbol) => (),
+            _ => {
+                return Ok(
+                    HttpResponse::BadRequest().body("unknown base currency")
+                )
+            }
+        }
+        setting.base_currency = base_currency
+    }
+    if let Some(date_range) = request.date_range {
+        setting.date_range = date_range
+    }
+    if let Some(display) = request.display.clone() {
+        if display.decimals > 8 {
+            return Ok(HttpResponse::BadRequest().body("too many decimals"));
+        }
+        setting.display = display
+    }
+
+    setting.save(&tran)?;
+    tran.commit()?;
+    Ok(HttpResponse::Ok().json(setting))
+}